@@ -0,0 +1,535 @@
+//! Bearer-token and OIDC authentication for HTTPS clients
+//!
+//! LXD accepts an `Authorization: Bearer <token>` header in place of a TLS
+//! client certificate. The token is either a static bearer identity token
+//! issued by the server, or an OIDC access token obtained through the
+//! device-code flow and refreshed before it expires.
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// Grant type used when polling for a device-code token
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Callback invoked with the new tokens after a refresh
+pub type RefreshCallback = Arc<dyn Fn(&OidcTokens) + Send + Sync>;
+
+/// How the HTTPS transport authenticates without a client certificate
+#[derive(Clone)]
+pub enum BearerAuth {
+    /// A static bearer token, such as one issued for a bearer identity
+    Token(String),
+
+    /// OIDC tokens that are refreshed transparently before they expire
+    Oidc(OidcCredentials),
+}
+
+impl BearerAuth {
+    /// Use a static bearer token
+    pub fn token(token: impl Into<String>) -> Self {
+        Self::Token(token.into())
+    }
+
+    /// Get the token to send with the next request
+    pub(crate) async fn access_token(&self) -> Result<String> {
+        match self {
+            Self::Token(token) => Ok(token.clone()),
+            Self::Oidc(credentials) => credentials.access_token().await,
+        }
+    }
+}
+
+impl fmt::Debug for BearerAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(_) => f.debug_tuple("Token").field(&Redacted).finish(),
+            Self::Oidc(credentials) => f.debug_tuple("Oidc").field(credentials).finish(),
+        }
+    }
+}
+
+/// Endpoints and client settings of an OIDC identity provider
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// OAuth client ID configured on the LXD server (`oidc.client.id`)
+    pub client_id: String,
+    /// Device authorization endpoint
+    pub device_authorization_endpoint: String,
+    /// Token endpoint
+    pub token_endpoint: String,
+    /// Requested scopes
+    pub scopes: Vec<String>,
+    /// Audience to request, if the provider requires one (`oidc.audience`)
+    pub audience: Option<String>,
+}
+
+impl OidcConfig {
+    /// Create a configuration from explicit endpoints
+    pub fn new(
+        client_id: impl Into<String>,
+        device_authorization_endpoint: impl Into<String>,
+        token_endpoint: impl Into<String>,
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            device_authorization_endpoint: device_authorization_endpoint.into(),
+            token_endpoint: token_endpoint.into(),
+            scopes: vec![
+                "openid".to_string(),
+                "email".to_string(),
+                "profile".to_string(),
+                "offline_access".to_string(),
+            ],
+            audience: None,
+        }
+    }
+
+    /// Set the requested scopes
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Set the audience
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+}
+
+/// Pending device authorization returned by the identity provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceAuthorization {
+    /// Code used to poll the token endpoint
+    pub device_code: String,
+    /// Code the user enters at the verification URI
+    pub user_code: String,
+    /// Where the user completes the login
+    pub verification_uri: String,
+    /// Verification URI with the user code already filled in
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    /// Seconds until the device code expires
+    pub expires_in: u64,
+    /// Minimum seconds between token requests
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// Tokens issued by the identity provider
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct OidcTokens {
+    /// Access token sent to LXD
+    pub access_token: String,
+    /// Refresh token used to obtain a new access token
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// ID token
+    #[serde(default)]
+    pub id_token: Option<String>,
+    /// Expiry of the access token (Unix seconds), if known
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+impl OidcTokens {
+    /// Create tokens from an access token alone
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self {
+            access_token: access_token.into(),
+            refresh_token: None,
+            id_token: None,
+            expires_at: None,
+        }
+    }
+
+    /// Set the refresh token
+    pub fn with_refresh_token(mut self, refresh_token: impl Into<String>) -> Self {
+        self.refresh_token = Some(refresh_token.into());
+        self
+    }
+
+    /// Set the access token expiry (Unix seconds)
+    pub fn with_expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Whether the access token expires within `margin`
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now() + margin.as_secs() >= expires_at,
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for OidcTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OidcTokens")
+            .field("access_token", &Redacted)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| Redacted),
+            )
+            .field("id_token", &self.id_token.as_ref().map(|_| Redacted))
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Token endpoint success response
+/// Placeholder for a secret in `Debug` output
+struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    id_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+impl From<TokenResponse> for OidcTokens {
+    fn from(response: TokenResponse) -> Self {
+        Self {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            id_token: response.id_token,
+            expires_at: response.expires_in.map(|secs| now() + secs),
+        }
+    }
+}
+
+/// Token endpoint error response
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+impl TokenError {
+    fn into_error(self) -> Error {
+        match self.error_description {
+            Some(description) => Error::Auth(format!("{}: {}", self.error, description)),
+            None => Error::Auth(self.error),
+        }
+    }
+}
+
+/// Provider metadata from `/.well-known/openid-configuration`
+#[derive(Deserialize)]
+struct Discovery {
+    device_authorization_endpoint: Option<String>,
+    token_endpoint: String,
+}
+
+/// OAuth client for an OIDC identity provider
+#[derive(Debug, Clone)]
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+}
+
+impl OidcClient {
+    /// Create a client from explicit provider endpoints
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a client from the provider's discovery document
+    pub async fn discover(issuer: &str, client_id: impl Into<String>) -> Result<Self> {
+        let http = reqwest::Client::new();
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+        let response = http
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::Auth(format!(
+                "OIDC discovery failed with status {}",
+                response.status()
+            )));
+        }
+        let discovery: Discovery = response
+            .json()
+            .await
+            .map_err(|e| Error::Auth(format!("Invalid discovery document: {}", e)))?;
+        let device_authorization_endpoint =
+            discovery.device_authorization_endpoint.ok_or_else(|| {
+                Error::Auth("Provider does not support the device authorization grant".into())
+            })?;
+
+        Ok(Self {
+            config: OidcConfig::new(
+                client_id,
+                device_authorization_endpoint,
+                discovery.token_endpoint,
+            ),
+            http,
+        })
+    }
+
+    /// Get the client configuration
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Start the device-code flow
+    ///
+    /// Show the returned `verification_uri` and `user_code` to the user, then
+    /// call [`OidcClient::poll_device_flow`].
+    pub async fn start_device_flow(&self) -> Result<DeviceAuthorization> {
+        let scope = self.config.scopes.join(" ");
+        let mut form = vec![
+            ("client_id", self.config.client_id.as_str()),
+            ("scope", scope.as_str()),
+        ];
+        if let Some(audience) = &self.config.audience {
+            form.push(("audience", audience));
+        }
+
+        let response = self
+            .http
+            .post(&self.config.device_authorization_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Self::token_error(response).await);
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| Error::Auth(format!("Invalid device authorization response: {}", e)))
+    }
+
+    /// Poll the token endpoint until the user completes the login
+    pub async fn poll_device_flow(
+        &self,
+        authorization: &DeviceAuthorization,
+    ) -> Result<OidcTokens> {
+        let deadline = now() + authorization.expires_in;
+        let mut interval = authorization.interval;
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            let response = self
+                .http
+                .post(&self.config.token_endpoint)
+                .form(&[
+                    ("grant_type", DEVICE_CODE_GRANT),
+                    ("device_code", authorization.device_code.as_str()),
+                    ("client_id", self.config.client_id.as_str()),
+                ])
+                .send()
+                .await
+                .map_err(|e| Error::connection(e.to_string()))?;
+
+            if response.status().is_success() {
+                return Self::tokens(response).await;
+            }
+
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| Error::request(e.to_string()))?;
+            let error: TokenError = serde_json::from_slice(&body)
+                .map_err(|_| Error::Auth(String::from_utf8_lossy(&body).to_string()))?;
+            match error.error.as_str() {
+                "authorization_pending" => {}
+                "slow_down" => interval += 5,
+                _ => return Err(error.into_error()),
+            }
+
+            if now() >= deadline {
+                return Err(Error::Auth("Device code expired".into()));
+            }
+        }
+    }
+
+    /// Exchange a refresh token for new tokens
+    pub async fn refresh(&self, refresh_token: &str) -> Result<OidcTokens> {
+        let response = self
+            .http
+            .post(&self.config.token_endpoint)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+                ("client_id", self.config.client_id.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(Self::token_error(response).await);
+        }
+        let mut tokens = Self::tokens(response).await?;
+        // Providers may omit the refresh token when it is not rotated
+        if tokens.refresh_token.is_none() {
+            tokens.refresh_token = Some(refresh_token.to_string());
+        }
+        Ok(tokens)
+    }
+
+    async fn tokens(response: reqwest::Response) -> Result<OidcTokens> {
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::Auth(format!("Invalid token response: {}", e)))?;
+        Ok(tokens.into())
+    }
+
+    async fn token_error(response: reqwest::Response) -> Error {
+        let status = response.status();
+        match response.json::<TokenError>().await {
+            Ok(error) => error.into_error(),
+            Err(_) => Error::Auth(format!("Token request failed with status {}", status)),
+        }
+    }
+}
+
+/// OIDC tokens shared by every request made through a client
+///
+/// The access token is refreshed with the refresh token once it is within
+/// the refresh margin of its expiry (one minute by default).
+#[derive(Clone)]
+pub struct OidcCredentials {
+    client: OidcClient,
+    tokens: Arc<Mutex<OidcTokens>>,
+    refresh_margin: Duration,
+    on_refresh: Option<RefreshCallback>,
+}
+
+impl OidcCredentials {
+    /// Create credentials from previously obtained tokens
+    pub fn new(client: OidcClient, tokens: OidcTokens) -> Self {
+        Self {
+            client,
+            tokens: Arc::new(Mutex::new(tokens)),
+            refresh_margin: Duration::from_secs(60),
+            on_refresh: None,
+        }
+    }
+
+    /// Set how long before expiry the access token is refreshed
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Call `callback` with the new tokens after each refresh
+    ///
+    /// Use this to persist the rotated refresh token.
+    pub fn on_refresh<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&OidcTokens) + Send + Sync + 'static,
+    {
+        self.on_refresh = Some(Arc::new(callback));
+        self
+    }
+
+    /// Get a copy of the current tokens
+    pub async fn tokens(&self) -> OidcTokens {
+        self.tokens.lock().await.clone()
+    }
+
+    /// Get a valid access token, refreshing it first if needed
+    pub async fn access_token(&self) -> Result<String> {
+        let mut tokens = self.tokens.lock().await;
+        if tokens.expires_within(self.refresh_margin) {
+            let refresh_token = tokens.refresh_token.clone().ok_or_else(|| {
+                Error::Auth("Access token expired and no refresh token is available".into())
+            })?;
+            *tokens = self.client.refresh(&refresh_token).await?;
+            if let Some(callback) = &self.on_refresh {
+                callback(&tokens);
+            }
+        }
+        Ok(tokens.access_token.clone())
+    }
+}
+
+impl fmt::Debug for OidcCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OidcCredentials")
+            .field("client", &self.client)
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Current Unix time in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expires_within() {
+        let tokens = OidcTokens::new("a");
+        assert!(!tokens.expires_within(Duration::from_secs(60)));
+
+        let tokens = tokens.with_expires_at(now() + 30);
+        assert!(tokens.expires_within(Duration::from_secs(60)));
+        assert!(!tokens.expires_within(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_debug_redacts_tokens() {
+        let auth = BearerAuth::token("bearer-secret");
+        assert_eq!(format!("{:?}", auth), "Token(<redacted>)");
+
+        let tokens = OidcTokens::new("access-secret").with_refresh_token("refresh-secret");
+        let debug = format!("{:?}", tokens);
+        assert!(!debug.contains("secret"), "{}", debug);
+        assert!(
+            debug.contains("refresh_token: Some(<redacted>)"),
+            "{}",
+            debug
+        );
+    }
+
+    #[test]
+    fn test_token_response_sets_expiry() {
+        let response: TokenResponse =
+            serde_json::from_str(r#"{"access_token":"a","expires_in":300}"#).unwrap();
+        let tokens = OidcTokens::from(response);
+        let expires_at = tokens.expires_at.unwrap();
+        assert!(expires_at >= now() + 299 && expires_at <= now() + 300);
+    }
+}
@@ -0,0 +1,46 @@
+//! Instance backup import
+//!
+//! A backup tarball is restored by POSTing it to `/1.0/instances`, with the
+//! target pool and instance name in `X-LXD-*` headers.
+
+/// Where and under which name a backup is restored
+///
+/// Unset values are taken from the backup itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupImportOptions {
+    /// Storage pool to restore into
+    pub pool: Option<String>,
+    /// Name of the restored instance
+    pub name: Option<String>,
+}
+
+impl BackupImportOptions {
+    /// Create options that restore the backup as it was
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restore into `pool`
+    pub fn with_pool(mut self, pool: impl Into<String>) -> Self {
+        self.pool = Some(pool.into());
+        self
+    }
+
+    /// Restore under a different instance name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Build the `X-LXD-*` headers for the import request
+    pub(crate) fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(pool) = &self.pool {
+            headers.push(("X-LXD-pool".to_string(), pool.clone()));
+        }
+        if let Some(name) = &self.name {
+            headers.push(("X-LXD-name".to_string(), name.clone()));
+        }
+        headers
+    }
+}
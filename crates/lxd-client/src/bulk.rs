@@ -0,0 +1,124 @@
+//! State changes across many instances
+//!
+//! LXD can change the state of every instance in a project with a single
+//! `PUT /1.0/instances`. [`Client::change_instances_state`] is the
+//! client-side alternative for a subset of instances: it calls
+//! `PUT /1.0/instances/{name}/state` for each one, a few at a time.
+
+use crate::{Client, Result};
+use futures::StreamExt;
+use lxd_types::{Instance, InstanceStatePut, Operation};
+use std::sync::Arc;
+
+/// Predicate selecting the instances a bulk state change applies to
+pub type InstanceFilter = Arc<dyn Fn(&Instance) -> bool + Send + Sync>;
+
+/// Settings for [`Client::change_instances_state`]
+#[derive(Clone)]
+pub struct StateChangeOptions {
+    /// Number of instances changed at the same time
+    pub parallelism: usize,
+
+    /// Only change the instances this returns `true` for (all if unset)
+    pub filter: Option<InstanceFilter>,
+}
+
+impl Default for StateChangeOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: 4,
+            filter: None,
+        }
+    }
+}
+
+impl StateChangeOptions {
+    /// Create options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of instances changed at the same time
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Only change the instances `filter` returns `true` for
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(&Instance) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+}
+
+impl std::fmt::Debug for StateChangeOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateChangeOptions")
+            .field("parallelism", &self.parallelism)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+/// Outcome of the state change of one instance
+#[derive(Debug)]
+pub struct StateChangeResult {
+    /// Instance name
+    pub name: String,
+
+    /// The finished operation, or why the change failed
+    pub result: Result<Operation>,
+}
+
+impl StateChangeResult {
+    /// Whether the state change succeeded
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Change the state of each selected instance and wait for it to finish
+pub(crate) async fn change_instances_state(
+    client: &Client,
+    request: &InstanceStatePut,
+    options: &StateChangeOptions,
+) -> Result<Vec<StateChangeResult>> {
+    let names: Vec<String> = client
+        .list_instances_full()
+        .await?
+        .iter()
+        .filter(|instance| {
+            options
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter(instance))
+        })
+        .map(instance_name)
+        .collect();
+
+    let results = futures::stream::iter(names)
+        .map(|name| async move {
+            let result = match client.change_instance_state(&name, request).await {
+                Ok(handle) => handle.wait().await,
+                Err(e) => Err(e),
+            };
+            StateChangeResult { name, result }
+        })
+        .buffered(options.parallelism.max(1))
+        .collect()
+        .await;
+    Ok(results)
+}
+
+#[cfg(not(feature = "generated"))]
+fn instance_name(instance: &Instance) -> String {
+    instance.name.clone()
+}
+
+#[cfg(feature = "generated")]
+fn instance_name(instance: &Instance) -> String {
+    instance.name.clone().unwrap_or_default()
+}
@@ -1,5 +1,11 @@
 //! Client configuration options
 
+use crate::{
+    BearerAuth, Client, ClientIdentity, Error, HttpsTransport, Result, ServerVerification,
+    TransportKind,
+};
+use hyper::Method;
+use std::path::Path;
 use std::time::Duration;
 
 /// Client configuration
@@ -22,6 +28,9 @@ pub struct ClientConfig {
 
     /// Whether to retry on 5xx errors
     pub retry_on_server_error: bool,
+
+    /// Whether to also retry non-idempotent requests (POST, PATCH)
+    pub retry_non_idempotent: bool,
 }
 
 impl Default for ClientConfig {
@@ -33,6 +42,7 @@ impl Default for ClientConfig {
             max_retry_delay: Duration::from_secs(5),
             retry_on_connection_error: true,
             retry_on_server_error: true,
+            retry_non_idempotent: false,
         }
     }
 }
@@ -67,6 +77,27 @@ impl ClientConfig {
         self
     }
 
+    /// Set whether to retry on connection errors and timeouts
+    pub fn with_retry_on_connection_error(mut self, retry: bool) -> Self {
+        self.retry_on_connection_error = retry;
+        self
+    }
+
+    /// Set whether to retry on 5xx errors
+    pub fn with_retry_on_server_error(mut self, retry: bool) -> Self {
+        self.retry_on_server_error = retry;
+        self
+    }
+
+    /// Set whether to retry non-idempotent requests (POST, PATCH)
+    ///
+    /// Only enable this if the server side of every request you send is safe
+    /// to apply twice.
+    pub fn with_retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
     /// Disable all retries
     pub fn no_retries(mut self) -> Self {
         self.retries = 0;
@@ -79,12 +110,34 @@ impl ClientConfig {
         let max = self.max_retry_delay.as_millis() as u64;
         Duration::from_millis(delay.min(max))
     }
+
+    /// Check whether a failed request should be retried
+    ///
+    /// `attempt` is the zero-based index of the attempt that just failed.
+    pub fn should_retry(&self, method: &Method, error: &Error, attempt: u32) -> bool {
+        if attempt >= self.retries {
+            return false;
+        }
+
+        if !method.is_idempotent() && !self.retry_non_idempotent {
+            return false;
+        }
+
+        match error {
+            Error::Connection(_) | Error::Timeout => self.retry_on_connection_error,
+            Error::Http { status, .. } => self.retry_on_server_error && *status >= 500,
+            Error::Api { code, .. } => self.retry_on_server_error && *code >= 500,
+            _ => false,
+        }
+    }
 }
 
 /// Builder for creating a configured client
 pub struct ClientBuilder {
     config: ClientConfig,
     project: Option<String>,
+    verification: ServerVerification,
+    accept_invalid_certs: bool,
 }
 
 impl ClientBuilder {
@@ -93,6 +146,8 @@ impl ClientBuilder {
         Self {
             config: ClientConfig::default(),
             project: None,
+            verification: ServerVerification::default(),
+            accept_invalid_certs: false,
         }
     }
 
@@ -120,6 +175,59 @@ impl ClientBuilder {
         self
     }
 
+    /// Set how HTTPS clients verify the server certificate
+    pub fn server_verification(mut self, verification: ServerVerification) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Accept any server certificate on HTTPS connections
+    ///
+    /// # Warning
+    ///
+    /// This disables server authentication entirely and makes every
+    /// connection open to man-in-the-middle attacks. Prefer pinning the
+    /// server certificate with [`ServerVerification::PinnedCertificate`].
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Build a client using HTTPS transport
+    pub fn build_https(
+        self,
+        url: impl Into<String>,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Client> {
+        let identity = ClientIdentity::from_files(cert_path, key_path)?;
+        self.build_https_with_identity(url, &identity)
+    }
+
+    /// Build a client using HTTPS transport with an in-memory client identity
+    pub fn build_https_with_identity(
+        self,
+        url: impl Into<String>,
+        identity: &ClientIdentity,
+    ) -> Result<Client> {
+        let transport = if self.accept_invalid_certs {
+            HttpsTransport::insecure(url, identity)?
+        } else {
+            HttpsTransport::with_identity(url, identity, self.verification.clone())?
+        };
+        Ok(Client::from_builder(self, TransportKind::Https(transport)))
+    }
+
+    /// Build a client using HTTPS transport with bearer-token authentication
+    pub fn build_https_with_auth(self, url: impl Into<String>, auth: BearerAuth) -> Result<Client> {
+        let transport = if self.accept_invalid_certs {
+            HttpsTransport::insecure_with_bearer_auth(url, auth)?
+        } else {
+            HttpsTransport::with_bearer_auth(url, auth, self.verification.clone())?
+        };
+        Ok(Client::from_builder(self, TransportKind::Https(transport)))
+    }
+
     /// Get the configuration
     pub fn get_config(&self) -> &ClientConfig {
         &self.config
@@ -129,6 +237,11 @@ impl ClientBuilder {
     pub fn get_project(&self) -> Option<&str> {
         self.project.as_deref()
     }
+
+    /// Get the server verification mode
+    pub fn get_server_verification(&self) -> &ServerVerification {
+        &self.verification
+    }
 }
 
 impl Default for ClientBuilder {
@@ -164,6 +277,32 @@ mod tests {
         assert_eq!(config.delay_for_attempt(10), Duration::from_secs(5));
     }
 
+    #[test]
+    fn test_should_retry() {
+        let config = ClientConfig::new().with_retries(2);
+        let server_error = Error::http(503, "Service Unavailable");
+
+        assert!(config.should_retry(&Method::GET, &server_error, 0));
+        assert!(config.should_retry(&Method::PUT, &Error::Timeout, 1));
+        assert!(config.should_retry(&Method::DELETE, &Error::api(500, "boom"), 0));
+
+        // Out of attempts
+        assert!(!config.should_retry(&Method::GET, &server_error, 2));
+
+        // Client errors are never retried
+        assert!(!config.should_retry(&Method::GET, &Error::http(404, "Not Found"), 0));
+        assert!(!config.should_retry(&Method::GET, &Error::api(403, "denied"), 0));
+
+        // Non-idempotent methods are opt-in
+        assert!(!config.should_retry(&Method::POST, &server_error, 0));
+        let config = config.with_retry_non_idempotent(true);
+        assert!(config.should_retry(&Method::POST, &server_error, 0));
+
+        let config = config.with_retry_on_server_error(false);
+        assert!(!config.should_retry(&Method::GET, &server_error, 0));
+        assert!(config.should_retry(&Method::GET, &Error::connection("reset"), 0));
+    }
+
     #[test]
     fn test_builder() {
         let builder = ClientBuilder::new()
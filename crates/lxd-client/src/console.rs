@@ -0,0 +1,93 @@
+//! Instance text consoles
+
+use crate::exec::{self, ExecControl};
+use crate::operation::OperationHandle;
+use crate::transport::WebSocketIo;
+use crate::{Client, Result};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+/// An attached instance console, started by [`Client::console`](crate::Client::console)
+///
+/// The console is read and written through [`AsyncRead`] and
+/// [`AsyncWrite`]. Reads reach end of file when LXD closes the console.
+pub struct ConsoleSession<'a> {
+    handle: OperationHandle<'a>,
+    terminal: WebSocketIo,
+    control: ExecControl,
+}
+
+impl<'a> ConsoleSession<'a> {
+    /// Connect to the websockets of a console operation
+    pub(crate) async fn attach(client: &'a Client, handle: OperationHandle<'a>) -> Result<Self> {
+        let control = exec::connect(client, &handle, "control").await?;
+        let terminal = exec::connect(client, &handle, "0").await?;
+
+        Ok(Self {
+            handle,
+            terminal: WebSocketIo::new(terminal),
+            control: ExecControl::new(control),
+        })
+    }
+
+    /// The console operation
+    pub fn operation(&self) -> &OperationHandle<'a> {
+        &self.handle
+    }
+
+    /// Resize the console
+    pub async fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        self.control.resize(width, height).await
+    }
+
+    /// Detach from the console and wait for LXD to end the operation
+    pub async fn close(mut self) -> Result<()> {
+        let _ = self.terminal.shutdown().await;
+        self.control.close().await;
+        self.handle.wait().await?;
+        Ok(())
+    }
+
+    /// Split into the console stream, the control channel and the operation
+    pub fn into_parts(self) -> (WebSocketIo, ExecControl, OperationHandle<'a>) {
+        (self.terminal, self.control, self.handle)
+    }
+}
+
+impl AsyncRead for ConsoleSession<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.terminal).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ConsoleSession<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.terminal).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.terminal).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.terminal).poll_shutdown(cx)
+    }
+}
+
+impl std::fmt::Debug for ConsoleSession<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsoleSession")
+            .field("operation", &self.handle)
+            .finish_non_exhaustive()
+    }
+}
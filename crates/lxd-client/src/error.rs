@@ -42,6 +42,26 @@ pub enum Error {
     #[error("TLS error: {0}")]
     Tls(String),
 
+    /// Configuration error
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    /// WebSocket error
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
+    /// SFTP error
+    #[error("SFTP error: {0}")]
+    Sftp(String),
+
+    /// The instance must be stopped for the request
+    #[error("Instance {name} is {status}, it must be stopped first")]
+    InstanceNotStopped { name: String, status: String },
+
+    /// Authentication error
+    #[error("Authentication error: {0}")]
+    Auth(String),
+
     /// Other error
     #[error("{0}")]
     Other(String),
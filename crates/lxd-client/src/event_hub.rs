@@ -0,0 +1,258 @@
+//! Shared, self-healing event subscriptions
+
+use crate::{Client, Error, EventFilter};
+use futures::{Stream, StreamExt};
+use lxd_types::Event;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval};
+
+/// Settings for an [`EventHub`]
+#[derive(Debug, Clone)]
+pub struct EventHubConfig {
+    /// Events buffered per subscriber before it starts lagging
+    pub capacity: usize,
+
+    /// Initial reconnect delay (doubles with each failed attempt)
+    pub reconnect_delay: Duration,
+
+    /// Maximum reconnect delay
+    pub max_reconnect_delay: Duration,
+
+    /// How often to ping the server while no events arrive
+    ///
+    /// If nothing, not even the pong, is received for twice this long, the
+    /// connection is treated as lost and re-established. This catches
+    /// half-open connections that would otherwise never end. `None`
+    /// disables the keepalive.
+    pub keepalive_interval: Option<Duration>,
+}
+
+impl Default for EventHubConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            reconnect_delay: Duration::from_millis(500),
+            max_reconnect_delay: Duration::from_secs(30),
+            keepalive_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl EventHubConfig {
+    /// Create a new configuration with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of events buffered per subscriber
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set the initial reconnect delay
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Set the maximum reconnect delay
+    pub fn with_max_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.max_reconnect_delay = delay;
+        self
+    }
+
+    /// Set the keepalive ping interval, or `None` to disable it
+    pub fn with_keepalive_interval(mut self, interval: Option<Duration>) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// Calculate the delay before a reconnect attempt (exponential backoff)
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        self.reconnect_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_reconnect_delay)
+    }
+}
+
+/// An item received by an [`EventSubscription`]
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    /// An event from LXD
+    Event(Arc<Event>),
+
+    /// The connection to LXD was lost and has been re-established
+    ///
+    /// Events sent while disconnected are lost; resync any cached state
+    /// through the `list_*_full` endpoints.
+    Reconnected,
+
+    /// This subscriber fell behind and this many events were dropped
+    ///
+    /// As with [`SubscriptionEvent::Reconnected`], cached state should be
+    /// resynced.
+    Lagged(u64),
+}
+
+/// One upstream `/1.0/events` connection shared by many subscribers
+///
+/// The hub keeps the connection open in a background task, reconnecting with
+/// exponential backoff whenever it fails, the server closes it or it stops
+/// answering keepalive pings. Each
+/// reconnect is announced to subscribers with
+/// [`SubscriptionEvent::Reconnected`]. Dropping the hub closes the
+/// connection and ends every subscription.
+pub struct EventHub {
+    sender: broadcast::Sender<SubscriptionEvent>,
+    task: JoinHandle<()>,
+}
+
+impl EventHub {
+    /// Start a hub with the default settings
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(client: Arc<Client>, filter: EventFilter) -> Self {
+        Self::start_with_config(client, filter, EventHubConfig::default())
+    }
+
+    /// Start a hub with custom settings
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start_with_config(
+        client: Arc<Client>,
+        filter: EventFilter,
+        config: EventHubConfig,
+    ) -> Self {
+        let (sender, _) = broadcast::channel(config.capacity.max(1));
+        let task = tokio::spawn(run(client, filter, config, sender.clone()));
+        Self { sender, task }
+    }
+
+    /// Subscribe to events received from now on
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription {
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    /// Number of active subscriptions
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Drop for EventHub {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl std::fmt::Debug for EventHub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventHub")
+            .field("subscribers", &self.subscriber_count())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A subscription to an [`EventHub`]
+#[derive(Debug)]
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<SubscriptionEvent>,
+}
+
+impl EventSubscription {
+    /// Wait for the next item, or `None` once the hub has been dropped
+    pub async fn recv(&mut self) -> Option<SubscriptionEvent> {
+        match self.receiver.recv().await {
+            Ok(event) => Some(event),
+            Err(RecvError::Lagged(missed)) => Some(SubscriptionEvent::Lagged(missed)),
+            Err(RecvError::Closed) => None,
+        }
+    }
+
+    /// Turn the subscription into a [`Stream`]
+    pub fn into_stream(self) -> impl Stream<Item = SubscriptionEvent> + Send + 'static {
+        futures::stream::unfold(self, |mut subscription| async move {
+            let event = subscription.recv().await?;
+            Some((event, subscription))
+        })
+    }
+}
+
+/// Keep the upstream connection open and forward its events
+async fn run(
+    client: Arc<Client>,
+    filter: EventFilter,
+    config: EventHubConfig,
+    sender: broadcast::Sender<SubscriptionEvent>,
+) {
+    let mut connected = false;
+    let mut attempt = 0;
+    loop {
+        if let Ok(mut events) = client.events(&filter).await {
+            if connected {
+                let _ = sender.send(SubscriptionEvent::Reconnected);
+            }
+            connected = true;
+            attempt = 0;
+
+            let mut keepalive = config
+                .keepalive_interval
+                .map(|interval| tokio::time::interval_at(Instant::now() + interval, interval));
+            loop {
+                tokio::select! {
+                    event = events.next() => match event {
+                        Some(Ok(event)) => {
+                            let _ = sender.send(SubscriptionEvent::Event(Arc::new(event)));
+                        }
+                        // Skip events that fail to decode rather than dropping the connection
+                        Some(Err(Error::Json(_))) => continue,
+                        Some(Err(_)) | None => break,
+                    },
+                    interval = tick(&mut keepalive) => {
+                        if events.idle_time() >= interval.saturating_mul(2)
+                            || events.ping().await.is_err()
+                        {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        tokio::time::sleep(config.delay_for_attempt(attempt)).await;
+        attempt = attempt.saturating_add(1);
+    }
+}
+
+/// Wait for the next keepalive tick, returning the interval, or forever if disabled
+async fn tick(keepalive: &mut Option<Interval>) -> Duration {
+    match keepalive {
+        Some(keepalive) => {
+            keepalive.tick().await;
+            keepalive.period()
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let config = EventHubConfig::new()
+            .with_reconnect_delay(Duration::from_millis(100))
+            .with_max_reconnect_delay(Duration::from_secs(1));
+        assert_eq!(config.delay_for_attempt(0), Duration::from_millis(100));
+        assert_eq!(config.delay_for_attempt(3), Duration::from_millis(800));
+        assert_eq!(config.delay_for_attempt(4), Duration::from_secs(1));
+        assert_eq!(config.delay_for_attempt(u32::MAX), Duration::from_secs(1));
+    }
+}
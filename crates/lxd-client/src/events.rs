@@ -0,0 +1,228 @@
+//! Event subscriptions over the `/1.0/events` websocket
+
+use crate::transport::{self, WebSocket};
+use crate::{Error, Result};
+use futures::{ready, SinkExt, Stream, StreamExt};
+use lxd_types::Event;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// Type of event sent by LXD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventType {
+    /// Log messages from the daemon
+    Logging,
+    /// Operation created, updated or finished
+    Operation,
+    /// Resource lifecycle changes (instance started, image created, ...)
+    Lifecycle,
+}
+
+impl EventType {
+    /// Event type name as used by LXD
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Logging => "logging",
+            Self::Operation => "operation",
+            Self::Lifecycle => "lifecycle",
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Selects the events returned by [`Client::events`](crate::Client::events)
+///
+/// By default all event types are received for the client's project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    types: Vec<EventType>,
+    project: Option<String>,
+    all_projects: bool,
+    target: Option<String>,
+}
+
+impl EventFilter {
+    /// Receive every event type for the client's project
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only receive events of this type (may be called several times)
+    pub fn with_type(mut self, event_type: EventType) -> Self {
+        if !self.types.contains(&event_type) {
+            self.types.push(event_type);
+        }
+        self
+    }
+
+    /// Only receive events of these types
+    pub fn with_types(self, event_types: impl IntoIterator<Item = EventType>) -> Self {
+        event_types.into_iter().fold(self, Self::with_type)
+    }
+
+    /// Receive events for this project instead of the client's project
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self.all_projects = false;
+        self
+    }
+
+    /// Receive events for all projects
+    pub fn all_projects(mut self) -> Self {
+        self.project = None;
+        self.all_projects = true;
+        self
+    }
+
+    /// Receive events from a single cluster member
+    pub fn with_target(mut self, member: impl Into<String>) -> Self {
+        self.target = Some(member.into());
+        self
+    }
+
+    /// Event types to receive, empty for all
+    pub fn types(&self) -> &[EventType] {
+        &self.types
+    }
+
+    /// Build the `/1.0/events` path, falling back to `default_project`
+    pub(crate) fn path(&self, default_project: Option<&str>) -> String {
+        let mut query = Vec::new();
+        if !self.types.is_empty() {
+            let types: Vec<&str> = self.types.iter().map(EventType::as_str).collect();
+            query.push(format!("type={}", types.join(",")));
+        }
+        if self.all_projects {
+            query.push("all-projects=true".to_string());
+        } else if let Some(project) = self.project.as_deref().or(default_project) {
+            query.push(format!("project={}", project));
+        }
+        if let Some(target) = &self.target {
+            query.push(format!("target={}", target));
+        }
+
+        if query.is_empty() {
+            "/1.0/events".to_string()
+        } else {
+            format!("/1.0/events?{}", query.join("&"))
+        }
+    }
+}
+
+/// A stream of events from [`Client::events`](crate::Client::events)
+///
+/// The stream ends when the server closes the connection; it does not
+/// reconnect.
+pub struct EventStream {
+    socket: WebSocket,
+    done: bool,
+    last_seen: Instant,
+}
+
+impl EventStream {
+    pub(crate) fn new(socket: WebSocket) -> Self {
+        Self {
+            socket,
+            done: false,
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// Send a ping; the server's pong counts as activity in [`Self::idle_time`]
+    pub(crate) async fn ping(&mut self) -> Result<()> {
+        self.socket
+            .send(Message::Ping(Default::default()))
+            .await
+            .map_err(transport::websocket_error)
+    }
+
+    /// Time since anything, including a pong, was last received
+    pub(crate) fn idle_time(&self) -> Duration {
+        self.last_seen.elapsed()
+    }
+
+    /// Close the connection
+    pub async fn close(mut self) -> Result<()> {
+        match self.socket.close(None).await {
+            Ok(()) | Err(tungstenite::Error::ConnectionClosed) => Ok(()),
+            Err(e) => Err(transport::websocket_error(e)),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Event>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.done {
+            let message = ready!(self.socket.poll_next_unpin(cx));
+            if let Some(Ok(_)) = message {
+                self.last_seen = Instant::now();
+            }
+            let data = match message {
+                Some(Ok(Message::Text(text))) => text.as_bytes().to_vec(),
+                Some(Ok(Message::Binary(data))) => data.to_vec(),
+                Some(Ok(Message::Close(_)))
+                | Some(Err(tungstenite::Error::ConnectionClosed))
+                | None => {
+                    self.done = true;
+                    break;
+                }
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(transport::websocket_error(e))));
+                }
+                // Pings are answered by the websocket itself, pongs only mark activity
+                Some(Ok(_)) => continue,
+            };
+            return Poll::Ready(Some(serde_json::from_slice(&data).map_err(Error::Json)));
+        }
+        Poll::Ready(None)
+    }
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_path() {
+        assert_eq!(EventFilter::new().path(None), "/1.0/events");
+        assert_eq!(
+            EventFilter::new().path(Some("web")),
+            "/1.0/events?project=web"
+        );
+        assert_eq!(
+            EventFilter::new()
+                .with_types([EventType::Lifecycle, EventType::Operation])
+                .with_type(EventType::Lifecycle)
+                .with_project("db")
+                .with_target("node2")
+                .path(Some("web")),
+            "/1.0/events?type=lifecycle,operation&project=db&target=node2"
+        );
+        assert_eq!(
+            EventFilter::new()
+                .with_project("db")
+                .all_projects()
+                .path(Some("web")),
+            "/1.0/events?all-projects=true"
+        );
+    }
+}
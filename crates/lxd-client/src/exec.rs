@@ -0,0 +1,471 @@
+//! Running commands in instances
+
+use crate::operation::{self, OperationHandle};
+use crate::transport::{self, WebSocket, WebSocketIo};
+use crate::{Client, Error, Result};
+use futures::{SinkExt, StreamExt};
+use lxd_types::{InstanceExecPost, Operation, Response};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// Output of a command run with [`Exec::output`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecOutput {
+    /// Exit code of the command
+    pub exit_code: i32,
+    /// Everything the command wrote to stdout
+    pub stdout: Vec<u8>,
+    /// Everything the command wrote to stderr
+    pub stderr: Vec<u8>,
+}
+
+impl ExecOutput {
+    /// Check if the command exited with code 0
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Stdout as a string, replacing invalid UTF-8
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// Stderr as a string, replacing invalid UTF-8
+    pub fn stderr_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+}
+
+/// A command to run in an instance, created by [`Client::exec`]
+#[derive(Clone)]
+pub struct Exec<'a> {
+    client: &'a Client,
+    instance: String,
+    command: Vec<String>,
+    environment: BTreeMap<String, String>,
+    cwd: Option<String>,
+    user: Option<u32>,
+    group: Option<u32>,
+    stdin: Vec<u8>,
+}
+
+impl<'a> Exec<'a> {
+    pub(crate) fn new(client: &'a Client, instance: &str, command: Vec<String>) -> Self {
+        Self {
+            client,
+            instance: instance.to_string(),
+            command,
+            environment: BTreeMap::new(),
+            cwd: None,
+            user: None,
+            group: None,
+            stdin: Vec::new(),
+        }
+    }
+
+    /// Set an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.environment.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set several environment variables
+    pub fn envs<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.environment
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Set the working directory
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Run as this UID
+    pub fn user(mut self, uid: u32) -> Self {
+        self.user = Some(uid);
+        self
+    }
+
+    /// Run with this GID
+    pub fn group(mut self, gid: u32) -> Self {
+        self.group = Some(gid);
+        self
+    }
+
+    /// Feed these bytes to the command's stdin, followed by end of file
+    pub fn stdin(mut self, stdin: impl Into<Vec<u8>>) -> Self {
+        self.stdin = stdin.into();
+        self
+    }
+
+    /// Build the exec request for `mode`
+    fn request(&self, mode: Mode) -> InstanceExecPost {
+        #[cfg(feature = "generated")]
+        let command = Some(self.command.clone());
+        #[cfg(not(feature = "generated"))]
+        let command = self.command.clone();
+
+        let size = match mode {
+            Mode::Pty(width, height) => Some((width, height)),
+            Mode::Capture | Mode::Record => None,
+        };
+
+        InstanceExecPost {
+            command,
+            cwd: self.cwd.clone(),
+            environment: Some(self.environment.clone()),
+            group: self.group.map(i64::from),
+            height: size.map(|(_, height)| i64::from(height)),
+            interactive: Some(size.is_some()),
+            record_output: Some(mode == Mode::Record),
+            user: self.user.map(i64::from),
+            wait_for_websocket: Some(mode != Mode::Record),
+            width: size.map(|(width, _)| i64::from(width)),
+        }
+    }
+
+    /// Start the operation and return its handle
+    async fn start(&self, mode: Mode) -> Result<OperationHandle<'a>> {
+        let path = self
+            .client
+            .path(&format!("/1.0/instances/{}/exec", self.instance));
+        let response: Response<Operation> = self.client.post(&path, &self.request(mode)).await?;
+        Ok(OperationHandle::new(self.client, response.metadata))
+    }
+
+    /// Run the command to completion, collecting its output
+    ///
+    /// Stdout and stderr are read from the operation's websockets and the
+    /// exit code from its metadata once it finishes. A non-zero exit code is
+    /// not an error; check [`ExecOutput::exit_code`].
+    pub async fn output(self) -> Result<ExecOutput> {
+        let handle = self.start(Mode::Capture).await?;
+
+        let control = self.connect(&handle, "control").await?;
+        let stdin = self.connect(&handle, "0").await?;
+        let stdout = self.connect(&handle, "1").await?;
+        let stderr = self.connect(&handle, "2").await?;
+
+        let (_, stdout, stderr) = tokio::try_join!(
+            write_all(stdin, &self.stdin),
+            read_to_end(stdout),
+            read_to_end(stderr)
+        )?;
+
+        let operation = handle.wait().await?;
+        close(control).await;
+
+        Ok(ExecOutput {
+            exit_code: exit_code(&operation)?,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Run the command on a PTY of `width` columns and `height` rows
+    ///
+    /// The returned session reads and writes the terminal, and can resize
+    /// it or send signals to the command. Stdin set with [`Exec::stdin`] is
+    /// ignored.
+    pub async fn pty(self, width: u32, height: u32) -> Result<PtySession<'a>> {
+        let handle = self.start(Mode::Pty(width, height)).await?;
+        let control = self.connect(&handle, "control").await?;
+        let terminal = self.connect(&handle, "0").await?;
+
+        Ok(PtySession {
+            handle,
+            terminal: WebSocketIo::new(terminal),
+            control: ExecControl::new(control),
+        })
+    }
+
+    /// Run the command with its output recorded to log files on the server
+    ///
+    /// No websockets are used, so the command keeps running if the client
+    /// goes away. Its stdin is empty; stdin set with [`Exec::stdin`] is
+    /// ignored. The recorded files are listed by
+    /// [`Client::list_exec_outputs`] and read with
+    /// [`Client::download_exec_output`].
+    pub async fn record(self) -> Result<RecordedExec<'a>> {
+        let handle = self.start(Mode::Record).await?;
+        Ok(RecordedExec { handle })
+    }
+
+    /// Connect to the websocket for file descriptor `fd`
+    async fn connect(&self, handle: &OperationHandle<'_>, fd: &str) -> Result<WebSocket> {
+        connect(self.client, handle, fd).await
+    }
+}
+
+impl std::fmt::Debug for Exec<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Exec")
+            .field("instance", &self.instance)
+            .field("command", &self.command)
+            .field("environment", &self.environment)
+            .field("cwd", &self.cwd)
+            .field("user", &self.user)
+            .field("group", &self.group)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How the command's input and output are connected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Separate stdin, stdout and stderr websockets
+    Capture,
+    /// One terminal websocket on a PTY of this many columns and rows
+    Pty(u32, u32),
+    /// Output written to log files on the server
+    Record,
+}
+
+/// A command started by [`Exec::record`]
+#[derive(Debug)]
+pub struct RecordedExec<'a> {
+    handle: OperationHandle<'a>,
+}
+
+impl<'a> RecordedExec<'a> {
+    /// The exec operation
+    ///
+    /// Keep its ID to collect the result later, even from another client,
+    /// with [`Client::wait_operation`] and [`ExecRecord::from_operation`].
+    pub fn operation(&self) -> &OperationHandle<'a> {
+        &self.handle
+    }
+
+    /// Wait for the command to exit
+    pub async fn wait(self) -> Result<ExecRecord> {
+        let operation = self.handle.wait().await?;
+        ExecRecord::from_operation(&operation)
+    }
+}
+
+/// Result of a command run with [`Exec::record`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecRecord {
+    /// Exit code of the command
+    pub exit_code: i32,
+    /// Name of the file holding the command's stdout
+    pub stdout: String,
+    /// Name of the file holding the command's stderr
+    pub stderr: String,
+}
+
+impl ExecRecord {
+    /// Read the exit code and output file names from a finished exec operation
+    pub fn from_operation(operation: &Operation) -> Result<Self> {
+        let file = |fd: &str| {
+            operation::metadata(operation, "output")
+                .and_then(|output| output.get(fd))
+                .and_then(|url| url.as_str())
+                .and_then(|url| url.rsplit('/').next())
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    Error::request(format!("Exec operation recorded no output for fd {}", fd))
+                })
+        };
+
+        Ok(Self {
+            exit_code: exit_code(operation)?,
+            stdout: file("1")?,
+            stderr: file("2")?,
+        })
+    }
+
+    /// Check if the command exited with code 0
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// An interactive command running on a PTY, started by [`Exec::pty`]
+///
+/// The session reads and writes the terminal through [`AsyncRead`] and
+/// [`AsyncWrite`]. Reads reach end of file when the command exits.
+pub struct PtySession<'a> {
+    handle: OperationHandle<'a>,
+    terminal: WebSocketIo,
+    control: ExecControl,
+}
+
+impl<'a> PtySession<'a> {
+    /// The exec operation
+    pub fn operation(&self) -> &OperationHandle<'a> {
+        &self.handle
+    }
+
+    /// Resize the terminal
+    pub async fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        self.control.resize(width, height).await
+    }
+
+    /// Send a signal (such as `2` for SIGINT) to the command
+    pub async fn signal(&mut self, signal: i32) -> Result<()> {
+        self.control.signal(signal).await
+    }
+
+    /// Wait for the command to exit and return its exit code
+    pub async fn wait(self) -> Result<i32> {
+        let operation = self.handle.wait().await?;
+        close(self.control.socket).await;
+        exit_code(&operation)
+    }
+
+    /// Split into the terminal stream, the control channel and the operation
+    ///
+    /// Use this to read and write the terminal from separate tasks while
+    /// resizing it from another.
+    pub fn into_parts(self) -> (WebSocketIo, ExecControl, OperationHandle<'a>) {
+        (self.terminal, self.control, self.handle)
+    }
+}
+
+impl AsyncRead for PtySession<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.terminal).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for PtySession<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.terminal).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.terminal).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.terminal).poll_shutdown(cx)
+    }
+}
+
+impl std::fmt::Debug for PtySession<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PtySession")
+            .field("operation", &self.handle)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Control channel of an exec or console session
+///
+/// Sends the `window-resize` and `signal` messages of LXD's control
+/// websocket.
+pub struct ExecControl {
+    socket: WebSocket,
+}
+
+impl ExecControl {
+    pub(crate) fn new(socket: WebSocket) -> Self {
+        Self { socket }
+    }
+
+    /// Resize the terminal
+    pub async fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        self.send(json!({
+            "command": "window-resize",
+            "args": {"width": width.to_string(), "height": height.to_string()}
+        }))
+        .await
+    }
+
+    /// Send a signal (such as `2` for SIGINT) to the command
+    pub async fn signal(&mut self, signal: i32) -> Result<()> {
+        self.send(json!({"command": "signal", "signal": signal}))
+            .await
+    }
+
+    /// Close the control channel
+    pub async fn close(self) {
+        close(self.socket).await;
+    }
+
+    async fn send(&mut self, message: serde_json::Value) -> Result<()> {
+        self.socket
+            .send(Message::text(message.to_string()))
+            .await
+            .map_err(transport::websocket_error)
+    }
+}
+
+impl std::fmt::Debug for ExecControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecControl").finish_non_exhaustive()
+    }
+}
+
+/// Connect to the websocket of an exec or console operation for file descriptor `fd`
+pub(crate) async fn connect(
+    client: &Client,
+    handle: &OperationHandle<'_>,
+    fd: &str,
+) -> Result<WebSocket> {
+    let secret = operation::metadata(handle.operation(), "fds")
+        .and_then(|fds| fds.get(fd))
+        .and_then(|secret| secret.as_str())
+        .ok_or_else(|| Error::request(format!("Operation has no websocket for fd {}", fd)))?;
+    client.operation_websocket(handle.id(), secret).await
+}
+
+/// Send `data`, then close the websocket to signal end of file
+async fn write_all(mut socket: WebSocket, data: &[u8]) -> Result<()> {
+    if !data.is_empty() {
+        socket
+            .send(Message::binary(data.to_vec()))
+            .await
+            .map_err(transport::websocket_error)?;
+    }
+    close(socket).await;
+    Ok(())
+}
+
+/// Collect everything received until the server closes the websocket
+async fn read_to_end(mut socket: WebSocket) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    while let Some(message) = socket.next().await {
+        match message {
+            Ok(Message::Binary(bytes)) => data.extend_from_slice(&bytes),
+            Ok(Message::Text(text)) => data.extend_from_slice(text.as_bytes()),
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => break,
+            Ok(_) => {}
+            Err(e) => return Err(transport::websocket_error(e)),
+        }
+    }
+    Ok(data)
+}
+
+/// Close a websocket, ignoring errors from a peer that already went away
+pub(crate) async fn close(mut socket: WebSocket) {
+    let _ = socket.close(None).await;
+}
+
+/// Read the exit code from a finished exec operation
+fn exit_code(operation: &Operation) -> Result<i32> {
+    operation::metadata(operation, "return")
+        .and_then(|code| code.as_i64())
+        .map(|code| code as i32)
+        .ok_or_else(|| Error::request("Exec operation did not report an exit code"))
+}
@@ -0,0 +1,227 @@
+//! Instance file transfers
+//!
+//! LXD sends file metadata in `X-LXD-*` headers next to the raw contents.
+
+use crate::transport::{ByteStream, RawResponse};
+use crate::{Error, Result};
+use std::fmt;
+
+/// Type of a file in an instance
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileType {
+    /// A regular file
+    File,
+    /// A directory
+    Directory,
+    /// A symbolic link
+    Symlink,
+    /// A type not known to this client
+    Unknown(String),
+}
+
+impl FileType {
+    /// File type name as used in the `X-LXD-type` header
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::File => "file",
+            Self::Directory => "directory",
+            Self::Symlink => "symlink",
+            Self::Unknown(file_type) => file_type,
+        }
+    }
+}
+
+impl From<&str> for FileType {
+    fn from(file_type: &str) -> Self {
+        match file_type {
+            "file" => Self::File,
+            "directory" => Self::Directory,
+            "symlink" => Self::Symlink,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a pushed file is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileWriteMode {
+    /// Replace any existing contents
+    #[default]
+    Overwrite,
+    /// Append to the existing contents
+    Append,
+}
+
+impl FileWriteMode {
+    /// Write mode as used in the `X-LXD-write` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Overwrite => "overwrite",
+            Self::Append => "append",
+        }
+    }
+}
+
+/// Type, ownership and permissions of a file in an instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// File type
+    pub file_type: FileType,
+    /// Owner UID
+    pub uid: u32,
+    /// Owner GID
+    pub gid: u32,
+    /// Permission bits
+    pub mode: u32,
+    /// Modification time as sent by LXD, if it sent one
+    pub modified: Option<String>,
+    /// Size in bytes, if known
+    pub size: Option<u64>,
+}
+
+impl FileMetadata {
+    /// Parse the `X-LXD-*` headers of a file response
+    pub fn from_response(response: &RawResponse) -> Result<Self> {
+        let number = |name: &str, radix: u32| {
+            response
+                .header(name)
+                .and_then(|value| u32::from_str_radix(value.trim(), radix).ok())
+                .ok_or_else(|| Error::request(format!("Missing or invalid {} header", name)))
+        };
+
+        Ok(Self {
+            file_type: FileType::from(response.header("X-LXD-type").unwrap_or("file")),
+            uid: number("X-LXD-uid", 10)?,
+            gid: number("X-LXD-gid", 10)?,
+            mode: number("X-LXD-mode", 8)?,
+            modified: response.header("X-LXD-modified").map(str::to_string),
+            size: response
+                .header("Content-Length")
+                .and_then(|length| length.parse().ok()),
+        })
+    }
+}
+
+/// A file pulled from an instance with [`Client::pull_file`](crate::Client::pull_file)
+#[derive(Debug)]
+pub struct InstanceFile {
+    /// Type, ownership and permissions
+    pub metadata: FileMetadata,
+    /// File contents (for a directory, LXD's JSON listing of its entries)
+    pub body: ByteStream,
+}
+
+/// Ownership, permissions and write mode for files created in an instance
+///
+/// Unset values are left to LXD's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileOptions {
+    /// Owner UID
+    pub uid: Option<u32>,
+    /// Owner GID
+    pub gid: Option<u32>,
+    /// Permission bits
+    pub mode: Option<u32>,
+    /// How file contents are written
+    pub write_mode: FileWriteMode,
+}
+
+impl FileOptions {
+    /// Create options using LXD's defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the owner UID
+    pub fn with_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Set the owner GID
+    pub fn with_gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Set the permission bits
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Set how file contents are written
+    pub fn with_write_mode(mut self, write_mode: FileWriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
+    /// Build the `X-LXD-*` headers for creating a file of `file_type`
+    pub(crate) fn headers(&self, file_type: FileType) -> Vec<(String, String)> {
+        let mut headers = vec![("X-LXD-type".to_string(), file_type.to_string())];
+        if let Some(uid) = self.uid {
+            headers.push(("X-LXD-uid".to_string(), uid.to_string()));
+        }
+        if let Some(gid) = self.gid {
+            headers.push(("X-LXD-gid".to_string(), gid.to_string()));
+        }
+        if let Some(mode) = self.mode {
+            headers.push(("X-LXD-mode".to_string(), format!("{:04o}", mode)));
+        }
+        if file_type == FileType::File {
+            headers.push((
+                "X-LXD-write".to_string(),
+                self.write_mode.as_str().to_string(),
+            ));
+        }
+        headers
+    }
+}
+
+/// Build the files endpoint path for `path` in `instance`
+pub(crate) fn endpoint(instance: &str, path: &str) -> String {
+    let path: String = url::form_urlencoded::byte_serialize(path.as_bytes()).collect();
+    format!("/1.0/instances/{}/files?path={}", instance, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_headers() {
+        let options = FileOptions::new()
+            .with_uid(1000)
+            .with_gid(100)
+            .with_mode(0o640)
+            .with_write_mode(FileWriteMode::Append);
+        assert_eq!(
+            options.headers(FileType::File),
+            vec![
+                ("X-LXD-type".to_string(), "file".to_string()),
+                ("X-LXD-uid".to_string(), "1000".to_string()),
+                ("X-LXD-gid".to_string(), "100".to_string()),
+                ("X-LXD-mode".to_string(), "0640".to_string()),
+                ("X-LXD-write".to_string(), "append".to_string()),
+            ]
+        );
+        assert_eq!(
+            FileOptions::new().headers(FileType::Directory),
+            vec![("X-LXD-type".to_string(), "directory".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_endpoint_encodes_path() {
+        assert_eq!(
+            endpoint("c1", "/root/my file"),
+            "/1.0/instances/c1/files?path=%2Froot%2Fmy+file"
+        );
+    }
+}
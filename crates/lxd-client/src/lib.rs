@@ -16,24 +16,68 @@
 //! }
 //! ```
 
+mod auth;
+mod backups;
+mod bulk;
 mod config;
+mod console;
 mod endpoints;
 mod error;
+mod event_hub;
+mod events;
+mod exec;
+mod files;
+mod migration;
+mod operation;
+mod remotes;
+mod sftp;
+mod transfer;
 mod transport;
+mod uefi;
 
+pub use auth::{
+    BearerAuth, DeviceAuthorization, OidcClient, OidcConfig, OidcCredentials, OidcTokens,
+    RefreshCallback,
+};
+pub use backups::BackupImportOptions;
+pub use bulk::{InstanceFilter, StateChangeOptions, StateChangeResult};
 pub use config::{ClientBuilder, ClientConfig};
+pub use console::ConsoleSession;
 pub use error::{Error, Result};
-pub use transport::{HttpsTransport, Transport, TransportKind, UnixSocketTransport};
+pub use event_hub::{EventHub, EventHubConfig, EventSubscription, SubscriptionEvent};
+pub use events::{EventFilter, EventStream, EventType};
+pub use exec::{Exec, ExecControl, ExecOutput, ExecRecord, PtySession, RecordedExec};
+pub use files::{FileMetadata, FileOptions, FileType, FileWriteMode, InstanceFile};
+pub use migration::{CopyOptions, MigrationMode, MigrationOptions, MoveOptions};
+pub use operation::{OperationHandle, OperationProgress, OperationResource};
+pub use remotes::{Remote, Remotes};
+pub use sftp::{SftpDirEntry, SftpFile, SftpMetadata, SftpSession};
+pub use transfer::{TransferCallback, TransferOptions, TransferProgress, TransferSummary};
+pub use transport::{
+    ByteStream, ClientIdentity, HttpsTransport, RawRequest, RawResponse, RequestBody,
+    ServerCertificate, ServerVerification, Transport, TransportKind, TrustCallback,
+    UnixSocketTransport, WebSocketIo,
+};
+pub use uefi::{UefiVariable, UefiVars, EFI_GLOBAL_VARIABLE_GUID, SECURE_BOOT_ENABLE_GUID};
+
+/// HTTP method of a [`RawRequest`]
+pub use hyper::Method;
+
+use serde::{de::DeserializeOwned, Serialize};
+use transport::WebSocket;
 
 use lxd_types::{
-    Image, ImagePut, ImagesPost, Instance, InstancePut, InstanceSnapshot, InstanceSnapshotsPost,
-    InstanceState, InstanceStatePut, InstancesPost, Network, NetworkPut, NetworksPost, Operation,
+    Image, ImageMetadata, ImagePut, ImagesPost, Instance, InstanceBackup, InstanceBackupPost,
+    InstanceBackupsPost, InstanceConsolePost, InstancePost, InstancePut, InstanceRebuildPost,
+    InstanceSnapshot, InstanceSnapshotsPost, InstanceSource, InstanceState, InstanceStatePut,
+    InstanceUEFIVars, InstancesPost, InstancesPut, Network, NetworkPut, NetworksPost, Operation,
     Profile, ProfilePut, ProfilesPost, Project, ProjectPut, ProjectsPost, Response, Server,
     StoragePool, StoragePoolPut, StoragePoolsPost, StorageVolume, StorageVolumePut,
     StorageVolumesPost,
 };
 use std::path::Path;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 
 /// LXD API client
 pub struct Client {
@@ -43,9 +87,18 @@ pub struct Client {
 }
 
 impl Client {
-    /// Create a new client using Unix socket transport
+    /// Create a new client using the local LXD Unix socket
+    ///
+    /// The socket is found with [`UnixSocketTransport::discover`], which
+    /// honours `LXD_SOCKET` and `LXD_DIR` before checking the snap, native
+    /// package and Incus locations.
     pub fn new_unix_socket() -> Result<Self> {
-        Self::new_unix_socket_path("/var/snap/lxd/common/lxd/unix.socket")
+        let transport = UnixSocketTransport::discover()?;
+        Ok(Self {
+            transport: TransportKind::UnixSocket(transport),
+            project: None,
+            config: ClientConfig::default(),
+        })
     }
 
     /// Create a new client using Unix socket at a specific path
@@ -59,12 +112,24 @@ impl Client {
     }
 
     /// Create a new client using HTTPS transport
+    ///
+    /// The server certificate is verified against the bundled web PKI roots.
     pub fn new_https(
         url: impl Into<String>,
         cert_path: impl AsRef<Path>,
         key_path: impl AsRef<Path>,
     ) -> Result<Self> {
-        let transport = HttpsTransport::new(url, cert_path, key_path)?;
+        Self::new_https_with_verification(url, cert_path, key_path, ServerVerification::default())
+    }
+
+    /// Create a new client using HTTPS transport with a server verification mode
+    pub fn new_https_with_verification(
+        url: impl Into<String>,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        let transport = HttpsTransport::with_verification(url, cert_path, key_path, verification)?;
         Ok(Self {
             transport: TransportKind::Https(transport),
             project: None,
@@ -72,6 +137,59 @@ impl Client {
         })
     }
 
+    /// Create a new client using HTTPS transport from PEM encoded certificate and key
+    pub fn new_https_from_pem(
+        url: impl Into<String>,
+        cert_pem: &[u8],
+        key_pem: &[u8],
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        let transport = HttpsTransport::from_pem(url, cert_pem, key_pem, verification)?;
+        Ok(Self {
+            transport: TransportKind::Https(transport),
+            project: None,
+            config: ClientConfig::default(),
+        })
+    }
+
+    /// Create a new client using HTTPS transport presenting a client identity
+    pub fn new_https_with_identity(
+        url: impl Into<String>,
+        identity: &ClientIdentity,
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        let transport = HttpsTransport::with_identity(url, identity, verification)?;
+        Ok(Self {
+            transport: TransportKind::Https(transport),
+            project: None,
+            config: ClientConfig::default(),
+        })
+    }
+
+    /// Create a new client using HTTPS transport with bearer-token authentication
+    ///
+    /// No client certificate is needed; see [`BearerAuth`] for static tokens
+    /// and OIDC.
+    pub fn new_https_with_auth(
+        url: impl Into<String>,
+        auth: BearerAuth,
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        let transport = HttpsTransport::with_bearer_auth(url, auth, verification)?;
+        Ok(Self {
+            transport: TransportKind::Https(transport),
+            project: None,
+            config: ClientConfig::default(),
+        })
+    }
+
+    /// Create a new client for a remote configured for the `lxc` CLI
+    ///
+    /// See [`Remotes::load`] for where the configuration is read from.
+    pub fn from_remote(name: &str) -> Result<Self> {
+        Remotes::load()?.client(name)
+    }
+
     /// Create a new client from a builder
     pub fn from_builder(builder: ClientBuilder, transport: TransportKind) -> Self {
         Self {
@@ -124,11 +242,86 @@ impl Client {
         }
     }
 
+    // Request helpers
+    // These apply the configured timeout and retry policy to every request
+
+    /// Perform a GET request
+    async fn get<T: DeserializeOwned + Send>(&self, path: &str) -> Result<T> {
+        transport::execute(&self.config, Method::GET, Some(self.config.timeout), || {
+            self.transport.get(path)
+        })
+        .await
+    }
+
+    /// Perform a POST request
+    async fn post<T: DeserializeOwned + Send, B: Serialize + Send + Sync>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        transport::execute(
+            &self.config,
+            Method::POST,
+            Some(self.config.timeout),
+            || self.transport.post(path, body),
+        )
+        .await
+    }
+
+    /// Perform a PUT request
+    async fn put<T: DeserializeOwned + Send, B: Serialize + Send + Sync>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        transport::execute(&self.config, Method::PUT, Some(self.config.timeout), || {
+            self.transport.put(path, body)
+        })
+        .await
+    }
+
+    /// Perform a DELETE request
+    async fn delete<T: DeserializeOwned + Send>(&self, path: &str) -> Result<T> {
+        transport::execute(
+            &self.config,
+            Method::DELETE,
+            Some(self.config.timeout),
+            || self.transport.delete(path),
+        )
+        .await
+    }
+
+    /// Perform a request with a raw body
+    ///
+    /// Requests with a body held in memory get the configured timeout and
+    /// retry policy; a streamed body can only be sent once, so it gets the
+    /// timeout but is never retried. The timeout covers receiving the
+    /// response headers, not reading the body.
+    async fn send_raw(&self, request: RawRequest) -> Result<RawResponse> {
+        if request.try_clone().is_none() {
+            return tokio::time::timeout(self.config.timeout, self.transport.send_raw(request))
+                .await
+                .unwrap_or(Err(Error::Timeout));
+        }
+        let method = request.method.clone();
+        transport::execute(&self.config, method, Some(self.config.timeout), || {
+            let request = request.try_clone().expect("body is held in memory");
+            self.transport.send_raw(request)
+        })
+        .await
+    }
+
+    /// Perform a GET request returning the raw response body
+    async fn download(&self, path: &str) -> Result<ByteStream> {
+        let response = self.send_raw(RawRequest::new(Method::GET, path)).await?;
+        Ok(response.body)
+    }
+
     // Server endpoints
 
     /// Get server information
     pub async fn get_server(&self) -> Result<Server> {
-        let response: Response<Server> = self.transport.get("/1.0").await?;
+        let response: Response<Server> = self.get("/1.0").await?;
         Ok(response.metadata)
     }
 
@@ -151,49 +344,82 @@ impl Client {
     /// List all instances (URLs)
     pub async fn list_instances(&self) -> Result<Vec<String>> {
         let path = self.path("/1.0/instances");
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// List all instances with full details
     pub async fn list_instances_full(&self) -> Result<Vec<Instance>> {
         let path = self.path("/1.0/instances?recursion=1");
-        let response: Response<Vec<Instance>> = self.transport.get(&path).await?;
+        let response: Response<Vec<Instance>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Get a specific instance
     pub async fn get_instance(&self, name: &str) -> Result<Instance> {
         let path = self.path(&format!("/1.0/instances/{}", name));
-        let response: Response<Instance> = self.transport.get(&path).await?;
+        let response: Response<Instance> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Create a new instance
-    pub async fn create_instance(&self, request: &InstancesPost) -> Result<Operation> {
+    pub async fn create_instance(&self, request: &InstancesPost) -> Result<OperationHandle<'_>> {
         let path = self.path("/1.0/instances");
-        let response: Response<Operation> = self.transport.post(&path, request).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.post(&path, request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
     }
 
     /// Update an instance
     pub async fn update_instance(&self, name: &str, request: &InstancePut) -> Result<()> {
         let path = self.path(&format!("/1.0/instances/{}", name));
-        let _response: Response<()> = self.transport.put(&path, request).await?;
+        let _response: Response<()> = self.put(&path, request).await?;
         Ok(())
     }
 
     /// Delete an instance
-    pub async fn delete_instance(&self, name: &str) -> Result<Operation> {
+    pub async fn delete_instance(&self, name: &str) -> Result<OperationHandle<'_>> {
         let path = self.path(&format!("/1.0/instances/{}", name));
-        let response: Response<Operation> = self.transport.delete(&path).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.delete(&path).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Rebuild an instance's root filesystem from an image, or as an empty one
+    ///
+    /// The instance keeps its configuration, devices and attached volumes.
+    /// It must be stopped; otherwise [`Error::InstanceNotStopped`] is
+    /// returned without sending the request.
+    pub async fn rebuild_instance(
+        &self,
+        name: &str,
+        source: &InstanceSource,
+    ) -> Result<OperationHandle<'_>> {
+        let instance = self.get_instance(name).await?;
+        #[cfg(feature = "generated")]
+        let status = instance.status.unwrap_or_default();
+        #[cfg(not(feature = "generated"))]
+        let status = instance.status;
+        if status != "Stopped" {
+            return Err(Error::InstanceNotStopped {
+                name: name.to_string(),
+                status,
+            });
+        }
+
+        #[cfg(feature = "generated")]
+        let request = InstanceRebuildPost {
+            source: Some(source.clone()),
+        };
+        #[cfg(not(feature = "generated"))]
+        let request = InstanceRebuildPost::new(source.clone());
+        let path = self.path(&format!("/1.0/instances/{}/rebuild", name));
+        let response: Response<Operation> = self.post(&path, &request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
     }
 
     /// Get instance state
     pub async fn get_instance_state(&self, name: &str) -> Result<InstanceState> {
         let path = self.path(&format!("/1.0/instances/{}/state", name));
-        let response: Response<InstanceState> = self.transport.get(&path).await?;
+        let response: Response<InstanceState> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
@@ -202,17 +428,49 @@ impl Client {
         &self,
         name: &str,
         request: &InstanceStatePut,
-    ) -> Result<Operation> {
+    ) -> Result<OperationHandle<'_>> {
         let path = self.path(&format!("/1.0/instances/{}/state", name));
-        let response: Response<Operation> = self.transport.put(&path, request).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.put(&path, request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Change the state of every instance in the project at once
+    ///
+    /// The server applies the action to all instances in a single operation.
+    pub async fn change_all_instances_state(
+        &self,
+        request: &InstanceStatePut,
+    ) -> Result<OperationHandle<'_>> {
+        #[cfg(feature = "generated")]
+        let request = InstancesPut {
+            state: Some(request.clone()),
+        };
+        #[cfg(not(feature = "generated"))]
+        let request = InstancesPut::new(request.clone());
+        let path = self.path("/1.0/instances");
+        let response: Response<Operation> = self.put(&path, &request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Change the state of the instances selected by `options`, a few at a time
+    ///
+    /// Each instance gets its own [`Client::change_instance_state`] call,
+    /// which is waited on. A failure is recorded in that instance's result
+    /// and does not stop the others; only failing to list the instances
+    /// fails the whole call. Results are in the order instances are listed.
+    pub async fn change_instances_state(
+        &self,
+        request: &InstanceStatePut,
+        options: &StateChangeOptions,
+    ) -> Result<Vec<StateChangeResult>> {
+        bulk::change_instances_state(self, request, options).await
     }
 
     // Convenience methods for instance state
     // These are implemented differently based on whether generated types are used
 
     /// Start an instance
-    pub async fn start_instance(&self, name: &str) -> Result<Operation> {
+    pub async fn start_instance(&self, name: &str) -> Result<OperationHandle<'_>> {
         #[cfg(feature = "generated")]
         let request = InstanceStatePut {
             action: Some("start".to_string()),
@@ -231,7 +489,7 @@ impl Client {
     }
 
     /// Stop an instance
-    pub async fn stop_instance(&self, name: &str, force: bool) -> Result<Operation> {
+    pub async fn stop_instance(&self, name: &str, force: bool) -> Result<OperationHandle<'_>> {
         #[cfg(feature = "generated")]
         let request = InstanceStatePut {
             action: Some("stop".to_string()),
@@ -250,7 +508,7 @@ impl Client {
     }
 
     /// Restart an instance
-    pub async fn restart_instance(&self, name: &str) -> Result<Operation> {
+    pub async fn restart_instance(&self, name: &str) -> Result<OperationHandle<'_>> {
         #[cfg(feature = "generated")]
         let request = InstanceStatePut {
             action: Some("restart".to_string()),
@@ -269,7 +527,7 @@ impl Client {
     }
 
     /// Freeze an instance
-    pub async fn freeze_instance(&self, name: &str) -> Result<Operation> {
+    pub async fn freeze_instance(&self, name: &str) -> Result<OperationHandle<'_>> {
         #[cfg(feature = "generated")]
         let request = InstanceStatePut {
             action: Some("freeze".to_string()),
@@ -288,7 +546,7 @@ impl Client {
     }
 
     /// Unfreeze an instance
-    pub async fn unfreeze_instance(&self, name: &str) -> Result<Operation> {
+    pub async fn unfreeze_instance(&self, name: &str) -> Result<OperationHandle<'_>> {
         #[cfg(feature = "generated")]
         let request = InstanceStatePut {
             action: Some("unfreeze".to_string()),
@@ -306,127 +564,497 @@ impl Client {
         self.change_instance_state(name, &request).await
     }
 
+    // Instance exec endpoints
+
+    /// Prepare a command to run in an instance
+    ///
+    /// ```rust,no_run
+    /// # async fn example(client: &lxd_client::Client) -> lxd_client::Result<()> {
+    /// let output = client
+    ///     .exec("c1", ["apt-get", "install", "-y", "nginx"])
+    ///     .env("DEBIAN_FRONTEND", "noninteractive")
+    ///     .output()
+    ///     .await?;
+    /// println!("exit code {}", output.exit_code);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exec<I, S>(&self, instance: &str, command: I) -> Exec<'_>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Exec::new(
+            self,
+            instance,
+            command.into_iter().map(Into::into).collect(),
+        )
+    }
+
+    /// List the output files recorded by [`Exec::record`] (URLs)
+    pub async fn list_exec_outputs(&self, instance: &str) -> Result<Vec<String>> {
+        let path = self.path(&format!("/1.0/instances/{}/logs/exec-output", instance));
+        let response: Response<Vec<String>> = self.get(&path).await?;
+        Ok(response.metadata)
+    }
+
+    /// Download a recorded exec output file
+    pub async fn download_exec_output(&self, instance: &str, filename: &str) -> Result<ByteStream> {
+        let path = self.path(&format!(
+            "/1.0/instances/{}/logs/exec-output/{}",
+            instance, filename
+        ));
+        self.download(&path).await
+    }
+
+    /// Delete a recorded exec output file
+    pub async fn delete_exec_output(&self, instance: &str, filename: &str) -> Result<()> {
+        let path = self.path(&format!(
+            "/1.0/instances/{}/logs/exec-output/{}",
+            instance, filename
+        ));
+        let _response: Response<()> = self.delete(&path).await?;
+        Ok(())
+    }
+
+    // Instance log endpoints
+
+    /// List the log files of an instance
+    pub async fn list_instance_logs(&self, name: &str) -> Result<Vec<String>> {
+        let path = self.path(&format!("/1.0/instances/{}/logs", name));
+        let response: Response<Vec<String>> = self.get(&path).await?;
+        Ok(response.metadata)
+    }
+
+    /// Download a log file of an instance, such as `lxc.log` or `qemu.log`
+    pub async fn get_instance_log(&self, name: &str, filename: &str) -> Result<ByteStream> {
+        let path = self.path(&format!("/1.0/instances/{}/logs/{}", name, filename));
+        self.download(&path).await
+    }
+
+    /// Delete a log file of an instance
+    pub async fn delete_instance_log(&self, name: &str, filename: &str) -> Result<()> {
+        let path = self.path(&format!("/1.0/instances/{}/logs/{}", name, filename));
+        let _response: Response<()> = self.delete(&path).await?;
+        Ok(())
+    }
+
+    // Instance metadata endpoints
+
+    /// Get the image metadata of an instance, including its templates
+    pub async fn get_instance_metadata(&self, name: &str) -> Result<ImageMetadata> {
+        let path = self.path(&format!("/1.0/instances/{}/metadata", name));
+        let response: Response<ImageMetadata> = self.get(&path).await?;
+        Ok(response.metadata)
+    }
+
+    /// Replace the image metadata of an instance
+    pub async fn update_instance_metadata(
+        &self,
+        name: &str,
+        metadata: &ImageMetadata,
+    ) -> Result<()> {
+        let path = self.path(&format!("/1.0/instances/{}/metadata", name));
+        let _response: Response<()> = self.put(&path, metadata).await?;
+        Ok(())
+    }
+
+    /// List the template files of an instance
+    pub async fn list_instance_templates(&self, name: &str) -> Result<Vec<String>> {
+        let path = self.path(&format!("/1.0/instances/{}/metadata/templates", name));
+        let response: Response<Vec<String>> = self.get(&path).await?;
+        Ok(response.metadata)
+    }
+
+    /// Download a template file of an instance
+    pub async fn get_instance_template(&self, name: &str, template: &str) -> Result<ByteStream> {
+        self.download(&self.template_path(name, template)).await
+    }
+
+    /// Upload a template file to an instance, replacing any existing one
+    ///
+    /// The template only takes effect once it is referenced from the
+    /// `templates` of the instance metadata.
+    pub async fn upload_instance_template(
+        &self,
+        name: &str,
+        template: &str,
+        contents: impl AsyncRead + Send + 'static,
+    ) -> Result<()> {
+        let path = self.template_path(name, template);
+        let request = RawRequest::new(Method::POST, path).body(RequestBody::from_reader(contents));
+        self.send_raw(request).await?;
+        Ok(())
+    }
+
+    /// Delete a template file of an instance
+    pub async fn delete_instance_template(&self, name: &str, template: &str) -> Result<()> {
+        let _response: Response<()> = self.delete(&self.template_path(name, template)).await?;
+        Ok(())
+    }
+
+    /// Build the path of a template file of an instance
+    fn template_path(&self, name: &str, template: &str) -> String {
+        let template: String = url::form_urlencoded::byte_serialize(template.as_bytes()).collect();
+        self.path(&format!(
+            "/1.0/instances/{}/metadata/templates?path={}",
+            name, template
+        ))
+    }
+
+    // Instance UEFI variable endpoints
+
+    /// Get the UEFI variables of a virtual machine
+    pub async fn get_instance_uefi_vars(&self, name: &str) -> Result<UefiVars> {
+        let path = self.path(&format!("/1.0/instances/{}/uefi-vars", name));
+        let response: Response<InstanceUEFIVars> = self.get(&path).await?;
+        UefiVars::from_api(&response.metadata)
+    }
+
+    /// Replace the UEFI variables of a virtual machine
+    ///
+    /// The virtual machine must be stopped.
+    pub async fn update_instance_uefi_vars(&self, name: &str, vars: &UefiVars) -> Result<()> {
+        let path = self.path(&format!("/1.0/instances/{}/uefi-vars", name));
+        let _response: Response<()> = self.put(&path, &vars.to_api()).await?;
+        Ok(())
+    }
+
+    /// Enable or disable Secure Boot in the UEFI variables of a virtual machine
+    pub async fn set_instance_secure_boot(&self, name: &str, enabled: bool) -> Result<()> {
+        let mut vars = self.get_instance_uefi_vars(name).await?;
+        vars.set_secure_boot(enabled);
+        self.update_instance_uefi_vars(name, &vars).await
+    }
+
+    /// Set the boot order in the UEFI variables of a virtual machine
+    ///
+    /// `order` lists `Boot####` option numbers, first to try first.
+    pub async fn set_instance_boot_order(&self, name: &str, order: &[u16]) -> Result<()> {
+        let mut vars = self.get_instance_uefi_vars(name).await?;
+        vars.set_boot_order(order);
+        self.update_instance_uefi_vars(name, &vars).await
+    }
+
+    // Instance console endpoints
+
+    /// Attach to the text console of an instance
+    ///
+    /// The console is `width` columns by `height` rows; resize it with
+    /// [`ConsoleSession::resize`].
+    pub async fn console(&self, name: &str, width: u32, height: u32) -> Result<ConsoleSession<'_>> {
+        #[cfg(feature = "generated")]
+        let request = InstanceConsolePost {
+            height: Some(i64::from(height)),
+            kind: Some("console".to_string()),
+            width: Some(i64::from(width)),
+        };
+        #[cfg(not(feature = "generated"))]
+        let request = InstanceConsolePost {
+            height: Some(i64::from(height)),
+            console_type: Some("console".to_string()),
+            width: Some(i64::from(width)),
+        };
+
+        let path = self.path(&format!("/1.0/instances/{}/console", name));
+        let response: Response<Operation> = self.post(&path, &request).await?;
+        ConsoleSession::attach(self, OperationHandle::new(self, response.metadata)).await
+    }
+
+    /// Get the console log (the ring buffer of recent console output)
+    pub async fn get_console_log(&self, name: &str) -> Result<String> {
+        let path = self.path(&format!("/1.0/instances/{}/console", name));
+        let log = self.download(&path).await?.bytes().await?;
+        Ok(String::from_utf8_lossy(&log).into_owned())
+    }
+
+    /// Clear the console log
+    pub async fn clear_console_log(&self, name: &str) -> Result<()> {
+        let path = self.path(&format!("/1.0/instances/{}/console", name));
+        let _response: Response<()> = self.delete(&path).await?;
+        Ok(())
+    }
+
+    // Instance file endpoints
+
+    /// Pull a file from an instance
+    ///
+    /// The contents are streamed; the metadata comes from the response
+    /// headers.
+    pub async fn pull_file(&self, name: &str, path: &str) -> Result<InstanceFile> {
+        let path = self.path(&files::endpoint(name, path));
+        let response = self.send_raw(RawRequest::new(Method::GET, path)).await?;
+        Ok(InstanceFile {
+            metadata: FileMetadata::from_response(&response)?,
+            body: response.body,
+        })
+    }
+
+    /// List the entries of a directory in an instance
+    pub async fn list_files(&self, name: &str, path: &str) -> Result<Vec<String>> {
+        let file = self.pull_file(name, path).await?;
+        if file.metadata.file_type != FileType::Directory {
+            return Err(Error::request(format!("{} is not a directory", path)));
+        }
+        let response: Response<Vec<String>> = serde_json::from_slice(&file.body.bytes().await?)?;
+        Ok(response.metadata)
+    }
+
+    /// Get the type, ownership and permissions of a path in an instance
+    pub async fn stat_file(&self, name: &str, path: &str) -> Result<FileMetadata> {
+        let path = self.path(&files::endpoint(name, path));
+        let response = self.send_raw(RawRequest::new(Method::HEAD, path)).await?;
+        FileMetadata::from_response(&response)
+    }
+
+    /// Push a file into an instance, streaming its contents from `contents`
+    pub async fn push_file(
+        &self,
+        name: &str,
+        path: &str,
+        contents: impl AsyncRead + Send + 'static,
+        options: &FileOptions,
+    ) -> Result<()> {
+        let path = self.path(&files::endpoint(name, path));
+        let mut request =
+            RawRequest::new(Method::POST, path).body(RequestBody::from_reader(contents));
+        request.headers = options.headers(FileType::File);
+        self.send_raw(request).await?;
+        Ok(())
+    }
+
+    /// Create a directory in an instance
+    pub async fn create_directory(
+        &self,
+        name: &str,
+        path: &str,
+        options: &FileOptions,
+    ) -> Result<()> {
+        let path = self.path(&files::endpoint(name, path));
+        let mut request = RawRequest::new(Method::POST, path);
+        request.headers = options.headers(FileType::Directory);
+        self.send_raw(request).await?;
+        Ok(())
+    }
+
+    /// Create a symbolic link at `path` pointing to `target` in an instance
+    pub async fn create_symlink(
+        &self,
+        name: &str,
+        path: &str,
+        target: &str,
+        options: &FileOptions,
+    ) -> Result<()> {
+        let path = self.path(&files::endpoint(name, path));
+        let mut request = RawRequest::new(Method::POST, path).body(target.to_string());
+        request.headers = options.headers(FileType::Symlink);
+        self.send_raw(request).await?;
+        Ok(())
+    }
+
+    /// Push the local directory tree at `local` to `remote` in an instance
+    ///
+    /// Directories, regular files and symbolic links are copied with their
+    /// permission bits and, unless disabled in `options`, their owner. Other
+    /// file types are skipped. Existing remote files are overwritten.
+    pub async fn push_dir(
+        &self,
+        local: impl AsRef<Path>,
+        name: &str,
+        remote: &str,
+        options: &TransferOptions,
+    ) -> Result<TransferSummary> {
+        transfer::push_dir(self, local.as_ref(), name, remote, options).await
+    }
+
+    /// Pull the directory tree at `remote` in an instance to `local`
+    ///
+    /// The counterpart of [`Client::push_dir`]; `local` is created if needed
+    /// and existing local files are overwritten.
+    pub async fn pull_dir(
+        &self,
+        name: &str,
+        remote: &str,
+        local: impl AsRef<Path>,
+        options: &TransferOptions,
+    ) -> Result<TransferSummary> {
+        transfer::pull_dir(self, name, remote, local.as_ref(), options).await
+    }
+
+    /// Open an SFTP session on an instance
+    ///
+    /// The connection is upgraded to SFTP over the same unix socket or
+    /// HTTPS server as other requests, so many files can be read and
+    /// written without a request per file.
+    pub async fn sftp(&self, name: &str) -> Result<SftpSession> {
+        let path = self.path(&format!("/1.0/instances/{}/sftp", name));
+        let stream =
+            transport::execute(&self.config, Method::GET, Some(self.config.timeout), || {
+                self.transport.upgrade(&path, "sftp")
+            })
+            .await?;
+        SftpSession::start(stream).await
+    }
+
+    /// Delete a file or empty directory in an instance
+    pub async fn delete_file(&self, name: &str, path: &str) -> Result<()> {
+        let path = self.path(&files::endpoint(name, path));
+        let _response: Response<()> = self.delete(&path).await?;
+        Ok(())
+    }
+
     // Image endpoints
 
     /// List all images (URLs)
     pub async fn list_images(&self) -> Result<Vec<String>> {
         let path = self.path("/1.0/images");
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// List all images with full details
     pub async fn list_images_full(&self) -> Result<Vec<Image>> {
         let path = self.path("/1.0/images?recursion=1");
-        let response: Response<Vec<Image>> = self.transport.get(&path).await?;
+        let response: Response<Vec<Image>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Get a specific image
     pub async fn get_image(&self, fingerprint: &str) -> Result<Image> {
         let path = self.path(&format!("/1.0/images/{}", fingerprint));
-        let response: Response<Image> = self.transport.get(&path).await?;
+        let response: Response<Image> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Create/import an image
-    pub async fn create_image(&self, request: &ImagesPost) -> Result<Operation> {
+    pub async fn create_image(&self, request: &ImagesPost) -> Result<OperationHandle<'_>> {
         let path = self.path("/1.0/images");
-        let response: Response<Operation> = self.transport.post(&path, request).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.post(&path, request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
     }
 
     /// Update an image
     pub async fn update_image(&self, fingerprint: &str, request: &ImagePut) -> Result<()> {
         let path = self.path(&format!("/1.0/images/{}", fingerprint));
-        let _response: Response<()> = self.transport.put(&path, request).await?;
+        let _response: Response<()> = self.put(&path, request).await?;
         Ok(())
     }
 
     /// Delete an image
-    pub async fn delete_image(&self, fingerprint: &str) -> Result<Operation> {
+    pub async fn delete_image(&self, fingerprint: &str) -> Result<OperationHandle<'_>> {
         let path = self.path(&format!("/1.0/images/{}", fingerprint));
-        let response: Response<Operation> = self.transport.delete(&path).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.delete(&path).await?;
+        Ok(OperationHandle::new(self, response.metadata))
     }
 
     // Operation endpoints
 
     /// List all operations (URLs)
     pub async fn list_operations(&self) -> Result<Vec<String>> {
-        let response: Response<Vec<String>> = self.transport.get("/1.0/operations").await?;
+        let response: Response<Vec<String>> = self.get("/1.0/operations").await?;
         Ok(response.metadata)
     }
 
     /// Get a specific operation
     pub async fn get_operation(&self, id: &str) -> Result<Operation> {
-        let response: Response<Operation> = self
-            .transport
-            .get(&format!("/1.0/operations/{}", id))
-            .await?;
+        let response: Response<Operation> = self.get(&format!("/1.0/operations/{}", id)).await?;
         Ok(response.metadata)
     }
 
     /// Wait for an operation to complete
+    ///
+    /// The server-side `timeout` (in seconds) extends the client deadline for
+    /// this request; without one the client waits as long as the server does.
     pub async fn wait_operation(&self, id: &str, timeout: Option<i64>) -> Result<Operation> {
-        let path = match timeout {
-            Some(t) => format!("/1.0/operations/{}/wait?timeout={}", id, t),
-            None => format!("/1.0/operations/{}/wait", id),
+        let (path, deadline) = match timeout {
+            Some(t) if t >= 0 => (
+                format!("/1.0/operations/{}/wait?timeout={}", id, t),
+                Some(self.config.timeout + Duration::from_secs(t as u64)),
+            ),
+            Some(t) => (format!("/1.0/operations/{}/wait?timeout={}", id, t), None),
+            None => (format!("/1.0/operations/{}/wait", id), None),
         };
-        let response: Response<Operation> = self.transport.get(&path).await?;
+        let response: Response<Operation> =
+            transport::execute(&self.config, Method::GET, deadline, || {
+                self.transport.get(&path)
+            })
+            .await?;
         Ok(response.metadata)
     }
 
+    /// Connect to one of an operation's websockets
+    pub(crate) async fn operation_websocket(&self, id: &str, secret: &str) -> Result<WebSocket> {
+        let path = format!("/1.0/operations/{}/websocket?secret={}", id, secret);
+        transport::execute(&self.config, Method::GET, Some(self.config.timeout), || {
+            self.transport.websocket(&path)
+        })
+        .await
+    }
+
     /// Cancel an operation
     pub async fn cancel_operation(&self, id: &str) -> Result<()> {
-        let _response: Response<()> = self
-            .transport
-            .delete(&format!("/1.0/operations/{}", id))
-            .await?;
+        let _response: Response<()> = self.delete(&format!("/1.0/operations/{}", id)).await?;
         Ok(())
     }
 
+    // Event endpoints
+
+    /// Subscribe to events
+    ///
+    /// Unless `filter` names a project (or all projects), events are limited
+    /// to the client's project. The returned stream ends when the server
+    /// closes the connection.
+    pub async fn events(&self, filter: &EventFilter) -> Result<EventStream> {
+        let path = filter.path(self.project.as_deref());
+        let socket =
+            transport::execute(&self.config, Method::GET, Some(self.config.timeout), || {
+                self.transport.websocket(&path)
+            })
+            .await?;
+        Ok(EventStream::new(socket))
+    }
+
     // Profile endpoints
 
     /// List all profiles (URLs)
     pub async fn list_profiles(&self) -> Result<Vec<String>> {
         let path = self.path("/1.0/profiles");
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// List all profiles with full details
     pub async fn list_profiles_full(&self) -> Result<Vec<Profile>> {
         let path = self.path("/1.0/profiles?recursion=1");
-        let response: Response<Vec<Profile>> = self.transport.get(&path).await?;
+        let response: Response<Vec<Profile>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Get a specific profile
     pub async fn get_profile(&self, name: &str) -> Result<Profile> {
         let path = self.path(&format!("/1.0/profiles/{}", name));
-        let response: Response<Profile> = self.transport.get(&path).await?;
+        let response: Response<Profile> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Create a new profile
     pub async fn create_profile(&self, request: &ProfilesPost) -> Result<()> {
         let path = self.path("/1.0/profiles");
-        let _response: Response<()> = self.transport.post(&path, request).await?;
+        let _response: Response<()> = self.post(&path, request).await?;
         Ok(())
     }
 
     /// Update a profile
     pub async fn update_profile(&self, name: &str, request: &ProfilePut) -> Result<()> {
         let path = self.path(&format!("/1.0/profiles/{}", name));
-        let _response: Response<()> = self.transport.put(&path, request).await?;
+        let _response: Response<()> = self.put(&path, request).await?;
         Ok(())
     }
 
     /// Delete a profile
     pub async fn delete_profile(&self, name: &str) -> Result<()> {
         let path = self.path(&format!("/1.0/profiles/{}", name));
-        let _response: Response<()> = self.transport.delete(&path).await?;
+        let _response: Response<()> = self.delete(&path).await?;
         Ok(())
     }
 
@@ -434,36 +1062,31 @@ impl Client {
 
     /// List all projects (URLs)
     pub async fn list_projects(&self) -> Result<Vec<String>> {
-        let response: Response<Vec<String>> = self.transport.get("/1.0/projects").await?;
+        let response: Response<Vec<String>> = self.get("/1.0/projects").await?;
         Ok(response.metadata)
     }
 
     /// List all projects with full details
     pub async fn list_projects_full(&self) -> Result<Vec<Project>> {
-        let response: Response<Vec<Project>> =
-            self.transport.get("/1.0/projects?recursion=1").await?;
+        let response: Response<Vec<Project>> = self.get("/1.0/projects?recursion=1").await?;
         Ok(response.metadata)
     }
 
     /// Get a specific project
     pub async fn get_project(&self, name: &str) -> Result<Project> {
-        let response: Response<Project> = self
-            .transport
-            .get(&format!("/1.0/projects/{}", name))
-            .await?;
+        let response: Response<Project> = self.get(&format!("/1.0/projects/{}", name)).await?;
         Ok(response.metadata)
     }
 
     /// Create a new project
     pub async fn create_project(&self, request: &ProjectsPost) -> Result<()> {
-        let _response: Response<()> = self.transport.post("/1.0/projects", request).await?;
+        let _response: Response<()> = self.post("/1.0/projects", request).await?;
         Ok(())
     }
 
     /// Update a project
     pub async fn update_project(&self, name: &str, request: &ProjectPut) -> Result<()> {
         let _response: Response<()> = self
-            .transport
             .put(&format!("/1.0/projects/{}", name), request)
             .await?;
         Ok(())
@@ -471,10 +1094,7 @@ impl Client {
 
     /// Delete a project
     pub async fn delete_project(&self, name: &str) -> Result<()> {
-        let _response: Response<()> = self
-            .transport
-            .delete(&format!("/1.0/projects/{}", name))
-            .await?;
+        let _response: Response<()> = self.delete(&format!("/1.0/projects/{}", name)).await?;
         Ok(())
     }
 
@@ -483,42 +1103,42 @@ impl Client {
     /// List all networks (URLs)
     pub async fn list_networks(&self) -> Result<Vec<String>> {
         let path = self.path("/1.0/networks");
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// List all networks with full details
     pub async fn list_networks_full(&self) -> Result<Vec<Network>> {
         let path = self.path("/1.0/networks?recursion=1");
-        let response: Response<Vec<Network>> = self.transport.get(&path).await?;
+        let response: Response<Vec<Network>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Get a specific network
     pub async fn get_network(&self, name: &str) -> Result<Network> {
         let path = self.path(&format!("/1.0/networks/{}", name));
-        let response: Response<Network> = self.transport.get(&path).await?;
+        let response: Response<Network> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Create a new network
     pub async fn create_network(&self, request: &NetworksPost) -> Result<()> {
         let path = self.path("/1.0/networks");
-        let _response: Response<()> = self.transport.post(&path, request).await?;
+        let _response: Response<()> = self.post(&path, request).await?;
         Ok(())
     }
 
     /// Update a network
     pub async fn update_network(&self, name: &str, request: &NetworkPut) -> Result<()> {
         let path = self.path(&format!("/1.0/networks/{}", name));
-        let _response: Response<()> = self.transport.put(&path, request).await?;
+        let _response: Response<()> = self.put(&path, request).await?;
         Ok(())
     }
 
     /// Delete a network
     pub async fn delete_network(&self, name: &str) -> Result<()> {
         let path = self.path(&format!("/1.0/networks/{}", name));
-        let _response: Response<()> = self.transport.delete(&path).await?;
+        let _response: Response<()> = self.delete(&path).await?;
         Ok(())
     }
 
@@ -527,42 +1147,42 @@ impl Client {
     /// List all storage pools (URLs)
     pub async fn list_storage_pools(&self) -> Result<Vec<String>> {
         let path = self.path("/1.0/storage-pools");
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// List all storage pools with full details
     pub async fn list_storage_pools_full(&self) -> Result<Vec<StoragePool>> {
         let path = self.path("/1.0/storage-pools?recursion=1");
-        let response: Response<Vec<StoragePool>> = self.transport.get(&path).await?;
+        let response: Response<Vec<StoragePool>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Get a specific storage pool
     pub async fn get_storage_pool(&self, name: &str) -> Result<StoragePool> {
         let path = self.path(&format!("/1.0/storage-pools/{}", name));
-        let response: Response<StoragePool> = self.transport.get(&path).await?;
+        let response: Response<StoragePool> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// Create a new storage pool
     pub async fn create_storage_pool(&self, request: &StoragePoolsPost) -> Result<()> {
         let path = self.path("/1.0/storage-pools");
-        let _response: Response<()> = self.transport.post(&path, request).await?;
+        let _response: Response<()> = self.post(&path, request).await?;
         Ok(())
     }
 
     /// Update a storage pool
     pub async fn update_storage_pool(&self, name: &str, request: &StoragePoolPut) -> Result<()> {
         let path = self.path(&format!("/1.0/storage-pools/{}", name));
-        let _response: Response<()> = self.transport.put(&path, request).await?;
+        let _response: Response<()> = self.put(&path, request).await?;
         Ok(())
     }
 
     /// Delete a storage pool
     pub async fn delete_storage_pool(&self, name: &str) -> Result<()> {
         let path = self.path(&format!("/1.0/storage-pools/{}", name));
-        let _response: Response<()> = self.transport.delete(&path).await?;
+        let _response: Response<()> = self.delete(&path).await?;
         Ok(())
     }
 
@@ -571,14 +1191,14 @@ impl Client {
     /// List all volumes in a storage pool (URLs)
     pub async fn list_storage_volumes(&self, pool: &str) -> Result<Vec<String>> {
         let path = self.path(&format!("/1.0/storage-pools/{}/volumes", pool));
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
     /// List all volumes in a storage pool with full details
     pub async fn list_storage_volumes_full(&self, pool: &str) -> Result<Vec<StorageVolume>> {
         let path = self.path(&format!("/1.0/storage-pools/{}/volumes?recursion=1", pool));
-        let response: Response<Vec<StorageVolume>> = self.transport.get(&path).await?;
+        let response: Response<Vec<StorageVolume>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
@@ -593,7 +1213,7 @@ impl Client {
             "/1.0/storage-pools/{}/volumes/{}/{}",
             pool, volume_type, name
         ));
-        let response: Response<StorageVolume> = self.transport.get(&path).await?;
+        let response: Response<StorageVolume> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
@@ -604,7 +1224,7 @@ impl Client {
         request: &StorageVolumesPost,
     ) -> Result<()> {
         let path = self.path(&format!("/1.0/storage-pools/{}/volumes", pool));
-        let _response: Response<()> = self.transport.post(&path, request).await?;
+        let _response: Response<()> = self.post(&path, request).await?;
         Ok(())
     }
 
@@ -620,7 +1240,7 @@ impl Client {
             "/1.0/storage-pools/{}/volumes/{}/{}",
             pool, volume_type, name
         ));
-        let _response: Response<()> = self.transport.put(&path, request).await?;
+        let _response: Response<()> = self.put(&path, request).await?;
         Ok(())
     }
 
@@ -635,7 +1255,7 @@ impl Client {
             "/1.0/storage-pools/{}/volumes/{}/{}",
             pool, volume_type, name
         ));
-        let _response: Response<()> = self.transport.delete(&path).await?;
+        let _response: Response<()> = self.delete(&path).await?;
         Ok(())
     }
 
@@ -644,7 +1264,7 @@ impl Client {
     /// List all snapshots for an instance (URLs)
     pub async fn list_instance_snapshots(&self, instance: &str) -> Result<Vec<String>> {
         let path = self.path(&format!("/1.0/instances/{}/snapshots", instance));
-        let response: Response<Vec<String>> = self.transport.get(&path).await?;
+        let response: Response<Vec<String>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
@@ -657,7 +1277,7 @@ impl Client {
             "/1.0/instances/{}/snapshots?recursion=1",
             instance
         ));
-        let response: Response<Vec<InstanceSnapshot>> = self.transport.get(&path).await?;
+        let response: Response<Vec<InstanceSnapshot>> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
@@ -671,7 +1291,7 @@ impl Client {
             "/1.0/instances/{}/snapshots/{}",
             instance, snapshot
         ));
-        let response: Response<InstanceSnapshot> = self.transport.get(&path).await?;
+        let response: Response<InstanceSnapshot> = self.get(&path).await?;
         Ok(response.metadata)
     }
 
@@ -680,10 +1300,10 @@ impl Client {
         &self,
         instance: &str,
         request: &InstanceSnapshotsPost,
-    ) -> Result<Operation> {
+    ) -> Result<OperationHandle<'_>> {
         let path = self.path(&format!("/1.0/instances/{}/snapshots", instance));
-        let response: Response<Operation> = self.transport.post(&path, request).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.post(&path, request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
     }
 
     /// Delete an instance snapshot
@@ -691,13 +1311,13 @@ impl Client {
         &self,
         instance: &str,
         snapshot: &str,
-    ) -> Result<Operation> {
+    ) -> Result<OperationHandle<'_>> {
         let path = self.path(&format!(
             "/1.0/instances/{}/snapshots/{}",
             instance, snapshot
         ));
-        let response: Response<Operation> = self.transport.delete(&path).await?;
-        Ok(response.metadata)
+        let response: Response<Operation> = self.delete(&path).await?;
+        Ok(OperationHandle::new(self, response.metadata))
     }
 
     /// Restore an instance from a snapshot
@@ -705,12 +1325,221 @@ impl Client {
         &self,
         instance: &str,
         snapshot: &str,
-    ) -> Result<Operation> {
+    ) -> Result<OperationHandle<'_>> {
         let path = self.path(&format!("/1.0/instances/{}", instance));
         let request = serde_json::json!({
             "restore": snapshot
         });
-        let response: Response<Operation> = self.transport.put(&path, &request).await?;
+        let response: Response<Operation> = self.put(&path, &request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    // Instance backup endpoints
+
+    /// List all backups of an instance (URLs)
+    pub async fn list_instance_backups(&self, instance: &str) -> Result<Vec<String>> {
+        let path = self.path(&format!("/1.0/instances/{}/backups", instance));
+        let response: Response<Vec<String>> = self.get(&path).await?;
+        Ok(response.metadata)
+    }
+
+    /// List all backups of an instance with full details
+    pub async fn list_instance_backups_full(&self, instance: &str) -> Result<Vec<InstanceBackup>> {
+        let path = self.path(&format!("/1.0/instances/{}/backups?recursion=1", instance));
+        let response: Response<Vec<InstanceBackup>> = self.get(&path).await?;
         Ok(response.metadata)
     }
+
+    /// Get a specific instance backup
+    pub async fn get_instance_backup(
+        &self,
+        instance: &str,
+        backup: &str,
+    ) -> Result<InstanceBackup> {
+        let path = self.path(&format!("/1.0/instances/{}/backups/{}", instance, backup));
+        let response: Response<InstanceBackup> = self.get(&path).await?;
+        Ok(response.metadata)
+    }
+
+    /// Create a backup of an instance
+    pub async fn create_instance_backup(
+        &self,
+        instance: &str,
+        request: &InstanceBackupsPost,
+    ) -> Result<OperationHandle<'_>> {
+        let path = self.path(&format!("/1.0/instances/{}/backups", instance));
+        let response: Response<Operation> = self.post(&path, request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Rename an instance backup
+    pub async fn rename_instance_backup(
+        &self,
+        instance: &str,
+        backup: &str,
+        new_name: &str,
+    ) -> Result<OperationHandle<'_>> {
+        #[cfg(feature = "generated")]
+        let request = InstanceBackupPost {
+            name: Some(new_name.to_string()),
+        };
+        #[cfg(not(feature = "generated"))]
+        let request = InstanceBackupPost::rename(new_name);
+
+        let path = self.path(&format!("/1.0/instances/{}/backups/{}", instance, backup));
+        let response: Response<Operation> = self.post(&path, &request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Delete an instance backup
+    pub async fn delete_instance_backup(
+        &self,
+        instance: &str,
+        backup: &str,
+    ) -> Result<OperationHandle<'_>> {
+        let path = self.path(&format!("/1.0/instances/{}/backups/{}", instance, backup));
+        let response: Response<Operation> = self.delete(&path).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Download a backup tarball into `writer`, returning the number of bytes written
+    ///
+    /// The tarball is streamed, so it is never held in memory.
+    pub async fn export_instance_backup<W: AsyncWrite + Unpin>(
+        &self,
+        instance: &str,
+        backup: &str,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let path = self.path(&format!(
+            "/1.0/instances/{}/backups/{}/export",
+            instance, backup
+        ));
+        self.download(&path).await?.copy_to(writer).await
+    }
+
+    /// Create an instance from a backup tarball streamed from `tarball`
+    pub async fn import_instance_backup(
+        &self,
+        tarball: impl AsyncRead + Send + 'static,
+        options: &BackupImportOptions,
+    ) -> Result<OperationHandle<'_>> {
+        let path = self.path("/1.0/instances");
+        let mut request =
+            RawRequest::new(Method::POST, path).body(RequestBody::from_reader(tarball));
+        request.headers = options.headers();
+        let body = self.send_raw(request).await?.body.bytes().await?;
+        let response: Response<Operation> = serde_json::from_slice(&body)?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    // Instance rename, move, copy and migration endpoints
+
+    /// Send a raw rename, move or migration request for an instance
+    pub async fn post_instance(
+        &self,
+        name: &str,
+        request: &InstancePost,
+    ) -> Result<OperationHandle<'_>> {
+        let path = self.path(&format!("/1.0/instances/{}", name));
+        let response: Response<Operation> = self.post(&path, request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Rename an instance
+    pub async fn rename_instance(&self, name: &str, new_name: &str) -> Result<OperationHandle<'_>> {
+        #[cfg(feature = "generated")]
+        let request = InstancePost {
+            config: None,
+            devices: None,
+            profiles: None,
+            allow_inconsistent: None,
+            container_only: None,
+            instance_only: None,
+            live: None,
+            migration: None,
+            name: Some(new_name.to_string()),
+            override_snapshot_profiles: None,
+            pool: None,
+            project: None,
+            target: None,
+        };
+        #[cfg(not(feature = "generated"))]
+        let request = InstancePost::rename(new_name);
+        self.post_instance(name, &request).await
+    }
+
+    /// Move an instance to another storage pool, project or cluster member
+    ///
+    /// With only a new name set, the instance is renamed in place.
+    pub async fn move_instance(
+        &self,
+        name: &str,
+        options: &MoveOptions,
+    ) -> Result<OperationHandle<'_>> {
+        let mut path = format!("/1.0/instances/{}", name);
+        if let Some(target) = &options.target {
+            let target: String = url::form_urlencoded::byte_serialize(target.as_bytes()).collect();
+            path = format!("{}?target={}", path, target);
+        }
+        let path = self.path(&path);
+        let request = migration::move_request(name, options)?;
+        let response: Response<Operation> = self.post(&path, &request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Migrate an instance to the server `destination` is connected to
+    ///
+    /// The instance is created on `destination` with the source's
+    /// configuration and deleted from this server once both sides have
+    /// finished. The server that opens the connection must be able to reach
+    /// the other one over HTTPS.
+    pub async fn migrate_instance(
+        &self,
+        name: &str,
+        destination: &Client,
+        options: &MigrationOptions,
+    ) -> Result<()> {
+        migration::migrate(self, name, destination, options).await
+    }
+
+    /// Copy an instance, or one of its snapshots, to `name` on the same server
+    ///
+    /// With [`CopyOptions::with_refresh`], an existing copy is brought up to
+    /// date by transferring only what changed since the last copy.
+    pub async fn copy_instance(
+        &self,
+        source: &str,
+        name: &str,
+        options: &CopyOptions,
+    ) -> Result<OperationHandle<'_>> {
+        let (path, source_project) = match &options.project {
+            Some(project) => (
+                format!("/1.0/instances?project={}", project),
+                Some(self.project.as_deref().unwrap_or("default")),
+            ),
+            None => (self.path("/1.0/instances"), None),
+        };
+        let request = migration::copy_request(source, name, source_project, options);
+        let response: Response<Operation> = self.post(&path, &request).await?;
+        Ok(OperationHandle::new(self, response.metadata))
+    }
+
+    /// Copy an instance, or one of its snapshots, to `name` on the server
+    /// `destination` is connected to
+    ///
+    /// The copy is created with the source's configuration, minus the
+    /// `volatile.*` keys other than `volatile.base_image`, and the call
+    /// returns once both sides have finished. With
+    /// [`CopyOptions::with_refresh`], an existing copy is brought up to date
+    /// by transferring only what changed since the last copy.
+    pub async fn copy_instance_to(
+        &self,
+        source: &str,
+        destination: &Client,
+        name: &str,
+        options: &CopyOptions,
+    ) -> Result<()> {
+        migration::copy_to(self, source, destination, name, options).await
+    }
 }
@@ -0,0 +1,679 @@
+//! Instance moves, copies and migrations between servers
+//!
+//! Moves within a server or cluster are a single `POST /1.0/instances/{name}`,
+//! and copies a `copy` instance source. Migrating or copying to a separate
+//! server pairs a `POST` on the source with a `migration` instance source on
+//! the destination: in pull mode the destination connects to the source's
+//! migration websockets, in push mode the source connects to the
+//! destination's.
+
+use crate::{Client, Error, OperationHandle, Result};
+use lxd_types::{
+    Instance, InstancePost, InstancePostTarget, InstanceSnapshot, InstanceSource, InstancesPost,
+    Operation, Response, Server,
+};
+use std::collections::BTreeMap;
+
+/// Which side opens the migration connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MigrationMode {
+    /// The destination connects to the source
+    #[default]
+    Pull,
+    /// The source connects to the destination
+    Push,
+}
+
+impl MigrationMode {
+    /// Mode name as used in the instance source
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pull => "pull",
+            Self::Push => "push",
+        }
+    }
+}
+
+/// Where to move an instance on the same server or cluster
+///
+/// Unset values leave the instance where it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveOptions {
+    /// New instance name
+    pub name: Option<String>,
+    /// Target storage pool
+    pub pool: Option<String>,
+    /// Target project
+    pub project: Option<String>,
+    /// Target cluster member
+    pub target: Option<String>,
+    /// Whether to move a running instance without stopping it
+    pub live: bool,
+    /// Whether to leave snapshots behind
+    pub instance_only: bool,
+}
+
+impl MoveOptions {
+    /// Create options that leave the instance where it is
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename the instance as it moves
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Move to another storage pool
+    pub fn with_pool(mut self, pool: impl Into<String>) -> Self {
+        self.pool = Some(pool.into());
+        self
+    }
+
+    /// Move to another project
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    /// Move to another cluster member
+    pub fn with_target(mut self, member: impl Into<String>) -> Self {
+        self.target = Some(member.into());
+        self
+    }
+
+    /// Set whether a running instance moves without stopping
+    pub fn with_live(mut self, live: bool) -> Self {
+        self.live = live;
+        self
+    }
+
+    /// Set whether snapshots are left behind
+    pub fn with_instance_only(mut self, instance_only: bool) -> Self {
+        self.instance_only = instance_only;
+        self
+    }
+}
+
+/// How to migrate an instance to another server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationOptions {
+    /// Which side opens the migration connection
+    pub mode: MigrationMode,
+    /// Name on the destination, if different
+    pub name: Option<String>,
+    /// Whether to migrate a running instance without stopping it
+    pub live: bool,
+    /// Whether to leave snapshots behind
+    pub instance_only: bool,
+}
+
+impl MigrationOptions {
+    /// Create options for a cold pull-mode migration under the same name
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set which side opens the migration connection
+    pub fn with_mode(mut self, mode: MigrationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Use a different name on the destination
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set whether a running instance migrates without stopping
+    pub fn with_live(mut self, live: bool) -> Self {
+        self.live = live;
+        self
+    }
+
+    /// Set whether snapshots are left behind
+    pub fn with_instance_only(mut self, instance_only: bool) -> Self {
+        self.instance_only = instance_only;
+        self
+    }
+}
+
+/// How to copy an instance, on the same server or to another one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyOptions {
+    /// Copy this snapshot instead of the instance's current state
+    pub snapshot: Option<String>,
+    /// Project to create the copy in, for copies on the same server
+    pub project: Option<String>,
+    /// Whether to leave snapshots behind
+    pub instance_only: bool,
+    /// Whether to update an existing copy with only what changed
+    pub refresh: bool,
+    /// Which side opens the connection, for copies to another server
+    pub mode: MigrationMode,
+}
+
+impl CopyOptions {
+    /// Create options for a full copy of the instance with its snapshots
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy a snapshot instead of the instance's current state
+    pub fn with_snapshot(mut self, snapshot: impl Into<String>) -> Self {
+        self.snapshot = Some(snapshot.into());
+        self
+    }
+
+    /// Create the copy in another project on the same server
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    /// Set whether snapshots are left behind
+    pub fn with_instance_only(mut self, instance_only: bool) -> Self {
+        self.instance_only = instance_only;
+        self
+    }
+
+    /// Set whether an existing copy is updated with only what changed
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Set which side opens the connection for copies to another server
+    pub fn with_mode(mut self, mode: MigrationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+/// Build the request for a move on the same server
+///
+/// Without a pool, project or target member the move is a plain rename; LXD
+/// would otherwise treat the request as the source of a pull migration and
+/// wait for a peer that never connects.
+pub(crate) fn move_request(name: &str, options: &MoveOptions) -> Result<InstancePost> {
+    let relocates = options.pool.is_some() || options.project.is_some() || options.target.is_some();
+    let new_name = options.name.clone().unwrap_or_else(|| name.to_string());
+    if !relocates {
+        if options.name.is_none() {
+            return Err(Error::request(format!(
+                "Moving {} needs a new name, pool, project or target",
+                name
+            )));
+        }
+        let mut request = instance_post(new_name, false, false, None);
+        request.migration = None;
+        return Ok(request);
+    }
+    let mut request = instance_post(new_name, options.live, options.instance_only, None);
+    request.pool = options.pool.clone();
+    request.project = options.project.clone();
+    Ok(request)
+}
+
+/// Migrate `name` from `source` to `destination`, then delete it from `source`
+pub(crate) async fn migrate(
+    source: &Client,
+    name: &str,
+    destination: &Client,
+    options: &MigrationOptions,
+) -> Result<()> {
+    let instance = source.get_instance(name).await?;
+    let target_name = options.name.as_deref().unwrap_or(name);
+    let path = source.path(&format!("/1.0/instances/{}", name));
+    let send = instance_post(name.to_string(), options.live, options.instance_only, None);
+    let receive = migration_source(options.mode, options.live, options.instance_only);
+    transfer(
+        source,
+        &path,
+        send,
+        destination,
+        options.mode,
+        receive,
+        |from| create_request(&instance, target_name, from),
+    )
+    .await?;
+    source.delete_instance(name).await?.wait().await?;
+    Ok(())
+}
+
+/// Build the request for a copy on the same server
+pub(crate) fn copy_request(
+    source: &str,
+    name: &str,
+    source_project: Option<&str>,
+    options: &CopyOptions,
+) -> InstancesPost {
+    let mut from = match &options.snapshot {
+        Some(snapshot) => copy_source(format!("{}/{}", source, snapshot)),
+        None => copy_source(source.to_string()),
+    };
+    from.instance_only = options.instance_only.then_some(true);
+    from.refresh = options.refresh.then_some(true);
+    from.project = source_project.map(str::to_string);
+    copy_post(name, from)
+}
+
+/// Copy `name` (or one of its snapshots) from `source` to `destination` as `target_name`
+pub(crate) async fn copy_to(
+    source: &Client,
+    name: &str,
+    destination: &Client,
+    target_name: &str,
+    options: &CopyOptions,
+) -> Result<()> {
+    let instance = source.get_instance(name).await?;
+    let mut receive = migration_source(options.mode, false, options.instance_only);
+    receive.refresh = options.refresh.then_some(true);
+
+    match &options.snapshot {
+        Some(snapshot) => {
+            let config = source.get_instance_snapshot(name, snapshot).await?;
+            let path = source.path(&format!("/1.0/instances/{}/snapshots/{}", name, snapshot));
+            let send = instance_post(target_name.to_string(), false, false, None);
+            transfer(
+                source,
+                &path,
+                send,
+                destination,
+                options.mode,
+                receive,
+                |from| {
+                    let mut request = create_request(&instance, target_name, from);
+                    snapshot_config(&mut request, &config);
+                    strip_volatile(&mut request);
+                    request
+                },
+            )
+            .await
+        }
+        None => {
+            let path = source.path(&format!("/1.0/instances/{}", name));
+            let send = instance_post(name.to_string(), false, options.instance_only, None);
+            transfer(
+                source,
+                &path,
+                send,
+                destination,
+                options.mode,
+                receive,
+                |from| {
+                    let mut request = create_request(&instance, target_name, from);
+                    strip_volatile(&mut request);
+                    request
+                },
+            )
+            .await
+        }
+    }
+}
+
+/// Send the instance or snapshot at `path` from `source` to `destination`
+///
+/// `send` is POSTed to `path` on the source and `create(receive)` to
+/// `/1.0/instances` on the destination, with the connection details of the
+/// side that is connected to filled in. That side's address is looked up
+/// before anything starts; if either request or either side's operation
+/// fails, the operation on the other side is cancelled.
+async fn transfer(
+    source: &Client,
+    path: &str,
+    mut send: InstancePost,
+    destination: &Client,
+    mode: MigrationMode,
+    mut receive: InstanceSource,
+    create: impl FnOnce(InstanceSource) -> InstancesPost,
+) -> Result<()> {
+    match mode {
+        MigrationMode::Pull => {
+            let (url, certificate) = endpoint(source).await?;
+            let sending = start(source, path, &send).await?;
+
+            receive.operation = Some(format!("{}/1.0/operations/{}", url, sending.id()));
+            receive.certificate = Some(certificate);
+            receive.secrets = Some(secrets(sending.operation()));
+            let receiving = match destination.create_instance(&create(receive)).await {
+                Ok(receiving) => receiving,
+                Err(err) => {
+                    let _ = sending.cancel().await;
+                    return Err(err);
+                }
+            };
+            finish(&sending, &receiving).await?;
+        }
+        MigrationMode::Push => {
+            let (url, certificate) = endpoint(destination).await?;
+            let receiving = destination.create_instance(&create(receive)).await?;
+
+            send.target = Some(InstancePostTarget {
+                certificate: Some(certificate),
+                operation: Some(format!("{}/1.0/operations/{}", url, receiving.id())),
+                secrets: Some(secrets(receiving.operation())),
+            });
+            let sending = match start(source, path, &send).await {
+                Ok(sending) => sending,
+                Err(err) => {
+                    let _ = receiving.cancel().await;
+                    return Err(err);
+                }
+            };
+            finish(&sending, &receiving).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Wait for both sides of a transfer, cancelling one if the other fails
+async fn finish(sending: &OperationHandle<'_>, receiving: &OperationHandle<'_>) -> Result<()> {
+    let sent = sending.wait();
+    let received = receiving.wait();
+    tokio::pin!(sent, received);
+    tokio::select! {
+        result = &mut sent => match result {
+            Ok(_) => received.await.map(drop),
+            Err(err) => {
+                let _ = receiving.cancel().await;
+                Err(err)
+            }
+        },
+        result = &mut received => match result {
+            Ok(_) => sent.await.map(drop),
+            Err(err) => {
+                let _ = sending.cancel().await;
+                Err(err)
+            }
+        },
+    }
+}
+
+/// POST a migration request to `path`, returning the source operation
+async fn start<'c>(
+    client: &'c Client,
+    path: &str,
+    request: &InstancePost,
+) -> Result<OperationHandle<'c>> {
+    let response: Response<Operation> = client.post(path, request).await?;
+    Ok(OperationHandle::new(client, response.metadata))
+}
+
+/// The migration websocket secrets in an operation's metadata
+#[cfg(not(feature = "generated"))]
+fn secrets(operation: &Operation) -> BTreeMap<String, String> {
+    operation
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+/// The migration websocket secrets in an operation's metadata
+#[cfg(feature = "generated")]
+fn secrets(operation: &Operation) -> BTreeMap<String, String> {
+    operation
+        .metadata
+        .iter()
+        .flatten()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+/// The URL and certificate the other side of a migration uses to reach `client`'s server
+async fn endpoint(client: &Client) -> Result<(String, String)> {
+    let server = client.get_server().await?;
+    let (addresses, certificate) = environment(&server);
+    let url = match client.transport.base_url() {
+        Some(url) => url.to_string(),
+        None => {
+            let address = addresses.first().ok_or_else(|| {
+                Error::Config(
+                    "Server does not listen on the network (core.https_address is unset)"
+                        .to_string(),
+                )
+            })?;
+            format!("https://{}", address)
+        }
+    };
+    Ok((url, certificate))
+}
+
+/// A `migration` instance source, without connection details
+fn migration_source(mode: MigrationMode, live: bool, instance_only: bool) -> InstanceSource {
+    let mut source = source_of_mode(mode);
+    source.live = live.then_some(true);
+    source.instance_only = instance_only.then_some(true);
+    source
+}
+
+#[cfg(not(feature = "generated"))]
+fn source_of_mode(mode: MigrationMode) -> InstanceSource {
+    InstanceSource::from_migration(mode.as_str())
+}
+
+#[cfg(feature = "generated")]
+fn source_of_mode(mode: MigrationMode) -> InstanceSource {
+    let mut source = source_of_type("migration");
+    source.mode = Some(mode.as_str().to_string());
+    source
+}
+
+/// An instance source of `kind` with nothing else set
+#[cfg(feature = "generated")]
+fn source_of_type(kind: &str) -> InstanceSource {
+    InstanceSource {
+        alias: None,
+        allow_inconsistent: None,
+        base_image: None,
+        certificate: None,
+        container_only: None,
+        conversion_options: None,
+        fingerprint: None,
+        instance_only: None,
+        live: None,
+        mode: None,
+        operation: None,
+        override_snapshot_profiles: None,
+        project: None,
+        properties: None,
+        protocol: None,
+        refresh: None,
+        secret: None,
+        secrets: None,
+        server: None,
+        source: None,
+        source_disk_size: None,
+        kind: Some(kind.to_string()),
+    }
+}
+
+#[cfg(not(feature = "generated"))]
+fn instance_post(
+    name: String,
+    live: bool,
+    instance_only: bool,
+    target: Option<InstancePostTarget>,
+) -> InstancePost {
+    InstancePost {
+        name: Some(name),
+        migration: Some(true),
+        live: live.then_some(true),
+        instance_only: instance_only.then_some(true),
+        pool: None,
+        project: None,
+        target,
+    }
+}
+
+#[cfg(feature = "generated")]
+fn instance_post(
+    name: String,
+    live: bool,
+    instance_only: bool,
+    target: Option<InstancePostTarget>,
+) -> InstancePost {
+    InstancePost {
+        config: None,
+        devices: None,
+        profiles: None,
+        allow_inconsistent: None,
+        container_only: None,
+        instance_only: instance_only.then_some(true),
+        live: live.then_some(true),
+        migration: Some(true),
+        name: Some(name),
+        override_snapshot_profiles: None,
+        pool: None,
+        project: None,
+        target,
+    }
+}
+
+#[cfg(not(feature = "generated"))]
+fn copy_source(source: String) -> InstanceSource {
+    InstanceSource::from_copy(source)
+}
+
+#[cfg(feature = "generated")]
+fn copy_source(source: String) -> InstanceSource {
+    let mut from = source_of_type("copy");
+    from.source = Some(source);
+    from
+}
+
+#[cfg(not(feature = "generated"))]
+fn copy_post(name: &str, source: InstanceSource) -> InstancesPost {
+    InstancesPost::new(name, source)
+}
+
+#[cfg(feature = "generated")]
+fn copy_post(name: &str, source: InstanceSource) -> InstancesPost {
+    InstancesPost {
+        architecture: None,
+        config: None,
+        description: None,
+        devices: None,
+        ephemeral: None,
+        instance_type: None,
+        name: Some(name.to_string()),
+        profiles: None,
+        restore: None,
+        restore_disk_volumes_mode: None,
+        source: Some(source),
+        start: None,
+        stateful: None,
+        kind: None,
+    }
+}
+
+/// Drop the `volatile.*` keys a copy must not share with the original
+///
+/// These hold per-instance state such as `volatile.uuid` and the NIC MAC
+/// addresses; only `volatile.base_image` describes the copy too.
+fn strip_volatile(request: &mut InstancesPost) {
+    if let Some(config) = &mut request.config {
+        config.retain(|key, _| !key.starts_with("volatile.") || key == "volatile.base_image");
+    }
+}
+
+/// Replace the instance configuration in `request` with the one in `snapshot`
+#[cfg(not(feature = "generated"))]
+fn snapshot_config(request: &mut InstancesPost, snapshot: &InstanceSnapshot) {
+    request.architecture = Some(snapshot.architecture.clone());
+    request.ephemeral = Some(snapshot.ephemeral);
+    request.config = Some(snapshot.config.clone());
+    request.devices = Some(snapshot.devices.clone());
+    request.profiles = Some(snapshot.profiles.clone());
+}
+
+/// Replace the instance configuration in `request` with the one in `snapshot`
+#[cfg(feature = "generated")]
+fn snapshot_config(request: &mut InstancesPost, snapshot: &InstanceSnapshot) {
+    request.architecture = snapshot.architecture.clone();
+    request.ephemeral = snapshot.ephemeral;
+    request.config = snapshot.config.clone();
+    request.devices = snapshot.devices.clone();
+    request.profiles = snapshot.profiles.clone();
+}
+
+/// The request creating `name` on the destination with the configuration of `instance`
+#[cfg(not(feature = "generated"))]
+fn create_request(instance: &Instance, name: &str, source: InstanceSource) -> InstancesPost {
+    let mut request = InstancesPost::new(name, source).with_type(instance.instance_type);
+    request.description = Some(instance.description.clone());
+    request.architecture = Some(instance.architecture.clone());
+    request.ephemeral = Some(instance.ephemeral);
+    request.config = Some(instance.config.clone());
+    request.devices = Some(instance.devices.clone());
+    request.profiles = Some(instance.profiles.clone());
+    request
+}
+
+/// The request creating `name` on the destination with the configuration of `instance`
+#[cfg(feature = "generated")]
+fn create_request(instance: &Instance, name: &str, source: InstanceSource) -> InstancesPost {
+    InstancesPost {
+        architecture: instance.architecture.clone(),
+        config: instance.config.clone(),
+        description: instance.description.clone(),
+        devices: instance.devices.clone(),
+        ephemeral: instance.ephemeral,
+        instance_type: None,
+        name: Some(name.to_string()),
+        profiles: instance.profiles.clone(),
+        restore: None,
+        restore_disk_volumes_mode: None,
+        source: Some(source),
+        start: None,
+        stateful: None,
+        kind: instance.kind.clone(),
+    }
+}
+
+#[cfg(not(feature = "generated"))]
+fn environment(server: &Server) -> (Vec<String>, String) {
+    (
+        server.environment.addresses.clone(),
+        server.environment.certificate.clone(),
+    )
+}
+
+#[cfg(feature = "generated")]
+fn environment(server: &Server) -> (Vec<String>, String) {
+    let environment = server.environment.as_ref();
+    (
+        environment
+            .and_then(|environment| environment.addresses.clone())
+            .unwrap_or_default(),
+        environment
+            .and_then(|environment| environment.certificate.clone())
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_skip_non_strings() {
+        let operation: Operation = serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "class": "websocket",
+            "status": "Running",
+            "status_code": 103,
+            "metadata": {"control": "c", "fs": "f", "criu": null}
+        }))
+        .unwrap();
+        let secrets = secrets(&operation);
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(secrets["fs"], "f");
+    }
+}
@@ -0,0 +1,303 @@
+//! Handles for background operations
+
+use crate::{Client, Error, Result};
+use lxd_types::{Operation, OperationStatus};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A background operation started by an async endpoint
+///
+/// Endpoints such as [`Client::create_instance`] return a handle instead of
+/// the raw [`Operation`] so the caller can wait for, inspect or cancel it.
+pub struct OperationHandle<'a> {
+    client: &'a Client,
+    operation: Operation,
+}
+
+impl<'a> OperationHandle<'a> {
+    /// Wrap an operation returned by `client`
+    pub fn new(client: &'a Client, operation: Operation) -> Self {
+        Self { client, operation }
+    }
+
+    /// Operation ID
+    pub fn id(&self) -> &str {
+        id(&self.operation)
+    }
+
+    /// The operation as returned when it was started
+    pub fn operation(&self) -> &Operation {
+        &self.operation
+    }
+
+    /// Consume the handle, returning the operation as it was started
+    pub fn into_operation(self) -> Operation {
+        self.operation
+    }
+
+    /// Fetch the current state of the operation
+    pub async fn refresh(&self) -> Result<Operation> {
+        self.client.get_operation(self.id()).await
+    }
+
+    /// Wait for the operation to finish
+    ///
+    /// Fails with [`Error::OperationFailed`] if the operation fails or is
+    /// cancelled.
+    pub async fn wait(&self) -> Result<Operation> {
+        let operation = self.client.wait_operation(self.id(), Some(-1)).await?;
+        finished(operation)
+    }
+
+    /// Wait up to `timeout` for the operation to finish
+    ///
+    /// Fails with [`Error::Timeout`] if it is still running afterwards, and
+    /// with [`Error::OperationFailed`] if it fails or is cancelled.
+    pub async fn wait_timeout(&self, timeout: Duration) -> Result<Operation> {
+        let secs = timeout.as_secs_f64().ceil() as i64;
+        let operation = self.client.wait_operation(self.id(), Some(secs)).await?;
+        if !operation.is_complete() {
+            return Err(Error::Timeout);
+        }
+        finished(operation)
+    }
+
+    /// Cancel the operation
+    pub async fn cancel(&self) -> Result<()> {
+        self.client.cancel_operation(self.id()).await
+    }
+
+    /// Fetch the current progress of the operation, if it reports any
+    pub async fn progress(&self) -> Result<Option<OperationProgress>> {
+        let operation = self.refresh().await?;
+        Ok(OperationProgress::from_operation(&operation))
+    }
+
+    /// Resources affected by the operation
+    pub fn resources(&self) -> Vec<OperationResource> {
+        resources(&self.operation)
+            .values()
+            .flatten()
+            .map(|url| OperationResource::parse(url))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for OperationHandle<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OperationHandle")
+            .field("operation", &self.operation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Progress reported in an operation's metadata
+///
+/// LXD reports progress as strings under keys ending in `_progress`, such as
+/// `download_progress: "rootfs: 45% (12.30MB/s)"` or
+/// `create_instance_from_image_unpack_progress: "Unpack: 80%"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationProgress {
+    /// Metadata key without the `_progress` suffix (e.g. `download`)
+    pub stage: String,
+    /// Raw progress text
+    pub text: String,
+    /// Completion percentage, if reported
+    pub percent: Option<u8>,
+    /// Transfer speed (e.g. `12.30MB/s`), if reported
+    pub speed: Option<String>,
+}
+
+impl OperationProgress {
+    /// Parse the progress of an operation from its metadata
+    pub fn from_operation(operation: &Operation) -> Option<Self> {
+        #[cfg(feature = "generated")]
+        let metadata = operation.metadata.as_ref()?;
+        #[cfg(not(feature = "generated"))]
+        let metadata = operation.metadata.as_ref()?.as_object()?;
+
+        metadata.iter().find_map(|(key, value)| {
+            let stage = key.strip_suffix("_progress")?;
+            Some(Self::parse(stage, value.as_str()?))
+        })
+    }
+
+    /// Parse a single progress string
+    pub fn parse(stage: &str, text: &str) -> Self {
+        let percent = text.find('%').and_then(|end| {
+            let start = text[..end]
+                .rfind(|c: char| !c.is_ascii_digit())
+                .map_or(0, |i| i + 1);
+            text[start..end].parse().ok()
+        });
+        let speed = text
+            .rfind('(')
+            .and_then(|start| text[start + 1..].strip_suffix(')'))
+            .map(str::to_string);
+
+        Self {
+            stage: stage.to_string(),
+            text: text.to_string(),
+            percent,
+            speed,
+        }
+    }
+}
+
+/// A resource affected by an operation, parsed from its API URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperationResource {
+    /// `/1.0/instances/<name>`
+    Instance(String),
+    /// `/1.0/instances/<instance>/snapshots/<name>`
+    InstanceSnapshot {
+        /// Instance name
+        instance: String,
+        /// Snapshot name
+        name: String,
+    },
+    /// `/1.0/instances/<instance>/backups/<name>`
+    InstanceBackup {
+        /// Instance name
+        instance: String,
+        /// Backup name
+        name: String,
+    },
+    /// `/1.0/images/<fingerprint>`
+    Image(String),
+    /// `/1.0/storage-pools/<pool>/volumes/<type>/<name>`
+    StorageVolume {
+        /// Storage pool name
+        pool: String,
+        /// Volume type (`custom`, `container`, ...)
+        volume_type: String,
+        /// Volume name
+        name: String,
+    },
+    /// Any other resource URL
+    Other(String),
+}
+
+impl OperationResource {
+    /// Parse a resource URL such as `/1.0/instances/c1?project=web`
+    pub fn parse(url: &str) -> Self {
+        let path = url.split('?').next().unwrap_or(url);
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let owned = |s: &str| s.to_string();
+
+        match segments.as_slice() {
+            ["1.0", "instances", name] => Self::Instance(owned(name)),
+            ["1.0", "instances", instance, "snapshots", name] => Self::InstanceSnapshot {
+                instance: owned(instance),
+                name: owned(name),
+            },
+            ["1.0", "instances", instance, "backups", name] => Self::InstanceBackup {
+                instance: owned(instance),
+                name: owned(name),
+            },
+            ["1.0", "images", fingerprint] => Self::Image(owned(fingerprint)),
+            ["1.0", "storage-pools", pool, "volumes", volume_type, name] => Self::StorageVolume {
+                pool: owned(pool),
+                volume_type: owned(volume_type),
+                name: owned(name),
+            },
+            _ => Self::Other(url.to_string()),
+        }
+    }
+}
+
+/// Turn a finished operation into a result
+fn finished(operation: Operation) -> Result<Operation> {
+    match operation.status() {
+        OperationStatus::Failure => Err(Error::OperationFailed(err(&operation).to_string())),
+        OperationStatus::Cancelled => {
+            Err(Error::OperationFailed("Operation cancelled".to_string()))
+        }
+        _ => Ok(operation),
+    }
+}
+
+// Field accessors for both the hand-written and generated Operation
+
+#[cfg(not(feature = "generated"))]
+fn id(operation: &Operation) -> &str {
+    &operation.id
+}
+
+#[cfg(feature = "generated")]
+fn id(operation: &Operation) -> &str {
+    operation.id.as_deref().unwrap_or_default()
+}
+
+#[cfg(not(feature = "generated"))]
+fn err(operation: &Operation) -> &str {
+    &operation.err
+}
+
+#[cfg(feature = "generated")]
+fn err(operation: &Operation) -> &str {
+    operation.err.as_deref().unwrap_or_default()
+}
+
+/// Look up a key in the operation metadata
+pub(crate) fn metadata<'o>(operation: &'o Operation, key: &str) -> Option<&'o serde_json::Value> {
+    operation.metadata.as_ref()?.get(key)
+}
+
+#[cfg(not(feature = "generated"))]
+fn resources(operation: &Operation) -> &BTreeMap<String, Vec<String>> {
+    &operation.resources
+}
+
+#[cfg(feature = "generated")]
+fn resources(operation: &Operation) -> &BTreeMap<String, Vec<String>> {
+    static EMPTY: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    operation.resources.as_ref().unwrap_or(&EMPTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress() {
+        let progress = OperationProgress::parse("download", "rootfs: 45% (12.30MB/s)");
+        assert_eq!(progress.percent, Some(45));
+        assert_eq!(progress.speed.as_deref(), Some("12.30MB/s"));
+
+        let progress = OperationProgress::parse("create_instance_from_image_unpack", "Unpack: 80%");
+        assert_eq!(progress.percent, Some(80));
+        assert_eq!(progress.speed, None);
+
+        let progress = OperationProgress::parse("fs", "rootfs: 1.20GB (100.00MB/s)");
+        assert_eq!(progress.percent, None);
+        assert_eq!(progress.speed.as_deref(), Some("100.00MB/s"));
+    }
+
+    #[test]
+    fn test_parse_resources() {
+        assert_eq!(
+            OperationResource::parse("/1.0/instances/c1?project=web"),
+            OperationResource::Instance("c1".to_string())
+        );
+        assert_eq!(
+            OperationResource::parse("/1.0/instances/c1/snapshots/snap0"),
+            OperationResource::InstanceSnapshot {
+                instance: "c1".to_string(),
+                name: "snap0".to_string()
+            }
+        );
+        assert_eq!(
+            OperationResource::parse("/1.0/storage-pools/default/volumes/custom/v1"),
+            OperationResource::StorageVolume {
+                pool: "default".to_string(),
+                volume_type: "custom".to_string(),
+                name: "v1".to_string()
+            }
+        );
+        assert_eq!(
+            OperationResource::parse("/1.0/networks/lxdbr0"),
+            OperationResource::Other("/1.0/networks/lxdbr0".to_string())
+        );
+    }
+}
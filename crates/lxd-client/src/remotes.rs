@@ -0,0 +1,263 @@
+//! Remotes configured for the `lxc` CLI
+//!
+//! Reads `config.yml` from the `lxc` configuration directory together with
+//! the `client.crt`/`client.key` identity and the pinned certificates in
+//! `servercerts/`, so a [`Client`] can be created from a remote name.
+
+use crate::{Client, ClientIdentity, Error, Result, ServerVerification};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the remote used when `default-remote` is not set
+const DEFAULT_REMOTE: &str = "local";
+
+/// A remote entry from `config.yml`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Remote {
+    /// Server address (`unix://`, `unix:///path/to/socket` or `https://host:port`)
+    pub addr: String,
+
+    /// Remote protocol (`lxd` or `simplestreams`)
+    #[serde(default)]
+    pub protocol: Option<String>,
+
+    /// Authentication type (`tls` or `oidc`)
+    #[serde(default)]
+    pub auth_type: Option<String>,
+
+    /// Default project for requests to this remote
+    #[serde(default)]
+    pub project: Option<String>,
+
+    /// Whether the remote is public (no authentication)
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// Raw layout of `config.yml`
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(rename = "default-remote", default)]
+    default_remote: Option<String>,
+    #[serde(default)]
+    remotes: BTreeMap<String, Remote>,
+}
+
+/// Remotes configured for the `lxc` CLI
+#[derive(Debug, Clone)]
+pub struct Remotes {
+    config_dir: PathBuf,
+    default_remote: String,
+    remotes: BTreeMap<String, Remote>,
+}
+
+impl Remotes {
+    /// Load remotes from the default `lxc` configuration directory
+    ///
+    /// See [`Remotes::config_dir`] for how the directory is found.
+    pub fn load() -> Result<Self> {
+        let dir = Self::config_dir().ok_or_else(|| {
+            Error::Config("Could not determine the lxc configuration directory".to_string())
+        })?;
+        Self::load_from(dir)
+    }
+
+    /// Load remotes from a specific configuration directory
+    ///
+    /// A missing `config.yml` yields the built-in `local` remote only, as it
+    /// does for `lxc`.
+    pub fn load_from(config_dir: impl AsRef<Path>) -> Result<Self> {
+        let config_dir = config_dir.as_ref().to_path_buf();
+        let path = config_dir.join("config.yml");
+        let file = match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents)
+                .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ConfigFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut remotes = file.remotes;
+        remotes
+            .entry(DEFAULT_REMOTE.to_string())
+            .or_insert_with(|| Remote {
+                addr: "unix://".to_string(),
+                protocol: None,
+                auth_type: None,
+                project: None,
+                public: false,
+            });
+
+        Ok(Self {
+            config_dir,
+            default_remote: file
+                .default_remote
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| DEFAULT_REMOTE.to_string()),
+            remotes,
+        })
+    }
+
+    fn parse(contents: &str) -> std::result::Result<ConfigFile, serde_yaml::Error> {
+        if contents.trim().is_empty() {
+            return Ok(ConfigFile::default());
+        }
+        serde_yaml::from_str(contents)
+    }
+
+    /// Find the `lxc` configuration directory
+    ///
+    /// Checks `LXD_CONF`, then the snap location `~/snap/lxd/common/config`
+    /// if it exists, then `~/.config/lxc`.
+    pub fn config_dir() -> Option<PathBuf> {
+        if let Some(dir) = env::var_os("LXD_CONF").filter(|dir| !dir.is_empty()) {
+            return Some(PathBuf::from(dir));
+        }
+
+        let home = PathBuf::from(env::var_os("HOME")?);
+        let snap = home.join("snap/lxd/common/config");
+        if snap.is_dir() {
+            return Some(snap);
+        }
+        Some(home.join(".config/lxc"))
+    }
+
+    /// Directory the configuration was loaded from
+    pub fn dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    /// Name of the default remote
+    pub fn default_remote(&self) -> &str {
+        &self.default_remote
+    }
+
+    /// Get a remote by name
+    pub fn get(&self, name: &str) -> Option<&Remote> {
+        self.remotes.get(name)
+    }
+
+    /// Iterate over all remotes by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Remote)> {
+        self.remotes
+            .iter()
+            .map(|(name, remote)| (name.as_str(), remote))
+    }
+
+    /// Create a client for the default remote
+    pub fn default_client(&self) -> Result<Client> {
+        self.client(&self.default_remote)
+    }
+
+    /// Create a client for a remote
+    ///
+    /// A trailing colon is accepted, as in `lxc list my-remote:`. Unix socket
+    /// remotes use the local socket; HTTPS remotes present `client.crt` and
+    /// pin `servercerts/<name>.crt` when it exists. The remote's default
+    /// project is applied to the client.
+    pub fn client(&self, name: &str) -> Result<Client> {
+        let name = name.strip_suffix(':').unwrap_or(name);
+        let remote = self
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("Remote '{}' not found", name)))?;
+
+        if let Some(protocol) = remote.protocol.as_deref() {
+            if protocol != "lxd" {
+                return Err(Error::Config(format!(
+                    "Remote '{}' uses the {} protocol, which is not an LXD API server",
+                    name, protocol
+                )));
+            }
+        }
+
+        let client = if let Some(path) = remote.addr.strip_prefix("unix://") {
+            if path.is_empty() {
+                Client::new_unix_socket()?
+            } else {
+                Client::new_unix_socket_path(path)?
+            }
+        } else if remote.addr.starts_with("https://") {
+            self.https_client(name, remote)?
+        } else {
+            return Err(Error::Config(format!(
+                "Remote '{}' has unsupported address '{}'",
+                name, remote.addr
+            )));
+        };
+
+        Ok(match remote.project.as_deref() {
+            Some(project) if !project.is_empty() => client.with_project(project),
+            _ => client,
+        })
+    }
+
+    fn https_client(&self, name: &str, remote: &Remote) -> Result<Client> {
+        match remote.auth_type.as_deref() {
+            None | Some("") | Some("tls") => {}
+            Some(auth_type) => {
+                return Err(Error::Config(format!(
+                    "Remote '{}' uses {} authentication; use Client::new_https_with_auth",
+                    name, auth_type
+                )))
+            }
+        }
+
+        let server_cert = self
+            .config_dir
+            .join("servercerts")
+            .join(format!("{}.crt", name));
+        let verification = if server_cert.exists() {
+            ServerVerification::pinned_certificate_file(&server_cert)?
+        } else {
+            ServerVerification::default()
+        };
+
+        let identity = ClientIdentity::from_files(
+            self.config_dir.join("client.crt"),
+            self.config_dir.join("client.key"),
+        )?;
+        Client::new_https_with_identity(&remote.addr, &identity, verification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let file = Remotes::parse(
+            r#"
+default-remote: prod
+remotes:
+  images:
+    addr: https://images.lxd.canonical.com
+    protocol: simplestreams
+    public: true
+  prod:
+    addr: https://10.0.0.1:8443
+    auth_type: tls
+    project: web
+    protocol: lxd
+    public: false
+aliases: {}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(file.default_remote.as_deref(), Some("prod"));
+        let prod = &file.remotes["prod"];
+        assert_eq!(prod.addr, "https://10.0.0.1:8443");
+        assert_eq!(prod.project.as_deref(), Some("web"));
+        assert!(file.remotes["images"].public);
+    }
+
+    #[test]
+    fn test_parse_empty_config() {
+        let file = Remotes::parse("").unwrap();
+        assert!(file.default_remote.is_none());
+        assert!(file.remotes.is_empty());
+    }
+}
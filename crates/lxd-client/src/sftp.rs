@@ -0,0 +1,133 @@
+//! SFTP access to instance filesystems
+//!
+//! LXD serves SFTP on a connection upgraded from `GET /1.0/instances/{name}/sftp`,
+//! so one session can walk, read and write many files without an HTTP
+//! request per file.
+
+use crate::{Error, Result};
+use russh_sftp::client::error::Error as ProtocolError;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+pub use russh_sftp::client::fs::{
+    DirEntry as SftpDirEntry, File as SftpFile, Metadata as SftpMetadata,
+};
+
+/// An SFTP session on an instance, opened with [`Client::sftp`](crate::Client::sftp)
+///
+/// Paths are absolute paths inside the instance. Files opened through the
+/// session implement [`AsyncRead`], [`AsyncWrite`] and
+/// [`AsyncSeek`](tokio::io::AsyncSeek).
+pub struct SftpSession {
+    inner: russh_sftp::client::SftpSession,
+}
+
+impl SftpSession {
+    /// Start the SFTP protocol on an upgraded connection
+    pub(crate) async fn start(
+        stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    ) -> Result<Self> {
+        let inner = russh_sftp::client::SftpSession::new(stream)
+            .await
+            .map_err(error)?;
+        Ok(Self { inner })
+    }
+
+    /// List a directory, sorted by name and without `.` and `..`
+    pub async fn read_dir(&self, path: &str) -> Result<Vec<SftpDirEntry>> {
+        let mut entries: Vec<_> = self.inner.read_dir(path).await.map_err(error)?.collect();
+        entries.sort_by_key(SftpDirEntry::file_name);
+        Ok(entries)
+    }
+
+    /// Get the metadata of a file, following symbolic links
+    pub async fn stat(&self, path: &str) -> Result<SftpMetadata> {
+        self.inner.metadata(path).await.map_err(error)
+    }
+
+    /// Get the metadata of a file without following symbolic links
+    pub async fn lstat(&self, path: &str) -> Result<SftpMetadata> {
+        self.inner.symlink_metadata(path).await.map_err(error)
+    }
+
+    /// Check whether a path exists
+    pub async fn exists(&self, path: &str) -> Result<bool> {
+        self.inner.try_exists(path).await.map_err(error)
+    }
+
+    /// Open a file for reading
+    pub async fn open(&self, path: &str) -> Result<SftpFile> {
+        self.inner.open(path).await.map_err(error)
+    }
+
+    /// Create a file for writing, truncating it if it exists
+    pub async fn create(&self, path: &str) -> Result<SftpFile> {
+        self.inner.create(path).await.map_err(error)
+    }
+
+    /// Read a whole file into memory
+    pub async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.inner.read(path).await.map_err(error)
+    }
+
+    /// Write `data` to a file, creating it or replacing its contents
+    pub async fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        let mut file = self.create(path).await?;
+        file.write_all(data).await?;
+        file.shutdown().await?;
+        Ok(())
+    }
+
+    /// Create a directory
+    pub async fn create_dir(&self, path: &str) -> Result<()> {
+        self.inner.create_dir(path).await.map_err(error)
+    }
+
+    /// Create a symbolic link at `path` pointing to `target`
+    pub async fn symlink(&self, path: &str, target: &str) -> Result<()> {
+        self.inner.symlink(path, target).await.map_err(error)
+    }
+
+    /// Read the target of a symbolic link
+    pub async fn read_link(&self, path: &str) -> Result<String> {
+        self.inner.read_link(path).await.map_err(error)
+    }
+
+    /// Rename or move a file or directory
+    pub async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.inner.rename(from, to).await.map_err(error)
+    }
+
+    /// Remove a file or symbolic link
+    pub async fn remove_file(&self, path: &str) -> Result<()> {
+        self.inner.remove_file(path).await.map_err(error)
+    }
+
+    /// Remove an empty directory
+    pub async fn remove_dir(&self, path: &str) -> Result<()> {
+        self.inner.remove_dir(path).await.map_err(error)
+    }
+
+    /// The underlying protocol session, for requests not wrapped here
+    pub fn inner(&self) -> &russh_sftp::client::SftpSession {
+        &self.inner
+    }
+
+    /// End the session
+    pub async fn close(self) -> Result<()> {
+        self.inner.close().await.map_err(error)
+    }
+}
+
+impl std::fmt::Debug for SftpSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SftpSession").finish_non_exhaustive()
+    }
+}
+
+/// Convert an SFTP protocol error
+fn error(err: ProtocolError) -> Error {
+    match err {
+        ProtocolError::Timeout => Error::Timeout,
+        other => Error::Sftp(other.to_string()),
+    }
+}
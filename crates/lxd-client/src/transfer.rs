@@ -0,0 +1,471 @@
+//! Recursive directory transfers between the local filesystem and instances
+
+use crate::{Client, Error, FileMetadata, FileOptions, FileType, Result};
+use futures::{StreamExt, TryStreamExt};
+use lxd_types::Response;
+use std::fs::Permissions;
+use std::io;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Component, Path};
+use std::sync::{Arc, Mutex};
+
+/// Callback invoked each time an entry has been transferred
+pub type TransferCallback = Arc<dyn Fn(&TransferProgress) + Send + Sync>;
+
+/// Settings for [`Client::push_dir`] and [`Client::pull_dir`]
+#[derive(Clone)]
+pub struct TransferOptions {
+    /// Number of files transferred at the same time
+    pub parallelism: usize,
+
+    /// Copy the owner UID and GID along with the permission bits
+    ///
+    /// When pulling, ownership is only applied where the local user may
+    /// change it (usually only as root) and silently skipped otherwise.
+    pub preserve_ownership: bool,
+
+    /// Called after each file, directory or symlink is transferred
+    pub progress: Option<TransferCallback>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: 4,
+            preserve_ownership: true,
+            progress: None,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// Create options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of files transferred at the same time
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Set whether the owner UID and GID are copied
+    pub fn with_preserve_ownership(mut self, preserve: bool) -> Self {
+        self.preserve_ownership = preserve;
+        self
+    }
+
+    /// Set the progress callback
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&TransferProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+}
+
+impl std::fmt::Debug for TransferOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransferOptions")
+            .field("parallelism", &self.parallelism)
+            .field("preserve_ownership", &self.preserve_ownership)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Progress of a directory transfer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferProgress {
+    /// Path (relative to the transferred directory) of the last entry done
+    pub path: String,
+    /// Entries transferred so far
+    pub entries_done: u64,
+    /// File bytes transferred so far
+    pub bytes_done: u64,
+    /// Total number of entries, when known up front (pushes only)
+    pub entries_total: Option<u64>,
+    /// Total number of file bytes, when known up front (pushes only)
+    pub bytes_total: Option<u64>,
+}
+
+/// What a directory transfer copied
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferSummary {
+    /// Directories created, including the top one
+    pub directories: u64,
+    /// Regular files copied
+    pub files: u64,
+    /// Symbolic links created
+    pub symlinks: u64,
+    /// File bytes copied
+    pub bytes: u64,
+}
+
+/// Progress and summary shared by the concurrent parts of a transfer
+struct Tracker {
+    state: Mutex<(TransferProgress, TransferSummary)>,
+    callback: Option<TransferCallback>,
+}
+
+impl Tracker {
+    fn new(options: &TransferOptions, totals: Option<(u64, u64)>) -> Self {
+        let progress = TransferProgress {
+            entries_total: totals.map(|(entries, _)| entries),
+            bytes_total: totals.map(|(_, bytes)| bytes),
+            ..TransferProgress::default()
+        };
+        Self {
+            state: Mutex::new((progress, TransferSummary::default())),
+            callback: options.progress.clone(),
+        }
+    }
+
+    /// Record a transferred entry and report progress
+    fn done(&self, path: &str, file_type: &FileType, bytes: u64) {
+        let progress = {
+            let mut state = self.state.lock().unwrap();
+            let (progress, summary) = &mut *state;
+            match file_type {
+                FileType::Directory => summary.directories += 1,
+                FileType::Symlink => summary.symlinks += 1,
+                _ => summary.files += 1,
+            }
+            summary.bytes += bytes;
+            progress.path = path.to_string();
+            progress.entries_done += 1;
+            progress.bytes_done += bytes;
+            progress.clone()
+        };
+        if let Some(callback) = &self.callback {
+            callback(&progress);
+        }
+    }
+
+    fn summary(self) -> TransferSummary {
+        self.state.into_inner().unwrap().1
+    }
+}
+
+/// A local file, directory or symlink to push
+struct LocalEntry {
+    /// Path relative to the pushed directory, `/`-separated
+    relative: String,
+    file_type: FileType,
+    symlink_target: Option<String>,
+    metadata: std::fs::Metadata,
+}
+
+/// Push the directory tree at `local` to `remote` in `instance`
+pub(crate) async fn push_dir(
+    client: &Client,
+    local: &Path,
+    instance: &str,
+    remote: &str,
+    options: &TransferOptions,
+) -> Result<TransferSummary> {
+    let entries = walk_local(local).await?;
+    let bytes_total = entries
+        .iter()
+        .filter(|entry| entry.file_type == FileType::File)
+        .map(|entry| entry.metadata.len())
+        .sum();
+    let tracker = Tracker::new(options, Some((entries.len() as u64, bytes_total)));
+    let remote = remote.trim_end_matches('/');
+
+    let file_options = |metadata: &std::fs::Metadata| {
+        let created = FileOptions::new().with_mode(metadata.mode() & 0o7777);
+        if options.preserve_ownership {
+            created.with_uid(metadata.uid()).with_gid(metadata.gid())
+        } else {
+            created
+        }
+    };
+
+    // Directories come parents first, so create them in order
+    let (directories, others): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|entry| entry.file_type == FileType::Directory);
+    for entry in &directories {
+        let path = remote_path(remote, &entry.relative);
+        client
+            .create_directory(instance, &path, &file_options(&entry.metadata))
+            .await?;
+        tracker.done(&entry.relative, &entry.file_type, 0);
+    }
+
+    futures::stream::iter(others.into_iter().map(Ok))
+        .try_for_each_concurrent(options.parallelism.max(1), |entry| {
+            let tracker = &tracker;
+            let file_options = &file_options;
+            async move {
+                let path = remote_path(remote, &entry.relative);
+                let options = file_options(&entry.metadata);
+                let mut bytes = 0;
+                match &entry.symlink_target {
+                    Some(target) => {
+                        client
+                            .create_symlink(instance, &path, target, &options)
+                            .await?
+                    }
+                    None => {
+                        let file = tokio::fs::File::open(local.join(&entry.relative)).await?;
+                        client.push_file(instance, &path, file, &options).await?;
+                        bytes = entry.metadata.len();
+                    }
+                }
+                tracker.done(&entry.relative, &entry.file_type, bytes);
+                Ok::<_, Error>(())
+            }
+        })
+        .await?;
+
+    Ok(tracker.summary())
+}
+
+/// List the tree at `root`, directories before their contents
+async fn walk_local(root: &Path) -> Result<Vec<LocalEntry>> {
+    let metadata = tokio::fs::metadata(root).await?;
+    if !metadata.is_dir() {
+        return Err(Error::request(format!(
+            "{} is not a directory",
+            root.display()
+        )));
+    }
+
+    let mut entries = vec![LocalEntry {
+        relative: String::new(),
+        file_type: FileType::Directory,
+        symlink_target: None,
+        metadata,
+    }];
+    let mut pending = vec![String::new()];
+    while let Some(directory) = pending.pop() {
+        let mut children = Vec::new();
+        let mut reader = tokio::fs::read_dir(root.join(&directory)).await?;
+        while let Some(child) = reader.next_entry().await? {
+            children.push(child);
+        }
+        children.sort_by_key(|child| child.file_name());
+
+        for child in children {
+            let name = child.file_name().to_string_lossy().into_owned();
+            let relative = child_path(&directory, &name);
+            let metadata = tokio::fs::symlink_metadata(child.path()).await?;
+            let (file_type, symlink_target) = if metadata.file_type().is_symlink() {
+                let target = tokio::fs::read_link(child.path()).await?;
+                let target = target.to_string_lossy().into_owned();
+                (FileType::Symlink, Some(target))
+            } else if metadata.is_dir() {
+                pending.push(relative.clone());
+                (FileType::Directory, None)
+            } else if metadata.is_file() {
+                (FileType::File, None)
+            } else {
+                // Sockets, FIFOs and devices cannot be pushed
+                continue;
+            };
+            entries.push(LocalEntry {
+                relative,
+                file_type,
+                symlink_target,
+                metadata,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Pull the directory tree at `remote` in `instance` to `local`
+pub(crate) async fn pull_dir(
+    client: &Client,
+    instance: &str,
+    remote: &str,
+    local: &Path,
+    options: &TransferOptions,
+) -> Result<TransferSummary> {
+    let tracker = Tracker::new(options, None);
+    let directories = Mutex::new(Vec::new());
+    let remote = remote.trim_end_matches('/');
+
+    // Walk the tree one level at a time, pulling each level concurrently
+    let mut level = vec![String::new()];
+    while !level.is_empty() {
+        let children: Vec<Vec<String>> = futures::stream::iter(level)
+            .map(|relative| {
+                let tracker = &tracker;
+                let directories = &directories;
+                async move {
+                    let path = remote_path(remote, &relative);
+                    let file = client.pull_file(instance, &path).await?;
+                    let target = if relative.is_empty() {
+                        local.to_path_buf()
+                    } else {
+                        local.join(&relative)
+                    };
+                    let metadata = file.metadata;
+
+                    let mut bytes = 0;
+                    let mut children = Vec::new();
+                    match &metadata.file_type {
+                        FileType::Directory => {
+                            let listing: Response<Vec<String>> =
+                                serde_json::from_slice(&file.body.bytes().await?)?;
+                            check_no_symlinks(local, &relative, true)?;
+                            tokio::fs::create_dir_all(&target).await?;
+                            children = listing
+                                .metadata
+                                .into_iter()
+                                .map(|name| {
+                                    check_entry_name(&path, &name)?;
+                                    Ok(child_path(&relative, &name))
+                                })
+                                .collect::<Result<_>>()?;
+                            directories.lock().unwrap().push((target, metadata.clone()));
+                        }
+                        FileType::File if !relative.is_empty() => {
+                            check_no_symlinks(local, &relative, false)?;
+                            remove_symlink(&target).await?;
+                            let mut out = tokio::fs::File::create(&target).await?;
+                            bytes = file.body.copy_to(&mut out).await?;
+                            apply_metadata(&target, &metadata, options)?;
+                        }
+                        FileType::Symlink if !relative.is_empty() => {
+                            check_no_symlinks(local, &relative, false)?;
+                            let link =
+                                String::from_utf8_lossy(&file.body.bytes().await?).into_owned();
+                            match tokio::fs::remove_file(&target).await {
+                                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                                    return Err(e.into())
+                                }
+                                _ => {}
+                            }
+                            tokio::fs::symlink(link, &target).await?;
+                            if options.preserve_ownership {
+                                ignore_permission_denied(std::os::unix::fs::lchown(
+                                    &target,
+                                    Some(metadata.uid),
+                                    Some(metadata.gid),
+                                ))?;
+                            }
+                        }
+                        FileType::File | FileType::Symlink => {
+                            return Err(Error::request(format!("{} is not a directory", path)))
+                        }
+                        FileType::Unknown(other) => {
+                            return Err(Error::request(format!(
+                                "{} has unsupported file type {}",
+                                path, other
+                            )))
+                        }
+                    }
+                    tracker.done(&relative, &metadata.file_type, bytes);
+                    Ok::<_, Error>(children)
+                }
+            })
+            .buffer_unordered(options.parallelism.max(1))
+            .try_collect()
+            .await?;
+        level = children.into_iter().flatten().collect();
+    }
+
+    // Apply directory permissions last, deepest first, so read-only
+    // directories do not block writing their contents
+    let mut directories = directories.into_inner().unwrap();
+    directories.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+    for (path, metadata) in &directories {
+        apply_metadata(path, metadata, options)?;
+    }
+
+    Ok(tracker.summary())
+}
+
+/// Apply the mode and (if requested and allowed) ownership of a pulled entry
+fn apply_metadata(path: &Path, metadata: &FileMetadata, options: &TransferOptions) -> Result<()> {
+    std::fs::set_permissions(path, Permissions::from_mode(metadata.mode))?;
+    if options.preserve_ownership {
+        ignore_permission_denied(std::os::unix::fs::chown(
+            path,
+            Some(metadata.uid),
+            Some(metadata.gid),
+        ))?;
+    }
+    Ok(())
+}
+
+fn ignore_permission_denied(result: io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Check that a name from a remote directory listing stays inside the directory
+fn check_entry_name(directory: &str, name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains('/') => Ok(()),
+        _ => Err(Error::request(format!(
+            "{} lists invalid entry {:?}",
+            directory, name
+        ))),
+    }
+}
+
+/// Check that no directory on the way from `local` to `relative` is a symlink,
+/// so a link pulled earlier is never written through
+///
+/// With `inclusive`, `relative` itself must not be a symlink either.
+fn check_no_symlinks(local: &Path, relative: &str, inclusive: bool) -> Result<()> {
+    let relative = Path::new(relative);
+    let mut components: Vec<_> = relative.components().collect();
+    if !inclusive {
+        components.pop();
+    }
+    let mut path = local.to_path_buf();
+    for component in components {
+        path.push(component);
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(Error::request(format!(
+                    "{} is a symbolic link",
+                    path.display()
+                )))
+            }
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Remove `path` if it is a symlink, so it is replaced rather than followed
+async fn remove_symlink(path: &Path) -> Result<()> {
+    match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            Ok(tokio::fs::remove_file(path).await?)
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Join a relative path onto a remote directory (without trailing `/`)
+fn remote_path(base: &str, relative: &str) -> String {
+    match (base, relative) {
+        ("", "") => "/".to_string(),
+        (base, "") => base.to_string(),
+        (base, relative) => format!("{}/{}", base, relative),
+    }
+}
+
+/// Relative path of `name` inside the relative `directory`
+fn child_path(directory: &str, name: &str) -> String {
+    if directory.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", directory, name)
+    }
+}
@@ -0,0 +1,199 @@
+//! Raw (non-JSON) request and response bodies
+
+use crate::Result;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use hyper::Method;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Size of the chunks read from an [`AsyncRead`] request body
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A raw response body, received in chunks as it is read
+///
+/// Used for endpoints that return file contents rather than JSON. Read it
+/// as a [`Stream`] of chunks, or use [`ByteStream::copy_to`] to write it
+/// out without holding it all in memory.
+pub struct ByteStream {
+    inner: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>,
+}
+
+impl ByteStream {
+    /// Wrap a stream of chunks
+    pub fn new(inner: impl Stream<Item = Result<Bytes>> + Send + 'static) -> Self {
+        Self {
+            inner: Box::pin(inner),
+        }
+    }
+
+    /// Read the whole body into memory
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        while let Some(chunk) = self.inner.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+
+    /// Write the body to `writer`, returning the number of bytes written
+    pub async fn copy_to<W: AsyncWrite + Unpin>(mut self, writer: &mut W) -> Result<u64> {
+        let mut written = 0;
+        while let Some(chunk) = self.inner.next().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        writer.flush().await?;
+        Ok(written)
+    }
+}
+
+impl Stream for ByteStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl std::fmt::Debug for ByteStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ByteStream").finish_non_exhaustive()
+    }
+}
+
+/// Body of a [`RawRequest`]
+pub enum RequestBody {
+    /// No body
+    Empty,
+    /// A body held in memory
+    Bytes(Bytes),
+    /// A body streamed in chunks, sent with chunked transfer encoding
+    Stream(Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>),
+}
+
+impl RequestBody {
+    /// Stream the body from `reader` until end of file
+    pub fn from_reader(reader: impl AsyncRead + Send + 'static) -> Self {
+        let reader = Box::pin(reader);
+        let chunks = futures::stream::try_unfold(reader, |mut reader| async move {
+            let mut chunk = BytesMut::with_capacity(CHUNK_SIZE);
+            match reader.read_buf(&mut chunk).await? {
+                0 => Ok(None),
+                _ => Ok(Some((chunk.freeze(), reader))),
+            }
+        });
+        Self::Stream(Box::pin(chunks))
+    }
+
+    /// Copy the body if it is held in memory
+    fn try_clone(&self) -> Option<Self> {
+        match self {
+            Self::Empty => Some(Self::Empty),
+            Self::Bytes(bytes) => Some(Self::Bytes(bytes.clone())),
+            Self::Stream(_) => None,
+        }
+    }
+}
+
+impl From<Bytes> for RequestBody {
+    fn from(bytes: Bytes) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for RequestBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes.into())
+    }
+}
+
+impl From<String> for RequestBody {
+    fn from(text: String) -> Self {
+        Self::Bytes(text.into())
+    }
+}
+
+impl std::fmt::Debug for RequestBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("Empty"),
+            Self::Bytes(bytes) => f.debug_tuple("Bytes").field(&bytes.len()).finish(),
+            Self::Stream(_) => f.write_str("Stream"),
+        }
+    }
+}
+
+/// A request whose body is sent as-is rather than encoded as JSON
+///
+/// Used for file transfers, where metadata travels in `X-LXD-*` headers.
+/// The `Content-Type` defaults to `application/octet-stream`.
+#[derive(Debug)]
+pub struct RawRequest {
+    /// HTTP method
+    pub method: Method,
+    /// Path and query, starting with `/1.0`
+    pub path: String,
+    /// Extra request headers
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: RequestBody,
+}
+
+impl RawRequest {
+    /// Create a request without a body
+    pub fn new(method: Method, path: impl Into<String>) -> Self {
+        Self {
+            method,
+            path: path.into(),
+            headers: Vec::new(),
+            body: RequestBody::Empty,
+        }
+    }
+
+    /// Add a header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the body
+    pub fn body(mut self, body: impl Into<RequestBody>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Copy the request if its body is held in memory, so it can be retried
+    pub(crate) fn try_clone(&self) -> Option<Self> {
+        Some(Self {
+            method: self.method.clone(),
+            path: self.path.clone(),
+            headers: self.headers.clone(),
+            body: self.body.try_clone()?,
+        })
+    }
+}
+
+/// A successful response to a [`RawRequest`]
+#[derive(Debug)]
+pub struct RawResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    pub headers: Vec<(String, String)>,
+    /// Response body
+    pub body: ByteStream,
+}
+
+impl RawResponse {
+    /// Get a header value by name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
@@ -1,44 +1,148 @@
 //! HTTPS transport for LXD API
 
-use crate::transport::Transport;
+use crate::auth::BearerAuth;
+use crate::transport::identity::ClientIdentity;
+use crate::transport::tls::{self, ServerVerification, TlsMode};
+use crate::transport::upgrade;
+use crate::transport::websocket::{self, Io, WebSocket};
+use crate::transport::{self, ByteStream, RawRequest, RawResponse, RequestBody, Transport};
 use crate::{Error, Result};
+use futures::StreamExt;
+use rustls::pki_types::ServerName;
 use serde::{de::DeserializeOwned, Serialize};
-use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
 
 /// HTTPS transport for remote LXD API
 pub struct HttpsTransport {
     base_url: String,
     client: reqwest::Client,
+    tls_config: Arc<rustls::ClientConfig>,
+    auth: Option<BearerAuth>,
 }
 
 impl HttpsTransport {
     /// Create a new HTTPS transport
+    ///
+    /// The server certificate is verified against the bundled web PKI roots.
+    /// Use [`HttpsTransport::with_verification`] to trust a self-signed LXD
+    /// server.
     pub fn new(
         url: impl Into<String>,
         cert_path: impl AsRef<Path>,
         key_path: impl AsRef<Path>,
     ) -> Result<Self> {
-        let base_url = url.into().trim_end_matches('/').to_string();
+        Self::with_verification(url, cert_path, key_path, ServerVerification::default())
+    }
+
+    /// Create a new HTTPS transport with a server verification mode
+    pub fn with_verification(
+        url: impl Into<String>,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        let identity = ClientIdentity::from_files(cert_path, key_path)?;
+        Self::with_identity(url, &identity, verification)
+    }
+
+    /// Create a new HTTPS transport from PEM encoded client certificate and key
+    ///
+    /// Use this for credentials held in memory, such as secrets pulled from a
+    /// vault.
+    pub fn from_pem(
+        url: impl Into<String>,
+        cert_pem: &[u8],
+        key_pem: &[u8],
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        Self::build(
+            url,
+            Some((cert_pem, key_pem)),
+            None,
+            TlsMode::Verify(verification),
+        )
+    }
+
+    /// Create a new HTTPS transport presenting a client identity
+    pub fn with_identity(
+        url: impl Into<String>,
+        identity: &ClientIdentity,
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        Self::from_pem(
+            url,
+            identity.cert_pem().as_bytes(),
+            identity.key_pem().as_bytes(),
+            verification,
+        )
+    }
 
-        // Read certificate and key
-        let cert_pem = fs::read(cert_path.as_ref())
-            .map_err(|e| Error::Tls(format!("Failed to read certificate: {}", e)))?;
-        let key_pem = fs::read(key_path.as_ref())
-            .map_err(|e| Error::Tls(format!("Failed to read key: {}", e)))?;
+    /// Create a new HTTPS transport that authenticates with a bearer token
+    ///
+    /// No client certificate is presented; every request carries an
+    /// `Authorization: Bearer` header instead.
+    pub fn with_bearer_auth(
+        url: impl Into<String>,
+        auth: BearerAuth,
+        verification: ServerVerification,
+    ) -> Result<Self> {
+        Self::build(url, None, Some(auth), TlsMode::Verify(verification))
+    }
 
-        // Create identity from cert + key
-        let identity = reqwest::Identity::from_pem(&[cert_pem.clone(), key_pem].concat())
-            .map_err(|e| Error::Tls(format!("Failed to create identity: {}", e)))?;
+    /// Create a new HTTPS transport that accepts any server certificate
+    pub(crate) fn insecure(url: impl Into<String>, identity: &ClientIdentity) -> Result<Self> {
+        Self::build(
+            url,
+            Some((
+                identity.cert_pem().as_bytes(),
+                identity.key_pem().as_bytes(),
+            )),
+            None,
+            TlsMode::AcceptInvalidCerts,
+        )
+    }
+
+    /// Create a new bearer-token HTTPS transport that accepts any server certificate
+    pub(crate) fn insecure_with_bearer_auth(
+        url: impl Into<String>,
+        auth: BearerAuth,
+    ) -> Result<Self> {
+        Self::build(url, None, Some(auth), TlsMode::AcceptInvalidCerts)
+    }
+
+    /// Build the transport from an optional PEM encoded client certificate and key
+    fn build(
+        url: impl Into<String>,
+        identity: Option<(&[u8], &[u8])>,
+        auth: Option<BearerAuth>,
+        mode: TlsMode,
+    ) -> Result<Self> {
+        let base_url = url.into().trim_end_matches('/').to_string();
+
+        let tls_config = tls::client_config(&mode, identity)?;
 
         // Build client with custom TLS config
         let client = reqwest::Client::builder()
-            .identity(identity)
-            .danger_accept_invalid_certs(true) // LXD uses self-signed certs
+            .use_preconfigured_tls(tls_config.clone())
             .build()
             .map_err(|e| Error::Tls(format!("Failed to build client: {}", e)))?;
 
-        Ok(Self { base_url, client })
+        Ok(Self {
+            base_url,
+            client,
+            tls_config: Arc::new(tls_config),
+            auth,
+        })
+    }
+
+    /// The server URL requests are made against
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
     }
 
     /// Build full URL from path
@@ -46,6 +150,77 @@ impl HttpsTransport {
         format!("{}{}", self.base_url, path)
     }
 
+    /// Open a TLS connection to the server, verifying it the same way as
+    /// other requests, and return it with the `host:port` it was opened to
+    async fn connect(&self) -> Result<(Box<dyn Io>, String)> {
+        let url = url::Url::parse(&self.base_url)
+            .map_err(|e| Error::Config(format!("Invalid URL {}: {}", self.base_url, e)))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::Config(format!("No host in URL {}", self.base_url)))?;
+        let port = url.port_or_known_default().unwrap_or(8443);
+        let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+
+        let tcp = TcpStream::connect((bare_host, port))
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+        let server_name =
+            ServerName::try_from(bare_host.to_string()).map_err(|e| Error::Tls(e.to_string()))?;
+        let stream = TlsConnector::from(self.tls_config.clone())
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+
+        Ok((Box::new(stream), format!("{}:{}", host, port)))
+    }
+
+    /// The `Authorization` header value, if the transport has credentials
+    async fn authorization(&self) -> Result<Option<HeaderValue>> {
+        let Some(auth) = &self.auth else {
+            return Ok(None);
+        };
+        HeaderValue::from_str(&format!("Bearer {}", auth.access_token().await?))
+            .map(Some)
+            .map_err(|e| Error::Auth(e.to_string()))
+    }
+
+    /// Open a websocket, verifying the server the same way as other requests
+    pub(crate) async fn websocket(&self, path: &str) -> Result<WebSocket> {
+        let (stream, authority) = self.connect().await?;
+        let mut request = format!("wss://{}{}", authority, path)
+            .into_client_request()
+            .map_err(websocket::error)?;
+        if let Some(value) = self.authorization().await? {
+            request.headers_mut().insert("Authorization", value);
+        }
+        websocket::handshake(stream, request).await
+    }
+
+    /// Upgrade a connection to the server to `protocol`
+    pub(crate) async fn upgrade(&self, path: &str, protocol: &str) -> Result<Box<dyn Io>> {
+        let (stream, authority) = self.connect().await?;
+        let mut request = hyper::Request::builder()
+            .method(hyper::Method::GET)
+            .uri(path)
+            .header("Host", authority);
+        if let Some(value) = self.authorization().await? {
+            request = request.header("Authorization", value);
+        }
+        upgrade::upgrade(stream, request, protocol).await
+    }
+
+    /// Attach credentials and send a request
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = match &self.auth {
+            Some(auth) => request.bearer_auth(auth.access_token().await?),
+            None => request,
+        };
+        request
+            .send()
+            .await
+            .map_err(|e| Error::connection(e.to_string()))
+    }
+
     /// Parse response and handle errors
     async fn parse_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
         let status = response.status();
@@ -71,12 +246,7 @@ impl HttpsTransport {
 
 impl Transport for HttpsTransport {
     async fn get<T: DeserializeOwned + Send>(&self, path: &str) -> Result<T> {
-        let response = self
-            .client
-            .get(self.url(path))
-            .send()
-            .await
-            .map_err(|e| Error::connection(e.to_string()))?;
+        let response = self.send(self.client.get(self.url(path))).await?;
 
         Self::parse_response(response).await
     }
@@ -87,12 +257,8 @@ impl Transport for HttpsTransport {
         body: &B,
     ) -> Result<T> {
         let response = self
-            .client
-            .post(self.url(path))
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| Error::connection(e.to_string()))?;
+            .send(self.client.post(self.url(path)).json(body))
+            .await?;
 
         Self::parse_response(response).await
     }
@@ -103,12 +269,8 @@ impl Transport for HttpsTransport {
         body: &B,
     ) -> Result<T> {
         let response = self
-            .client
-            .put(self.url(path))
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| Error::connection(e.to_string()))?;
+            .send(self.client.put(self.url(path)).json(body))
+            .await?;
 
         Self::parse_response(response).await
     }
@@ -119,24 +281,62 @@ impl Transport for HttpsTransport {
         body: &B,
     ) -> Result<T> {
         let response = self
-            .client
-            .patch(self.url(path))
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| Error::connection(e.to_string()))?;
+            .send(self.client.patch(self.url(path)).json(body))
+            .await?;
 
         Self::parse_response(response).await
     }
 
     async fn delete<T: DeserializeOwned + Send>(&self, path: &str) -> Result<T> {
-        let response = self
-            .client
-            .delete(self.url(path))
-            .send()
-            .await
-            .map_err(|e| Error::connection(e.to_string()))?;
+        let response = self.send(self.client.delete(self.url(path))).await?;
 
         Self::parse_response(response).await
     }
+
+    async fn send_raw(&self, request: RawRequest) -> Result<RawResponse> {
+        let mut builder = self.client.request(request.method, self.url(&request.path));
+        if !request
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+        {
+            builder = builder.header("Content-Type", "application/octet-stream");
+        }
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        builder = match request.body {
+            RequestBody::Empty => builder,
+            RequestBody::Bytes(bytes) => builder.body(bytes),
+            RequestBody::Stream(stream) => builder.body(reqwest::Body::wrap_stream(stream)),
+        };
+        let response = self.send(builder).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| Error::request(e.to_string()))?;
+            return Err(transport::response_error(status.as_u16(), &body));
+        }
+
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.to_string(), value)
+            })
+            .collect();
+        let body = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(|e| Error::request(e.to_string())));
+
+        Ok(RawResponse {
+            status: status.as_u16(),
+            headers,
+            body: ByteStream::new(body),
+        })
+    }
 }
@@ -0,0 +1,163 @@
+//! Client certificates for HTTPS authentication
+
+use crate::transport::tls;
+use crate::{Error, Result};
+use rcgen::{
+    CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, KeyPair,
+    KeyUsagePurpose, PKCS_ECDSA_P384_SHA384,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use time::{Duration, OffsetDateTime};
+
+/// A PEM encoded client certificate and private key
+///
+/// This is the identity an HTTPS client presents to LXD, equivalent to the
+/// `client.crt` and `client.key` files used by `lxc`.
+#[derive(Clone)]
+pub struct ClientIdentity {
+    cert_pem: String,
+    key_pem: String,
+}
+
+impl ClientIdentity {
+    /// Create from PEM encoded certificate and key
+    ///
+    /// Both are parsed up front so malformed secrets fail here rather than on
+    /// the first request.
+    pub fn from_pem(cert_pem: impl Into<String>, key_pem: impl Into<String>) -> Result<Self> {
+        let identity = Self {
+            cert_pem: cert_pem.into(),
+            key_pem: key_pem.into(),
+        };
+        CertificateDer::from_pem_slice(identity.cert_pem.as_bytes())
+            .map_err(|e| Error::Tls(format!("Failed to parse certificate: {}", e)))?;
+        PrivateKeyDer::from_pem_slice(identity.key_pem.as_bytes())
+            .map_err(|e| Error::Tls(format!("Failed to parse key: {}", e)))?;
+        Ok(identity)
+    }
+
+    /// Read the certificate and key from disk
+    pub fn from_files(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<Self> {
+        let cert_pem = fs::read_to_string(cert_path.as_ref())
+            .map_err(|e| Error::Tls(format!("Failed to read certificate: {}", e)))?;
+        let key_pem = fs::read_to_string(key_path.as_ref())
+            .map_err(|e| Error::Tls(format!("Failed to read key: {}", e)))?;
+        Self::from_pem(cert_pem, key_pem)
+    }
+
+    /// Generate a new self-signed client certificate
+    ///
+    /// Like the certificate `lxc` creates on first use, it has an ECDSA P-384
+    /// key, is valid for ten years and is marked for client authentication.
+    /// `common_name` is conventionally `user@hostname`.
+    pub fn generate(common_name: impl Into<String>) -> Result<Self> {
+        let key = KeyPair::generate_for(&PKCS_ECDSA_P384_SHA384)
+            .map_err(|e| Error::Tls(format!("Failed to generate key: {}", e)))?;
+
+        let mut params = CertificateParams::default();
+        let mut name = DistinguishedName::new();
+        name.push(DnType::OrganizationName, "LXD");
+        name.push(DnType::CommonName, common_name.into());
+        params.distinguished_name = name;
+        params.key_usages = vec![
+            KeyUsagePurpose::DigitalSignature,
+            KeyUsagePurpose::KeyEncipherment,
+        ];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let now = OffsetDateTime::now_utc();
+        params.not_before = now - Duration::days(1);
+        params.not_after = now + Duration::days(3650);
+
+        let cert = params
+            .self_signed(&key)
+            .map_err(|e| Error::Tls(format!("Failed to generate certificate: {}", e)))?;
+
+        Ok(Self {
+            cert_pem: cert.pem(),
+            key_pem: key.serialize_pem(),
+        })
+    }
+
+    /// PEM encoded certificate
+    ///
+    /// This is what the LXD server needs to trust, for example through
+    /// `lxc config trust add`.
+    pub fn cert_pem(&self) -> &str {
+        &self.cert_pem
+    }
+
+    /// PEM encoded private key
+    pub fn key_pem(&self) -> &str {
+        &self.key_pem
+    }
+
+    /// SHA-256 fingerprint of the certificate as lower-case hex
+    pub fn fingerprint(&self) -> Result<String> {
+        let der = CertificateDer::from_pem_slice(self.cert_pem.as_bytes())
+            .map_err(|e| Error::Tls(format!("Failed to parse certificate: {}", e)))?;
+        Ok(tls::fingerprint(&der))
+    }
+
+    /// Write the certificate and key to disk
+    ///
+    /// The key file is only readable by its owner.
+    pub fn write(&self, cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<()> {
+        fs::write(cert_path.as_ref(), &self.cert_pem)?;
+        write_private(key_path.as_ref(), self.key_pem.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientIdentity").finish_non_exhaustive()
+    }
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let identity = ClientIdentity::generate("me@host").unwrap();
+        assert!(identity
+            .cert_pem()
+            .starts_with("-----BEGIN CERTIFICATE-----"));
+        assert!(identity.key_pem().contains("PRIVATE KEY"));
+        assert_eq!(identity.fingerprint().unwrap().len(), 64);
+
+        // Generated material round-trips through the PEM constructor
+        ClientIdentity::from_pem(identity.cert_pem(), identity.key_pem()).unwrap();
+    }
+
+    #[test]
+    fn test_from_pem_rejects_garbage() {
+        let identity = ClientIdentity::generate("me@host").unwrap();
+        assert!(ClientIdentity::from_pem("not a cert", identity.key_pem()).is_err());
+        assert!(ClientIdentity::from_pem(identity.cert_pem(), "not a key").is_err());
+    }
+}
@@ -1,12 +1,25 @@
 //! Transport layer for LXD API communication
 
+mod body;
 mod https;
+mod identity;
+mod retry;
+mod tls;
 mod unix;
+mod upgrade;
+mod websocket;
 
+pub use body::{ByteStream, RawRequest, RawResponse, RequestBody};
 pub use https::HttpsTransport;
+pub use identity::ClientIdentity;
+pub use tls::{ServerCertificate, ServerVerification, TrustCallback};
 pub use unix::UnixSocketTransport;
+pub use websocket::WebSocketIo;
 
-use crate::Result;
+pub(crate) use retry::execute;
+pub(crate) use websocket::{error as websocket_error, Io, WebSocket};
+
+use crate::{Error, Result};
 use serde::{de::DeserializeOwned, Serialize};
 
 /// Transport kind enum for runtime dispatch
@@ -70,6 +83,46 @@ impl TransportKind {
             TransportKind::Https(t) => t.delete(path).await,
         }
     }
+
+    /// Perform a request with a raw body
+    pub async fn send_raw(&self, request: RawRequest) -> Result<RawResponse> {
+        match self {
+            TransportKind::UnixSocket(t) => t.send_raw(request).await,
+            TransportKind::Https(t) => t.send_raw(request).await,
+        }
+    }
+
+    /// Open a websocket
+    pub(crate) async fn websocket(&self, path: &str) -> Result<WebSocket> {
+        match self {
+            TransportKind::UnixSocket(t) => t.websocket(path).await,
+            TransportKind::Https(t) => t.websocket(path).await,
+        }
+    }
+
+    /// The server URL, if the server is reached over the network
+    pub(crate) fn base_url(&self) -> Option<&str> {
+        match self {
+            TransportKind::UnixSocket(_) => None,
+            TransportKind::Https(t) => Some(t.base_url()),
+        }
+    }
+
+    /// Upgrade a connection to `protocol`, returning the raw stream
+    pub(crate) async fn upgrade(&self, path: &str, protocol: &str) -> Result<Box<dyn Io>> {
+        match self {
+            TransportKind::UnixSocket(t) => t.upgrade(path, protocol).await,
+            TransportKind::Https(t) => t.upgrade(path, protocol).await,
+        }
+    }
+}
+
+/// Build the error for a failed response from its status and body
+pub(crate) fn response_error(status: u16, body: &[u8]) -> Error {
+    match serde_json::from_slice::<lxd_types::Response<()>>(body) {
+        Ok(error_resp) => Error::api(error_resp.error_code, error_resp.error),
+        Err(_) => Error::http(status, String::from_utf8_lossy(body).to_string()),
+    }
 }
 
 /// Transport trait for LXD API communication
@@ -106,4 +159,13 @@ pub trait Transport: Send + Sync {
         &self,
         path: &str,
     ) -> impl std::future::Future<Output = Result<T>> + Send;
+
+    /// Perform a request with a raw body, returning the raw response
+    ///
+    /// Unsuccessful responses are turned into errors as for the JSON
+    /// methods; the body of a successful one is streamed.
+    fn send_raw(
+        &self,
+        request: RawRequest,
+    ) -> impl std::future::Future<Output = Result<RawResponse>> + Send;
 }
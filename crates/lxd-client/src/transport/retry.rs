@@ -0,0 +1,41 @@
+//! Timeout and retry layer shared by all transports
+
+use crate::{ClientConfig, Error, Result};
+use hyper::Method;
+use std::future::Future;
+use std::time::Duration;
+
+/// Run a request with the configured deadline and retry policy
+///
+/// `request` is invoked once per attempt. Each attempt is bounded by
+/// `timeout` (no deadline when `None`); an attempt that runs past it fails
+/// with [`Error::Timeout`]. Failed attempts are retried with exponential
+/// backoff as long as [`ClientConfig::should_retry`] allows it.
+pub(crate) async fn execute<T, F, Fut>(
+    config: &ClientConfig,
+    method: Method,
+    timeout: Option<Duration>,
+    mut request: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        let result = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, request())
+                .await
+                .unwrap_or(Err(Error::Timeout)),
+            None => request().await,
+        };
+
+        match result {
+            Err(err) if config.should_retry(&method, &err, attempt) => {
+                tokio::time::sleep(config.delay_for_attempt(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
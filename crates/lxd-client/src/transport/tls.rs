@@ -0,0 +1,411 @@
+//! TLS configuration and server certificate verification for HTTPS
+
+use crate::{Error, Result};
+use base64::Engine;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{self, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Callback invoked when a server certificate is trusted for the first time
+pub type TrustCallback = Arc<dyn Fn(&ServerCertificate) + Send + Sync>;
+
+/// How the HTTPS transport verifies the certificate presented by the server
+#[derive(Clone, Default)]
+pub enum ServerVerification {
+    /// Verify against the bundled Mozilla root store (default)
+    #[default]
+    WebPkiRoots,
+
+    /// Verify against the CA certificates in a PEM bundle
+    CaBundle(Vec<u8>),
+
+    /// Only accept this exact certificate (PEM encoded)
+    ///
+    /// This is how the `lxc` CLI trusts servers, using the certificate stored
+    /// in `servercerts/<remote>.crt`.
+    PinnedCertificate(Vec<u8>),
+
+    /// Only accept a certificate with this SHA-256 fingerprint (hex encoded)
+    Fingerprint(String),
+
+    /// Trust the first certificate seen and pin it from then on
+    ///
+    /// When `known_fingerprint` is set it is used as the pin; otherwise the
+    /// first certificate presented is accepted and `on_first_use` is called
+    /// so the caller can persist it.
+    TrustOnFirstUse {
+        /// Previously persisted fingerprint, if any
+        known_fingerprint: Option<String>,
+        /// Called once with the certificate accepted on first use
+        on_first_use: TrustCallback,
+    },
+}
+
+impl ServerVerification {
+    /// Verify against the CA certificates in a PEM bundle file
+    pub fn ca_bundle_file(path: impl AsRef<Path>) -> Result<Self> {
+        let pem = fs::read(path.as_ref())
+            .map_err(|e| Error::Tls(format!("Failed to read CA bundle: {}", e)))?;
+        Ok(Self::CaBundle(pem))
+    }
+
+    /// Pin the certificate stored in a PEM file
+    pub fn pinned_certificate_file(path: impl AsRef<Path>) -> Result<Self> {
+        let pem = fs::read(path.as_ref())
+            .map_err(|e| Error::Tls(format!("Failed to read server certificate: {}", e)))?;
+        Ok(Self::PinnedCertificate(pem))
+    }
+
+    /// Pin a SHA-256 fingerprint
+    ///
+    /// Both plain hex and colon-separated forms are accepted.
+    pub fn fingerprint(fingerprint: impl AsRef<str>) -> Self {
+        Self::Fingerprint(normalize_fingerprint(fingerprint.as_ref()))
+    }
+
+    /// Trust on first use, calling `on_first_use` with the accepted certificate
+    pub fn trust_on_first_use<F>(known_fingerprint: Option<String>, on_first_use: F) -> Self
+    where
+        F: Fn(&ServerCertificate) + Send + Sync + 'static,
+    {
+        Self::TrustOnFirstUse {
+            known_fingerprint,
+            on_first_use: Arc::new(on_first_use),
+        }
+    }
+}
+
+impl fmt::Debug for ServerVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WebPkiRoots => f.write_str("WebPkiRoots"),
+            Self::CaBundle(_) => f.write_str("CaBundle(..)"),
+            Self::PinnedCertificate(_) => f.write_str("PinnedCertificate(..)"),
+            Self::Fingerprint(fp) => f.debug_tuple("Fingerprint").field(fp).finish(),
+            Self::TrustOnFirstUse {
+                known_fingerprint, ..
+            } => f
+                .debug_struct("TrustOnFirstUse")
+                .field("known_fingerprint", known_fingerprint)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// A certificate presented by an LXD server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerCertificate {
+    der: Vec<u8>,
+}
+
+impl ServerCertificate {
+    /// Create from a DER encoded certificate
+    pub fn from_der(der: impl Into<Vec<u8>>) -> Self {
+        Self { der: der.into() }
+    }
+
+    /// Parse the first certificate in a PEM document
+    pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        let cert = CertificateDer::from_pem_slice(pem)
+            .map_err(|e| Error::Tls(format!("Failed to parse certificate: {}", e)))?;
+        Ok(Self::from_der(cert.as_ref()))
+    }
+
+    /// DER encoded certificate
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// SHA-256 fingerprint as lower-case hex, as shown by `lxc info`
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.der)
+    }
+
+    /// PEM encoded certificate, suitable for `servercerts/<remote>.crt`
+    pub fn to_pem(&self) -> String {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&self.der);
+        let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(&String::from_utf8_lossy(line));
+            pem.push('\n');
+        }
+        pem.push_str("-----END CERTIFICATE-----\n");
+        pem
+    }
+}
+
+/// How the transport treats server certificates
+pub(crate) enum TlsMode {
+    /// Verify the server certificate
+    Verify(ServerVerification),
+    /// Accept any server certificate (explicit opt-in only)
+    AcceptInvalidCerts,
+}
+
+/// Build a rustls client configuration
+///
+/// `identity` is the PEM encoded client certificate and key, if any.
+pub(crate) fn client_config(
+    mode: &TlsMode,
+    identity: Option<(&[u8], &[u8])>,
+) -> Result<rustls::ClientConfig> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| Error::Tls(e.to_string()))?;
+
+    let builder = match mode {
+        TlsMode::Verify(ServerVerification::WebPkiRoots) => {
+            let roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            builder.with_root_certificates(roots)
+        }
+        TlsMode::Verify(ServerVerification::CaBundle(pem)) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_slice_iter(pem) {
+                let cert =
+                    cert.map_err(|e| Error::Tls(format!("Failed to parse CA bundle: {}", e)))?;
+                roots
+                    .add(cert)
+                    .map_err(|e| Error::Tls(format!("Invalid CA certificate: {}", e)))?;
+            }
+            if roots.is_empty() {
+                return Err(Error::Tls("CA bundle contains no certificates".to_string()));
+            }
+            builder.with_root_certificates(roots)
+        }
+        TlsMode::Verify(ServerVerification::PinnedCertificate(pem)) => {
+            let cert = ServerCertificate::from_pem(pem)?;
+            let pin = Pin::Fingerprint(cert.fingerprint());
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(PinVerifier::new(pin, &provider))
+        }
+        TlsMode::Verify(ServerVerification::Fingerprint(fp)) => {
+            let pin = Pin::Fingerprint(normalize_fingerprint(fp));
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(PinVerifier::new(pin, &provider))
+        }
+        TlsMode::Verify(ServerVerification::TrustOnFirstUse {
+            known_fingerprint,
+            on_first_use,
+        }) => {
+            let pin = Pin::TrustOnFirstUse {
+                pinned: Mutex::new(known_fingerprint.as_deref().map(normalize_fingerprint)),
+                on_first_use: on_first_use.clone(),
+            };
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(PinVerifier::new(pin, &provider))
+        }
+        TlsMode::AcceptInvalidCerts => builder
+            .dangerous()
+            .with_custom_certificate_verifier(PinVerifier::new(Pin::Any, &provider)),
+    };
+
+    match identity {
+        Some((cert_pem, key_pem)) => {
+            let certs = CertificateDer::pem_slice_iter(cert_pem)
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| Error::Tls(format!("Failed to parse certificate: {}", e)))?;
+            let key = PrivateKeyDer::from_pem_slice(key_pem)
+                .map_err(|e| Error::Tls(format!("Failed to parse key: {}", e)))?;
+            builder
+                .with_client_auth_cert(certs, key)
+                .map_err(|e| Error::Tls(format!("Failed to create identity: {}", e)))
+        }
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// SHA-256 fingerprint of a DER encoded certificate
+pub(crate) fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Lower-case a fingerprint and strip separators
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// What a [`PinVerifier`] accepts
+enum Pin {
+    /// Only a certificate with this fingerprint
+    Fingerprint(String),
+    /// The first certificate seen, then only that one
+    TrustOnFirstUse {
+        pinned: Mutex<Option<String>>,
+        on_first_use: TrustCallback,
+    },
+    /// Anything
+    Any,
+}
+
+/// Verifies the server certificate against a pin instead of a CA chain
+///
+/// Handshake signatures are still checked, so the server must hold the
+/// private key of the pinned certificate.
+struct PinVerifier {
+    pin: Pin,
+    algorithms: crypto::WebPkiSupportedAlgorithms,
+}
+
+impl PinVerifier {
+    fn new(pin: Pin, provider: &CryptoProvider) -> Arc<Self> {
+        Arc::new(Self {
+            pin,
+            algorithms: provider.signature_verification_algorithms,
+        })
+    }
+
+    fn check(&self, cert: &[u8]) -> bool {
+        match &self.pin {
+            Pin::Fingerprint(expected) => fingerprint(cert) == *expected,
+            Pin::TrustOnFirstUse {
+                pinned,
+                on_first_use,
+            } => {
+                let mut pinned = pinned.lock().unwrap_or_else(|e| e.into_inner());
+                match pinned.as_deref() {
+                    Some(expected) => fingerprint(cert) == expected,
+                    None => {
+                        let cert = ServerCertificate::from_der(cert);
+                        *pinned = Some(cert.fingerprint());
+                        on_first_use(&cert);
+                        true
+                    }
+                }
+            }
+            Pin::Any => true,
+        }
+    }
+}
+
+impl fmt::Debug for PinVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinVerifier").finish_non_exhaustive()
+    }
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        if self.check(end_entity) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn verifier(pin: Pin) -> Arc<PinVerifier> {
+        PinVerifier::new(pin, &crypto::ring::default_provider())
+    }
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        assert_eq!(normalize_fingerprint("AB:cd:0F"), "abcd0f");
+        assert!(matches!(
+            ServerVerification::fingerprint("AA:BB"),
+            ServerVerification::Fingerprint(fp) if fp == "aabb"
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint_pin() {
+        let pin = Pin::Fingerprint(fingerprint(b"cert-a"));
+        let verifier = verifier(pin);
+        assert!(verifier.check(b"cert-a"));
+        assert!(!verifier.check(b"cert-b"));
+    }
+
+    #[test]
+    fn test_trust_on_first_use() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let pin = Pin::TrustOnFirstUse {
+            pinned: Mutex::new(None),
+            on_first_use: Arc::new(move |cert| {
+                assert_eq!(cert.fingerprint(), fingerprint(b"cert-a"));
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        };
+        let verifier = verifier(pin);
+
+        assert!(verifier.check(b"cert-a"));
+        assert!(verifier.check(b"cert-a"));
+        assert!(!verifier.check(b"cert-b"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_trust_on_first_use_with_known_fingerprint() {
+        let pin = Pin::TrustOnFirstUse {
+            pinned: Mutex::new(Some(fingerprint(b"cert-a"))),
+            on_first_use: Arc::new(|_| panic!("certificate already known")),
+        };
+        let verifier = verifier(pin);
+
+        assert!(verifier.check(b"cert-a"));
+        assert!(!verifier.check(b"cert-b"));
+    }
+
+    #[test]
+    fn test_server_certificate_pem_round_trip() {
+        let cert = ServerCertificate::from_der(vec![0x30, 0x03, 0x02, 0x01, 0x01]);
+        let parsed = ServerCertificate::from_pem(cert.to_pem().as_bytes()).unwrap();
+        assert_eq!(parsed, cert);
+        assert_eq!(parsed.fingerprint().len(), 64);
+    }
+}
@@ -1,22 +1,44 @@
 //! Unix socket transport for LXD API
 
-use crate::transport::Transport;
+use crate::transport::upgrade;
+use crate::transport::websocket::{self, Io, WebSocket};
+use crate::transport::{self, ByteStream, RawRequest, RawResponse, RequestBody, Transport};
 use crate::{Error, Result};
 use bytes::Bytes;
-use http_body_util::{BodyExt, Full};
-use hyper::body::Incoming;
+use futures::StreamExt;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
 use hyper::{Method, Request, Response, StatusCode};
 use hyper_util::client::legacy::Client;
 use hyperlocal::{UnixClientExt, UnixConnector, Uri};
 use serde::{de::DeserializeOwned, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+/// Well-known socket locations, checked after `LXD_SOCKET` and `LXD_DIR`
+const KNOWN_SOCKETS: &[&str] = &[
+    "/var/snap/lxd/common/lxd/unix.socket",
+    "/var/lib/lxd/unix.socket",
+    "/var/lib/incus/unix.socket",
+    "/run/incus/unix.socket",
+];
 
 /// Unix socket transport for LXD API
 pub struct UnixSocketTransport {
     socket_path: PathBuf,
-    client: Client<UnixConnector, Full<Bytes>>,
+    client: Client<UnixConnector, HyperBody>,
 }
 
+/// Body type sent by the hyper client
+type HyperBody = UnsyncBoxBody<Bytes, io::Error>;
+
 impl UnixSocketTransport {
     /// Create a new Unix socket transport
     pub fn new(socket_path: impl AsRef<Path>) -> Result<Self> {
@@ -29,7 +51,7 @@ impl UnixSocketTransport {
             )));
         }
 
-        let client: Client<UnixConnector, Full<Bytes>> = Client::unix();
+        let client: Client<UnixConnector, HyperBody> = Client::unix();
 
         Ok(Self {
             socket_path,
@@ -37,13 +59,59 @@ impl UnixSocketTransport {
         })
     }
 
+    /// Find the local LXD socket and create a transport for it
+    ///
+    /// The candidates from [`UnixSocketTransport::candidates`] are tried in
+    /// order and the first socket that accepts a connection is used. When
+    /// none does, the error lists every path that was tried and why it was
+    /// skipped. If a socket the current user may not connect to was found,
+    /// the error also names the group that owns it.
+    pub fn discover() -> Result<Self> {
+        let path = probe(Self::candidates(), |path| {
+            UnixStream::connect(path).map(drop)
+        })
+        .map_err(Error::connection)?;
+        Self::new(path)
+    }
+
+    /// Socket paths checked by [`UnixSocketTransport::discover`], in order
+    ///
+    /// `LXD_SOCKET` comes first, then `unix.socket` in `LXD_DIR`, then the
+    /// snap, native package and Incus locations.
+    pub fn candidates() -> Vec<PathBuf> {
+        candidates(env::var_os("LXD_SOCKET"), env::var_os("LXD_DIR"))
+    }
+
+    /// Open a websocket on the socket
+    pub(crate) async fn websocket(&self, path: &str) -> Result<WebSocket> {
+        let stream = tokio::net::UnixStream::connect(&self.socket_path)
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+        let request = format!("ws://localhost{}", path)
+            .into_client_request()
+            .map_err(websocket::error)?;
+        websocket::handshake(Box::new(stream), request).await
+    }
+
+    /// Upgrade a connection on the socket to `protocol`
+    pub(crate) async fn upgrade(&self, path: &str, protocol: &str) -> Result<Box<dyn Io>> {
+        let stream = tokio::net::UnixStream::connect(&self.socket_path)
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(path)
+            .header("Host", "localhost");
+        upgrade::upgrade(Box::new(stream), request, protocol).await
+    }
+
     /// Build a request
     fn build_request(
         &self,
         method: Method,
         path: &str,
         body: Option<Bytes>,
-    ) -> Result<Request<Full<Bytes>>> {
+    ) -> Result<Request<HyperBody>> {
         let uri = Uri::new(&self.socket_path, path);
 
         let builder = Request::builder()
@@ -55,12 +123,44 @@ impl UnixSocketTransport {
         let body = body.unwrap_or_default();
 
         builder
-            .body(Full::new(body))
+            .body(full(body))
+            .map_err(|e| Error::request(e.to_string()))
+    }
+
+    /// Build a request with a raw body
+    fn build_raw_request(&self, request: RawRequest) -> Result<Request<HyperBody>> {
+        let uri = Uri::new(&self.socket_path, &request.path);
+
+        let mut builder = Request::builder()
+            .method(request.method)
+            .uri(uri)
+            .header("Host", "localhost");
+        if !request
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+        {
+            builder = builder.header("Content-Type", "application/octet-stream");
+        }
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+
+        let body = match request.body {
+            RequestBody::Empty => full(Bytes::new()),
+            RequestBody::Bytes(bytes) => full(bytes),
+            RequestBody::Stream(stream) => {
+                StreamBody::new(stream.map(|chunk| chunk.map(Frame::data))).boxed_unsync()
+            }
+        };
+
+        builder
+            .body(body)
             .map_err(|e| Error::request(e.to_string()))
     }
 
     /// Send a request and parse the response
-    async fn send_request<T: DeserializeOwned>(&self, request: Request<Full<Bytes>>) -> Result<T> {
+    async fn send_request<T: DeserializeOwned>(&self, request: Request<HyperBody>) -> Result<T> {
         let response: Response<Incoming> = self
             .client
             .request(request)
@@ -90,6 +190,86 @@ impl UnixSocketTransport {
     }
 }
 
+/// Wrap an in-memory body
+fn full(body: Bytes) -> HyperBody {
+    Full::new(body)
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+fn candidates(socket: Option<OsString>, dir: Option<OsString>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(socket) = socket.filter(|s| !s.is_empty()) {
+        paths.push(PathBuf::from(socket));
+    }
+    if let Some(dir) = dir.filter(|d| !d.is_empty()) {
+        paths.push(PathBuf::from(dir).join("unix.socket"));
+    }
+    for known in KNOWN_SOCKETS {
+        let path = PathBuf::from(known);
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Return the first of `candidates` that `connect` succeeds on, or explain
+/// why each one was skipped
+fn probe(
+    candidates: Vec<PathBuf>,
+    connect: impl Fn(&Path) -> io::Result<()>,
+) -> std::result::Result<PathBuf, String> {
+    let mut tried = Vec::new();
+    let mut denied = None;
+
+    for path in candidates {
+        match connect(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                tried.push(format!("{} (permission denied)", path.display()));
+                denied.get_or_insert_with(|| permission_denied(&path));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                tried.push(format!("{} (not found)", path.display()));
+            }
+            Err(e) => tried.push(format!("{} ({})", path.display(), e)),
+        }
+    }
+
+    let tried = tried.join(", ");
+    Err(match denied {
+        Some(denied) => format!("{}; tried: {}", denied, tried),
+        None => format!("No LXD unix socket found, tried: {}", tried),
+    })
+}
+
+/// Explain a permission error on `path`, naming the group that owns it
+fn permission_denied(path: &Path) -> String {
+    let group = fs::metadata(path)
+        .ok()
+        .and_then(|meta| group_name(meta.gid()))
+        .unwrap_or_else(|| "lxd".to_string());
+    format!(
+        "Permission denied on {}: the current user is not in the '{}' group \
+         (add it with `sudo usermod -aG {} $USER` and log in again)",
+        path.display(),
+        group,
+        group
+    )
+}
+
+/// Look up a group name in /etc/group
+fn group_name(gid: u32) -> Option<String> {
+    let groups = fs::read_to_string("/etc/group").ok()?;
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id = fields.nth(1)?.parse::<u32>().ok()?;
+        (id == gid).then(|| name.to_string())
+    })
+}
+
 impl Transport for UnixSocketTransport {
     async fn get<T: DeserializeOwned + Send>(&self, path: &str) -> Result<T> {
         let request = self.build_request(Method::GET, path, None)?;
@@ -130,4 +310,99 @@ impl Transport for UnixSocketTransport {
         let request = self.build_request(Method::DELETE, path, None)?;
         self.send_request(request).await
     }
+
+    async fn send_raw(&self, request: RawRequest) -> Result<RawResponse> {
+        let request = self.build_raw_request(request)?;
+        let response: Response<Incoming> = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| Error::connection(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| Error::request(e.to_string()))?
+                .to_bytes();
+            return Err(transport::response_error(status.as_u16(), &body));
+        }
+
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.to_string(), value)
+            })
+            .collect();
+        let body = response
+            .into_body()
+            .into_data_stream()
+            .map(|chunk| chunk.map_err(|e| Error::request(e.to_string())));
+
+        Ok(RawResponse {
+            status: status.as_u16(),
+            headers,
+            body: ByteStream::new(body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_order() {
+        let paths = candidates(Some("/custom/lxd.socket".into()), Some("/srv/lxd".into()));
+        assert_eq!(paths[0], PathBuf::from("/custom/lxd.socket"));
+        assert_eq!(paths[1], PathBuf::from("/srv/lxd/unix.socket"));
+        assert_eq!(paths[2], PathBuf::from(KNOWN_SOCKETS[0]));
+        assert_eq!(paths.len(), 2 + KNOWN_SOCKETS.len());
+    }
+
+    #[test]
+    fn test_probe_keeps_going_after_permission_denied() {
+        let refuse = |path: &Path| match path.to_str() {
+            Some("/denied.socket") => Err(io::ErrorKind::PermissionDenied.into()),
+            Some("/live.socket") => Ok(()),
+            _ => Err(io::ErrorKind::NotFound.into()),
+        };
+
+        let found = probe(vec!["/denied.socket".into(), "/live.socket".into()], refuse);
+        assert_eq!(found, Ok(PathBuf::from("/live.socket")));
+
+        let message = probe(
+            vec![
+                "/missing.socket".into(),
+                "/denied.socket".into(),
+                "/other.socket".into(),
+            ],
+            refuse,
+        )
+        .unwrap_err();
+        assert!(
+            message.starts_with("Permission denied on /denied.socket"),
+            "{}",
+            message
+        );
+        assert!(
+            message.ends_with(
+                "tried: /missing.socket (not found), /denied.socket (permission denied), \
+                 /other.socket (not found)"
+            ),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_candidates_skip_duplicates_and_empty() {
+        let paths = candidates(Some("".into()), Some("/var/lib/lxd".into()));
+        assert_eq!(paths[0], PathBuf::from("/var/lib/lxd/unix.socket"));
+        assert_eq!(paths.len(), KNOWN_SOCKETS.len());
+    }
 }
@@ -0,0 +1,51 @@
+//! HTTP connection upgrades shared by both transports
+
+use crate::transport::{self, websocket::Io};
+use crate::{Error, Result};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper::http::request::Builder;
+use hyper::StatusCode;
+use hyper_util::rt::TokioIo;
+
+/// Upgrade an open connection to `protocol`, returning the raw stream
+///
+/// `request` carries the method, path and any credentials; the upgrade
+/// headers are added here. A response other than `101 Switching Protocols`
+/// is turned into the same error a regular request would produce.
+pub(crate) async fn upgrade(
+    stream: Box<dyn Io>,
+    request: Builder,
+    protocol: &str,
+) -> Result<Box<dyn Io>> {
+    let request = request
+        .header("Connection", "Upgrade")
+        .header("Upgrade", protocol)
+        .body(Empty::<Bytes>::new())
+        .map_err(|e| Error::request(e.to_string()))?;
+
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| Error::connection(e.to_string()))?;
+    tokio::spawn(connection.with_upgrades());
+
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(|e| Error::connection(e.to_string()))?;
+    let status = response.status();
+    if status != StatusCode::SWITCHING_PROTOCOLS {
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| Error::request(e.to_string()))?
+            .to_bytes();
+        return Err(transport::response_error(status.as_u16(), &body));
+    }
+
+    let upgraded = hyper::upgrade::on(response)
+        .await
+        .map_err(|e| Error::connection(e.to_string()))?;
+    Ok(Box::new(TokioIo::new(upgraded)))
+}
@@ -0,0 +1,136 @@
+//! WebSocket connections shared by both transports
+
+use crate::transport;
+use crate::{Error, Result};
+use bytes::Bytes;
+use futures::{ready, SinkExt, StreamExt};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::{self, handshake::client::Request, Message};
+use tokio_tungstenite::WebSocketStream;
+
+/// A byte stream a websocket can run over
+pub(crate) trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// A websocket connected to LXD over either transport
+pub(crate) type WebSocket = WebSocketStream<Box<dyn Io>>;
+
+/// Perform the websocket handshake for `request` over an open connection
+pub(crate) async fn handshake(stream: Box<dyn Io>, request: Request) -> Result<WebSocket> {
+    let (socket, _) = tokio_tungstenite::client_async(request, stream)
+        .await
+        .map_err(error)?;
+    Ok(socket)
+}
+
+/// Convert a websocket error
+///
+/// A rejected handshake carries the HTTP response, which is turned into the
+/// same API or HTTP error a regular request would produce.
+pub(crate) fn error(err: tungstenite::Error) -> Error {
+    match err {
+        tungstenite::Error::Http(response) => {
+            let status = response.status().as_u16();
+            let body = response.into_body().unwrap_or_default();
+            transport::response_error(status, &body)
+        }
+        tungstenite::Error::Io(e) => Error::connection(e.to_string()),
+        other => Error::WebSocket(other.to_string()),
+    }
+}
+
+/// Byte stream over an LXD websocket
+///
+/// Binary (and text) messages received are read as bytes, and every write
+/// is sent as one binary message and flushed. Shutting down the writer closes the
+/// websocket, which LXD treats as end of input.
+pub struct WebSocketIo {
+    socket: WebSocket,
+    buffer: Bytes,
+    eof: bool,
+}
+
+impl WebSocketIo {
+    pub(crate) fn new(socket: WebSocket) -> Self {
+        Self {
+            socket,
+            buffer: Bytes::new(),
+            eof: false,
+        }
+    }
+}
+
+impl AsyncRead for WebSocketIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.buffer.is_empty() && !self.eof {
+            match ready!(self.socket.poll_next_unpin(cx)) {
+                Some(Ok(Message::Binary(data))) => self.buffer = data,
+                Some(Ok(Message::Text(text))) => self.buffer = Bytes::from(text),
+                Some(Ok(Message::Close(_)))
+                | Some(Err(tungstenite::Error::ConnectionClosed))
+                | None => self.eof = true,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Poll::Ready(Err(io_error(e))),
+            }
+        }
+
+        let len = self.buffer.len().min(buf.remaining());
+        let data = self.buffer.split_to(len);
+        buf.put_slice(&data);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for WebSocketIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.socket.poll_ready_unpin(cx)).map_err(io_error)?;
+        self.socket
+            .start_send_unpin(Message::binary(buf.to_vec()))
+            .map_err(io_error)?;
+        // Send the frame right away; a pending flush resumes on the next write or flush
+        if let Poll::Ready(Err(e)) = self.socket.poll_flush_unpin(cx) {
+            return Poll::Ready(Err(io_error(e)));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_flush_unpin(cx).map_err(io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match ready!(self.socket.poll_close_unpin(cx)) {
+            Ok(()) | Err(tungstenite::Error::ConnectionClosed) => Poll::Ready(Ok(())),
+            Err(e) => Poll::Ready(Err(io_error(e))),
+        }
+    }
+}
+
+impl std::fmt::Debug for WebSocketIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketIo")
+            .field("buffered", &self.buffer.len())
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Convert a websocket error for the `AsyncRead`/`AsyncWrite` interface
+fn io_error(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(e) => e,
+        other => io::Error::other(other.to_string()),
+    }
+}
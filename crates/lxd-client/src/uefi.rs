@@ -0,0 +1,290 @@
+//! UEFI variables of virtual machines
+//!
+//! LXD exposes the firmware variable store at `/1.0/instances/{name}/uefi-vars`,
+//! keyed by `<name>-<GUID>` with hex-encoded values. [`UefiVars`] decodes them
+//! and knows the layout of the Secure Boot and boot-order variables.
+
+use std::collections::BTreeMap;
+
+use lxd_types::{InstanceUEFIVariable, InstanceUEFIVars};
+
+use crate::error::{Error, Result};
+
+/// GUID of the variables defined by the UEFI specification, such as `BootOrder`
+pub const EFI_GLOBAL_VARIABLE_GUID: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+/// GUID of the OVMF `SecureBootEnable` variable
+pub const SECURE_BOOT_ENABLE_GUID: &str = "f0a30bc7-af08-4556-99c4-001009c93a44";
+
+/// A decoded UEFI variable
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UefiVariable {
+    /// Attribute bits, see the `UefiVariable::*` constants
+    pub attributes: u32,
+    /// Variable data
+    pub data: Vec<u8>,
+    /// Digest of the authenticated variable's signer, empty if unused
+    pub digest: Vec<u8>,
+    /// Timestamp of an authenticated write, empty if unused
+    pub timestamp: Vec<u8>,
+}
+
+impl UefiVariable {
+    /// The variable persists across resets
+    pub const NON_VOLATILE: u32 = 0x1;
+    /// The variable is readable during boot
+    pub const BOOTSERVICE_ACCESS: u32 = 0x2;
+    /// The variable is readable by the operating system
+    pub const RUNTIME_ACCESS: u32 = 0x4;
+    /// Writes need a time-based authenticated signature
+    pub const TIME_BASED_AUTHENTICATED_WRITE_ACCESS: u32 = 0x20;
+
+    /// Create a variable with `attributes` holding `data`
+    pub fn new(attributes: u32, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            attributes,
+            data: data.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Decode an API entry
+    #[cfg(not(feature = "generated"))]
+    fn from_api(key: &str, variable: &InstanceUEFIVariable) -> Result<Self> {
+        Ok(Self {
+            attributes: attributes(key, variable.attr)?,
+            data: decode(key, &variable.data)?,
+            digest: decode(key, &variable.digest)?,
+            timestamp: decode(key, &variable.timestamp)?,
+        })
+    }
+
+    /// Decode an API entry
+    #[cfg(feature = "generated")]
+    fn from_api(key: &str, variable: &InstanceUEFIVariable) -> Result<Self> {
+        let decode_field = |value: &Option<String>| decode(key, value.as_deref().unwrap_or(""));
+        Ok(Self {
+            attributes: attributes(key, variable.attr.unwrap_or_default())?,
+            data: decode_field(&variable.data)?,
+            digest: decode_field(&variable.digest)?,
+            timestamp: decode_field(&variable.timestamp)?,
+        })
+    }
+
+    /// Encode as an API entry
+    #[cfg(not(feature = "generated"))]
+    fn to_api(&self) -> InstanceUEFIVariable {
+        InstanceUEFIVariable {
+            attr: i64::from(self.attributes),
+            data: encode(&self.data),
+            digest: encode(&self.digest),
+            timestamp: encode(&self.timestamp),
+        }
+    }
+
+    /// Encode as an API entry
+    #[cfg(feature = "generated")]
+    fn to_api(&self) -> InstanceUEFIVariable {
+        InstanceUEFIVariable {
+            attr: Some(i64::from(self.attributes)),
+            data: Some(encode(&self.data)),
+            digest: Some(encode(&self.digest)),
+            timestamp: Some(encode(&self.timestamp)),
+        }
+    }
+}
+
+/// The UEFI variable store of a virtual machine
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UefiVars {
+    variables: BTreeMap<String, UefiVariable>,
+}
+
+impl UefiVars {
+    /// Create an empty variable store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the variable `name` in the `guid` namespace
+    pub fn get(&self, guid: &str, name: &str) -> Option<&UefiVariable> {
+        self.variables.get(&key(guid, name))
+    }
+
+    /// Set the variable `name` in the `guid` namespace, returning the old one
+    pub fn set(&mut self, guid: &str, name: &str, variable: UefiVariable) -> Option<UefiVariable> {
+        self.variables.insert(key(guid, name), variable)
+    }
+
+    /// Remove the variable `name` in the `guid` namespace
+    pub fn remove(&mut self, guid: &str, name: &str) -> Option<UefiVariable> {
+        self.variables.remove(&key(guid, name))
+    }
+
+    /// Iterate over the variables as `(guid, name, variable)`
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &UefiVariable)> {
+        self.variables.iter().map(|(key, variable)| {
+            let (name, guid) = split_key(key);
+            (guid, name, variable)
+        })
+    }
+
+    /// Number of variables
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// Whether there are no variables
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Whether Secure Boot is enabled, if the firmware stores the setting
+    pub fn secure_boot(&self) -> Option<bool> {
+        let variable = self.get(SECURE_BOOT_ENABLE_GUID, "SecureBootEnable")?;
+        Some(variable.data.first().is_some_and(|enabled| *enabled != 0))
+    }
+
+    /// Enable or disable Secure Boot
+    pub fn set_secure_boot(&mut self, enabled: bool) {
+        let attributes = UefiVariable::NON_VOLATILE | UefiVariable::BOOTSERVICE_ACCESS;
+        self.update(
+            SECURE_BOOT_ENABLE_GUID,
+            "SecureBootEnable",
+            attributes,
+            vec![u8::from(enabled)],
+        );
+    }
+
+    /// The boot order as `Boot####` option numbers, if set
+    pub fn boot_order(&self) -> Option<Vec<u16>> {
+        let variable = self.get(EFI_GLOBAL_VARIABLE_GUID, "BootOrder")?;
+        Some(
+            variable
+                .data
+                .chunks_exact(2)
+                .map(|option| u16::from_le_bytes([option[0], option[1]]))
+                .collect(),
+        )
+    }
+
+    /// Set the boot order to the `Boot####` option numbers in `order`
+    pub fn set_boot_order(&mut self, order: &[u16]) {
+        let attributes = UefiVariable::NON_VOLATILE
+            | UefiVariable::BOOTSERVICE_ACCESS
+            | UefiVariable::RUNTIME_ACCESS;
+        let data = order
+            .iter()
+            .flat_map(|option| option.to_le_bytes())
+            .collect();
+        self.update(EFI_GLOBAL_VARIABLE_GUID, "BootOrder", attributes, data);
+    }
+
+    /// Replace the data of a variable, keeping the attributes of an existing one
+    fn update(&mut self, guid: &str, name: &str, attributes: u32, data: Vec<u8>) {
+        self.variables
+            .entry(key(guid, name))
+            .and_modify(|variable| variable.data = data.clone())
+            .or_insert_with(|| UefiVariable::new(attributes, data));
+    }
+
+    /// Decode the variables of an API response
+    #[cfg(not(feature = "generated"))]
+    pub(crate) fn from_api(vars: &InstanceUEFIVars) -> Result<Self> {
+        decode_all(&vars.variables)
+    }
+
+    /// Decode the variables of an API response
+    #[cfg(feature = "generated")]
+    pub(crate) fn from_api(vars: &InstanceUEFIVars) -> Result<Self> {
+        decode_all(vars.variables.as_ref().unwrap_or(&BTreeMap::new()))
+    }
+
+    /// Encode the variables for an API request
+    pub(crate) fn to_api(&self) -> InstanceUEFIVars {
+        let variables = self
+            .variables
+            .iter()
+            .map(|(key, variable)| (key.clone(), variable.to_api()))
+            .collect();
+        #[cfg(feature = "generated")]
+        let variables = Some(variables);
+        InstanceUEFIVars { variables }
+    }
+}
+
+/// Decode every entry of an API variables map
+fn decode_all(variables: &BTreeMap<String, InstanceUEFIVariable>) -> Result<UefiVars> {
+    let variables = variables
+        .iter()
+        .map(|(key, variable)| Ok((key.clone(), UefiVariable::from_api(key, variable)?)))
+        .collect::<Result<_>>()?;
+    Ok(UefiVars { variables })
+}
+
+/// The map key of variable `name` in the `guid` namespace
+fn key(guid: &str, name: &str) -> String {
+    format!("{}-{}", name, guid.to_ascii_lowercase())
+}
+
+/// Split a map key into the variable name and GUID
+fn split_key(key: &str) -> (&str, &str) {
+    // A GUID is 36 characters, after the separating dash
+    match key.len().checked_sub(37) {
+        Some(split) if key.as_bytes()[split] == b'-' && key.is_char_boundary(split) => {
+            (&key[..split], &key[split + 1..])
+        }
+        _ => (key, ""),
+    }
+}
+
+/// Check that an API attribute value fits the 32-bit attribute field
+fn attributes(key: &str, attr: i64) -> Result<u32> {
+    u32::try_from(attr)
+        .map_err(|_| Error::Other(format!("Invalid attributes of UEFI variable {}", key)))
+}
+
+/// Hex-encode a value
+fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex-encoded value of the variable `key`
+fn decode(key: &str, hex: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::Other(format!("Invalid hex value in UEFI variable {}", key));
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(invalid());
+    }
+    pairs
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = vec![0x00, 0x01, 0xab, 0xff];
+        assert_eq!(encode(&bytes), "0001abff");
+        assert_eq!(decode("k", "0001ABff").unwrap(), bytes);
+        assert!(decode("k", "abc").is_err());
+        assert!(decode("k", "zz").is_err());
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(
+            split_key("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"),
+            ("BootOrder", EFI_GLOBAL_VARIABLE_GUID)
+        );
+        assert_eq!(split_key("short"), ("short", ""));
+    }
+}
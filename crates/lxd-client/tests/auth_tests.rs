@@ -0,0 +1,233 @@
+//! Bearer-token and OIDC authentication tests against local servers
+
+mod common;
+
+use common::{MockHttp, MockHttps, MockRequest, MockResponse};
+use lxd_client::{
+    BearerAuth, Client, ClientConfig, Error, OidcClient, OidcConfig, OidcCredentials, OidcTokens,
+    ServerVerification,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Start an LXD stand-in that only answers requests carrying `token`
+async fn lxd_server(token: &'static str) -> (MockHttps, ServerVerification) {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let verification = ServerVerification::PinnedCertificate(certified.cert.pem().into_bytes());
+    let server = MockHttps::start(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        move |request: &MockRequest| {
+            if request.header("authorization") == Some(&format!("Bearer {}", token)) {
+                Some(MockResponse::sync(serde_json::json!(["/1.0/instances/c1"])))
+            } else {
+                Some(MockResponse::error(403, "not authorized"))
+            }
+        },
+    )
+    .await;
+    (server, verification)
+}
+
+fn oidc_client(idp: &MockHttp) -> OidcClient {
+    OidcClient::new(OidcConfig::new(
+        "lxd",
+        format!("{}/device", idp.url()),
+        format!("{}/token", idp.url()),
+    ))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[tokio::test]
+async fn test_static_bearer_token() {
+    let (server, verification) = lxd_server("secret").await;
+    let client =
+        Client::new_https_with_auth(server.url(), BearerAuth::token("secret"), verification)
+            .unwrap()
+            .with_config(ClientConfig::new().no_retries());
+
+    let instances = client.list_instances().await.unwrap();
+    assert_eq!(instances, vec!["/1.0/instances/c1"]);
+}
+
+#[tokio::test]
+async fn test_wrong_bearer_token_rejected() {
+    let (server, verification) = lxd_server("secret").await;
+    let client =
+        Client::new_https_with_auth(server.url(), BearerAuth::token("other"), verification)
+            .unwrap()
+            .with_config(ClientConfig::new().no_retries());
+
+    let err = client.list_instances().await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 403, .. }));
+}
+
+#[tokio::test]
+async fn test_device_code_flow() {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let counter = polls.clone();
+    let idp = MockHttp::start(move |request| match request.path.as_str() {
+        "/device" => {
+            assert_eq!(request.form("client_id").as_deref(), Some("lxd"));
+            Some(MockResponse::json(
+                200,
+                serde_json::json!({
+                    "device_code": "dev",
+                    "user_code": "ABCD-EFGH",
+                    "verification_uri": "https://idp.example/activate",
+                    "expires_in": 60,
+                    "interval": 0
+                }),
+            ))
+        }
+        "/token" => {
+            assert_eq!(request.form("device_code").as_deref(), Some("dev"));
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                Some(MockResponse::json(
+                    400,
+                    serde_json::json!({"error": "authorization_pending"}),
+                ))
+            } else {
+                Some(MockResponse::json(
+                    200,
+                    serde_json::json!({
+                        "access_token": "access",
+                        "refresh_token": "refresh",
+                        "expires_in": 300
+                    }),
+                ))
+            }
+        }
+        _ => Some(MockResponse::json(404, serde_json::json!({}))),
+    })
+    .await;
+
+    let oidc = oidc_client(&idp);
+    let authorization = oidc.start_device_flow().await.unwrap();
+    assert_eq!(authorization.user_code, "ABCD-EFGH");
+
+    let tokens = oidc.poll_device_flow(&authorization).await.unwrap();
+    assert_eq!(tokens.access_token, "access");
+    assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+    assert!(tokens.expires_at.unwrap() > now());
+    assert_eq!(polls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_device_code_flow_denied() {
+    let idp = MockHttp::start(|_| {
+        Some(MockResponse::json(
+            400,
+            serde_json::json!({"error": "access_denied"}),
+        ))
+    })
+    .await;
+    let authorization = serde_json::from_value(serde_json::json!({
+        "device_code": "dev",
+        "user_code": "ABCD",
+        "verification_uri": "https://idp.example/activate",
+        "expires_in": 60,
+        "interval": 0
+    }))
+    .unwrap();
+
+    let err = oidc_client(&idp)
+        .poll_device_flow(&authorization)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Auth(msg) if msg == "access_denied"));
+}
+
+#[tokio::test]
+async fn test_expiring_token_is_refreshed() {
+    let idp = MockHttp::start(|request| {
+        assert_eq!(request.form("grant_type").as_deref(), Some("refresh_token"));
+        assert_eq!(request.form("refresh_token").as_deref(), Some("refresh"));
+        Some(MockResponse::json(
+            200,
+            serde_json::json!({"access_token": "fresh", "expires_in": 300}),
+        ))
+    })
+    .await;
+    let (server, verification) = lxd_server("fresh").await;
+
+    let refreshed = Arc::new(Mutex::new(None));
+    let store = refreshed.clone();
+    let credentials = OidcCredentials::new(
+        oidc_client(&idp),
+        OidcTokens::new("stale")
+            .with_refresh_token("refresh")
+            .with_expires_at(now() + 10),
+    )
+    .on_refresh(move |tokens| *store.lock().unwrap() = Some(tokens.clone()));
+
+    let client = Client::new_https_with_auth(
+        server.url(),
+        BearerAuth::Oidc(credentials.clone()),
+        verification,
+    )
+    .unwrap()
+    .with_config(ClientConfig::new().no_retries());
+
+    client.list_instances().await.unwrap();
+    client.list_instances().await.unwrap();
+    assert_eq!(idp.hits(), 1);
+
+    let tokens = refreshed.lock().unwrap().clone().unwrap();
+    assert_eq!(tokens.access_token, "fresh");
+    // The refresh token is kept when the provider does not rotate it
+    assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+    assert_eq!(credentials.tokens().await, tokens);
+}
+
+#[tokio::test]
+async fn test_expired_token_without_refresh_token() {
+    let idp = MockHttp::start(|_| None).await;
+    let (server, verification) = lxd_server("fresh").await;
+    let credentials = OidcCredentials::new(
+        oidc_client(&idp),
+        OidcTokens::new("stale").with_expires_at(now()),
+    );
+    let client =
+        Client::new_https_with_auth(server.url(), BearerAuth::Oidc(credentials), verification)
+            .unwrap()
+            .with_config(ClientConfig::new().no_retries());
+
+    let err = client.list_instances().await.unwrap_err();
+    assert!(matches!(err, Error::Auth(_)));
+    assert_eq!(server.hits(), 0);
+    assert_eq!(idp.hits(), 0);
+}
+
+#[tokio::test]
+async fn test_discovery() {
+    let idp = MockHttp::start(|request| {
+        assert_eq!(request.path, "/.well-known/openid-configuration");
+        Some(MockResponse::json(
+            200,
+            serde_json::json!({
+                "issuer": "https://idp.example",
+                "device_authorization_endpoint": "https://idp.example/device",
+                "token_endpoint": "https://idp.example/token"
+            }),
+        ))
+    })
+    .await;
+
+    let oidc = OidcClient::discover(&format!("{}/", idp.url()), "lxd")
+        .await
+        .unwrap();
+    assert_eq!(oidc.config().client_id, "lxd");
+    assert_eq!(
+        oidc.config().device_authorization_endpoint,
+        "https://idp.example/device"
+    );
+    assert_eq!(oidc.config().token_endpoint, "https://idp.example/token");
+}
@@ -0,0 +1,147 @@
+//! Instance backup tests against a stand-in LXD socket
+
+#![cfg(not(feature = "generated"))]
+
+mod common;
+
+use common::{MockRequest, MockResponse, MockSocket};
+use lxd_client::{BackupImportOptions, Client};
+use lxd_types::InstanceBackupsPost;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_backup_lifecycle() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1/backups?project=web") => {
+                MockResponse::sync(json!(["/1.0/instances/c1/backups/nightly"]))
+            }
+            ("GET", "/1.0/instances/c1/backups?recursion=1&project=web") => {
+                MockResponse::sync(json!([{
+                    "name": "nightly",
+                    "created_at": "2024-05-01T02:00:00Z",
+                    "expires_at": "2024-05-08T02:00:00Z",
+                    "instance_only": true,
+                    "container_only": true,
+                    "optimized_storage": false
+                }]))
+            }
+            ("GET", "/1.0/instances/c1/backups/nightly?project=web") => {
+                MockResponse::sync(json!({"name": "nightly", "optimized_storage": true}))
+            }
+            ("POST", "/1.0/instances/c1/backups?project=web") => {
+                MockResponse::task("create", "Running")
+            }
+            ("POST", "/1.0/instances/c1/backups/nightly?project=web") => {
+                MockResponse::task("rename", "Running")
+            }
+            ("DELETE", "/1.0/instances/c1/backups/weekly?project=web") => {
+                MockResponse::task("delete", "Running")
+            }
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let urls = client.list_instance_backups("c1").await.unwrap();
+    assert_eq!(urls, vec!["/1.0/instances/c1/backups/nightly"]);
+
+    let backups = client.list_instance_backups_full("c1").await.unwrap();
+    assert_eq!(backups[0].name, "nightly");
+    assert_eq!(backups[0].expires_at, "2024-05-08T02:00:00Z");
+    assert!(backups[0].instance_only);
+
+    let backup = client.get_instance_backup("c1", "nightly").await.unwrap();
+    assert!(backup.optimized_storage);
+    assert_eq!(backup.created_at, "");
+
+    let request = InstanceBackupsPost::new("nightly")
+        .instance_only(true)
+        .optimized_storage(true)
+        .compression_algorithm("zstd")
+        .expires_at("2024-05-08T02:00:00Z");
+    let handle = client.create_instance_backup("c1", &request).await.unwrap();
+    assert_eq!(handle.id(), "create");
+
+    let handle = client
+        .rename_instance_backup("c1", "nightly", "weekly")
+        .await
+        .unwrap();
+    assert_eq!(handle.id(), "rename");
+
+    let handle = client.delete_instance_backup("c1", "weekly").await.unwrap();
+    assert_eq!(handle.id(), "delete");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[3].json(),
+        json!({
+            "name": "nightly",
+            "instance_only": true,
+            "optimized_storage": true,
+            "compression_algorithm": "zstd",
+            "expires_at": "2024-05-08T02:00:00Z"
+        })
+    );
+    assert_eq!(requests[4].json(), json!({"name": "weekly"}));
+}
+
+#[tokio::test]
+async fn test_export_and_import_backup() {
+    let tarball: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let served = tarball.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1/backups/nightly/export") => {
+                MockResponse::raw(200, served.clone())
+            }
+            ("POST", "/1.0/instances") => MockResponse::task("import", "Running"),
+            _ => MockResponse::error(404, "Backup not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let mut exported = Vec::new();
+    let written = client
+        .export_instance_backup("c1", "nightly", &mut exported)
+        .await
+        .unwrap();
+    assert_eq!(written, 300_000);
+    assert_eq!(exported, tarball);
+
+    let err = client
+        .export_instance_backup("c1", "missing", &mut Vec::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Backup not found"), "{}", err);
+
+    let options = BackupImportOptions::new()
+        .with_pool("fast")
+        .with_name("c1-restored");
+    let handle = client
+        .import_instance_backup(std::io::Cursor::new(tarball.clone()), &options)
+        .await
+        .unwrap();
+    assert_eq!(handle.id(), "import");
+
+    let requests = requests.lock().unwrap();
+    let import = &requests[2];
+    assert_eq!(
+        import.header("content-type"),
+        Some("application/octet-stream")
+    );
+    assert_eq!(import.header("x-lxd-pool"), Some("fast"));
+    assert_eq!(import.header("x-lxd-name"), Some("c1-restored"));
+    assert_eq!(import.body, tarball);
+}
@@ -0,0 +1,114 @@
+//! Bulk instance state change tests against a stand-in LXD socket
+
+#![cfg(not(feature = "generated"))]
+
+mod common;
+
+use common::{failed_operation, MockRequest, MockResponse, MockSocket};
+use lxd_client::{Client, StateChangeOptions};
+use lxd_types::{InstanceAction, InstanceStatePut};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+fn instance(name: &str, status: &str) -> Value {
+    json!({
+        "name": name,
+        "status": status,
+        "status_code": if status == "Stopped" { 102 } else { 103 },
+        "type": "container"
+    })
+}
+
+fn stop() -> InstanceStatePut {
+    InstanceStatePut {
+        action: InstanceAction::Stop,
+        timeout: Some(30),
+        force: Some(false),
+        stateful: None,
+    }
+}
+
+#[tokio::test]
+async fn test_change_all_instances_state() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("PUT", "/1.0/instances?project=web") => MockResponse::task("all", "Running"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let handle = client.change_all_instances_state(&stop()).await.unwrap();
+    assert_eq!(handle.id(), "all");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0].json(),
+        json!({"state": {"action": "stop", "timeout": 30, "force": false}})
+    );
+}
+
+#[tokio::test]
+async fn test_change_instances_state() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response =
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/1.0/instances?recursion=1") => MockResponse::sync(json!([
+                    instance("c1", "Running"),
+                    instance("c2", "Stopped"),
+                    instance("c3", "Running"),
+                    instance("c4", "Running")
+                ])),
+                ("PUT", "/1.0/instances/c1/state") => MockResponse::task("op1", "Running"),
+                ("PUT", "/1.0/instances/c3/state") => MockResponse::task("op3", "Running"),
+                ("PUT", "/1.0/instances/c4/state") => MockResponse::error(403, "not authorized"),
+                ("GET", "/1.0/operations/op1/wait?timeout=-1") => MockResponse::finished("op1"),
+                ("GET", "/1.0/operations/op3/wait?timeout=-1") => MockResponse::sync(
+                    failed_operation("op3", "task", "Failed shutting down instance"),
+                ),
+                _ => MockResponse::error(404, "not found"),
+            };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let options = StateChangeOptions::new()
+        .with_parallelism(2)
+        .with_filter(|instance| instance.status == "Running");
+    let results = client
+        .change_instances_state(&stop(), &options)
+        .await
+        .unwrap();
+
+    let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["c1", "c3", "c4"]);
+    assert!(results[0].is_ok());
+    let err = results[1].result.as_ref().unwrap_err();
+    assert!(err.to_string().contains("Failed shutting down"), "{}", err);
+    let err = results[2].result.as_ref().unwrap_err();
+    assert!(err.to_string().contains("not authorized"), "{}", err);
+
+    let requests = requests.lock().unwrap();
+    assert!(!requests.iter().any(|r| r.path.contains("c2")));
+}
+
+#[tokio::test]
+async fn test_change_instances_state_list_failure() {
+    let socket = MockSocket::start(|_| Some(MockResponse::error(403, "not authorized")));
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let err = client
+        .change_instances_state(&stop(), &StateChangeOptions::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not authorized"), "{}", err);
+}
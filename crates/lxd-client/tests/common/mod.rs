@@ -0,0 +1,650 @@
+//! Shared helpers for tests that talk to a stand-in LXD socket
+
+#![allow(dead_code)]
+
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempdir::TempDir;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener};
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::WebSocketStream;
+
+/// A parsed HTTP request received by the mock socket
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    /// Request method
+    pub method: String,
+    /// Request path including the query string
+    pub path: String,
+    /// Request headers (lower-cased names)
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// Get a form-encoded body field by name
+    pub fn form(&self, name: &str) -> Option<String> {
+        url::form_urlencoded::parse(&self.body)
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    /// Parse the body as JSON
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+
+    /// Get a header value by name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Response returned by a mock handler
+pub struct MockResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Extra response headers
+    pub headers: Vec<(String, String)>,
+    /// Response body
+    pub body: Vec<u8>,
+    /// How long to wait before responding
+    pub delay: Option<Duration>,
+}
+
+impl MockResponse {
+    /// A JSON response
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: body.to_string().into_bytes(),
+            delay: None,
+        }
+    }
+
+    /// A raw `application/octet-stream` response
+    pub fn raw(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "application/octet-stream".into())],
+            body: body.into(),
+            delay: None,
+        }
+    }
+
+    /// Delay the response by `delay`
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// A successful LXD sync response wrapping `metadata`
+    pub fn sync(metadata: serde_json::Value) -> Self {
+        Self::json(
+            200,
+            serde_json::json!({
+                "type": "sync",
+                "status": "Success",
+                "status_code": 200,
+                "operation": "",
+                "error": "",
+                "error_code": 0,
+                "metadata": metadata,
+            }),
+        )
+    }
+
+    /// An LXD async response for a background `operation`
+    pub fn operation(operation: serde_json::Value) -> Self {
+        Self::json(
+            202,
+            serde_json::json!({
+                "type": "async",
+                "status": "Operation created",
+                "status_code": 100,
+                "operation": format!("/1.0/operations/{}", operation["id"].as_str().unwrap_or_default()),
+                "error": "",
+                "error_code": 0,
+                "metadata": operation,
+            }),
+        )
+    }
+
+    /// An LXD async response for a `task` operation in `status`
+    pub fn task(id: &str, status: &str) -> Self {
+        Self::operation(operation(id, "task", status, serde_json::Value::Null))
+    }
+
+    /// An LXD async response for a running `websocket` operation
+    pub fn websocket_operation(id: &str, metadata: serde_json::Value) -> Self {
+        Self::operation(operation(id, "websocket", "Running", metadata))
+    }
+
+    /// An LXD sync response for a wait on an operation that succeeded
+    pub fn finished(id: &str) -> Self {
+        Self::sync(operation(id, "task", "Success", serde_json::Value::Null))
+    }
+
+    /// An LXD error response
+    pub fn error(code: u16, message: &str) -> Self {
+        Self::json(
+            code,
+            serde_json::json!({
+                "type": "error",
+                "status": "",
+                "status_code": 0,
+                "operation": "",
+                "error": message,
+                "error_code": code,
+                "metadata": null,
+            }),
+        )
+    }
+}
+
+/// An LXD operation of `class` in `status`, as found in response metadata
+pub fn operation(
+    id: &str,
+    class: &str,
+    status: &str,
+    metadata: serde_json::Value,
+) -> serde_json::Value {
+    let status_code = match status {
+        "Success" => 200,
+        "Failure" => 400,
+        "Cancelled" => 401,
+        _ => 103,
+    };
+    serde_json::json!({
+        "id": id,
+        "class": class,
+        "status": status,
+        "status_code": status_code,
+        "resources": {},
+        "metadata": metadata,
+        "may_cancel": false,
+        "err": ""
+    })
+}
+
+/// An LXD operation of `class` that failed with `err`
+pub fn failed_operation(id: &str, class: &str, err: &str) -> serde_json::Value {
+    let mut operation = operation(id, class, "Failure", serde_json::Value::Null);
+    operation["err"] = err.into();
+    operation
+}
+
+/// Any stream a mock server accepts connections on
+pub trait MockIo: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> MockIo for T {}
+
+/// Server side of a websocket accepted by a mock server
+pub type MockWebSocket = WebSocketStream<Box<dyn MockIo>>;
+
+/// Handler for websocket upgrade requests
+type WebSocketHandler =
+    Arc<dyn Fn(MockRequest, MockWebSocket) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Handler for connections upgraded to another protocol
+type RawUpgradeHandler =
+    Arc<dyn Fn(MockRequest, Box<dyn MockIo>) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// What a mock server does with upgrade requests
+#[derive(Clone)]
+enum UpgradeHandler {
+    /// Accept websocket handshakes
+    WebSocket(WebSocketHandler),
+    /// Hand over the connection after the handler answers with a 101
+    Raw(RawUpgradeHandler),
+}
+
+/// Box a websocket handler
+fn websocket_handler<W, Fut>(websocket: W) -> UpgradeHandler
+where
+    W: Fn(MockRequest, MockWebSocket) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    UpgradeHandler::WebSocket(Arc::new(move |request, socket| {
+        Box::pin(websocket(request, socket))
+    }))
+}
+
+/// Box a raw upgrade handler
+fn raw_upgrade_handler<U, Fut>(upgrade: U) -> UpgradeHandler
+where
+    U: Fn(MockRequest, Box<dyn MockIo>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    UpgradeHandler::Raw(Arc::new(move |request, stream| {
+        Box::pin(upgrade(request, stream))
+    }))
+}
+
+/// A Unix socket that speaks just enough HTTP/1.1 to stand in for LXD
+///
+/// Every connection serves a single request and is then closed. When the
+/// handler returns `None` the connection is held open without a response.
+pub struct MockSocket {
+    _dir: TempDir,
+    path: PathBuf,
+    hits: Arc<AtomicUsize>,
+}
+
+impl MockSocket {
+    /// Start serving requests with `handler`
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+    {
+        Self::spawn(handler, None)
+    }
+
+    /// Like [`MockSocket::start`], but hand websocket upgrades to `websocket`
+    pub fn start_with_websocket<F, W, Fut>(handler: F, websocket: W) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+        W: Fn(MockRequest, MockWebSocket) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self::spawn(handler, Some(websocket_handler(websocket)))
+    }
+
+    /// Like [`MockSocket::start`], but when `handler` answers with a 101,
+    /// hand the connection to `upgrade`
+    pub fn start_with_upgrade<F, U, Fut>(handler: F, upgrade: U) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+        U: Fn(MockRequest, Box<dyn MockIo>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self::spawn(handler, Some(raw_upgrade_handler(upgrade)))
+    }
+
+    fn spawn<F>(handler: F, upgrade: Option<UpgradeHandler>) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+    {
+        let dir = TempDir::new("lxd-mock").unwrap();
+        let path = dir.path().join("unix.socket");
+        let listener = UnixListener::bind(&path).unwrap();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let upgrade = upgrade.clone();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    serve(stream, &*handler, upgrade.as_ref(), &counter).await;
+                });
+            }
+        });
+
+        Self {
+            _dir: dir,
+            path,
+            hits,
+        }
+    }
+
+    /// Path of the socket
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of requests received so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+}
+
+/// A TLS listener on localhost that speaks the same HTTP subset as [`MockSocket`]
+pub struct MockHttps {
+    port: u16,
+    hits: Arc<AtomicUsize>,
+}
+
+impl MockHttps {
+    /// Start serving requests with `handler`, presenting `cert_der` signed by `key_der`
+    pub async fn start<F>(cert_der: Vec<u8>, key_der: Vec<u8>, handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+    {
+        Self::spawn(cert_der, key_der, None, handler, None).await
+    }
+
+    /// Like [`MockHttps::start`], but hand websocket upgrades to `websocket`
+    pub async fn start_with_websocket<F, W, Fut>(
+        cert_der: Vec<u8>,
+        key_der: Vec<u8>,
+        handler: F,
+        websocket: W,
+    ) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+        W: Fn(MockRequest, MockWebSocket) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let websocket = websocket_handler(websocket);
+        Self::spawn(cert_der, key_der, None, handler, Some(websocket)).await
+    }
+
+    /// Like [`MockHttps::start`], but when `handler` answers with a 101,
+    /// hand the connection to `upgrade`
+    pub async fn start_with_upgrade<F, U, Fut>(
+        cert_der: Vec<u8>,
+        key_der: Vec<u8>,
+        handler: F,
+        upgrade: U,
+    ) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+        U: Fn(MockRequest, Box<dyn MockIo>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let upgrade = raw_upgrade_handler(upgrade);
+        Self::spawn(cert_der, key_der, None, handler, Some(upgrade)).await
+    }
+
+    /// Like [`MockHttps::start`], but require a client certificate
+    ///
+    /// When `client_cert_der` is set, handshakes only succeed if the client
+    /// presents that (self-signed) certificate.
+    pub async fn start_with_client_cert<F>(
+        cert_der: Vec<u8>,
+        key_der: Vec<u8>,
+        client_cert_der: Option<Vec<u8>>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+    {
+        Self::spawn(cert_der, key_der, client_cert_der, handler, None).await
+    }
+
+    async fn spawn<F>(
+        cert_der: Vec<u8>,
+        key_der: Vec<u8>,
+        client_cert_der: Option<Vec<u8>>,
+        handler: F,
+        upgrade: Option<UpgradeHandler>,
+    ) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+    {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = match client_cert_der {
+            Some(der) => {
+                let mut roots = rustls::RootCertStore::empty();
+                roots.add(der.into()).unwrap();
+                let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(roots),
+                    provider,
+                )
+                .build()
+                .unwrap();
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(
+                vec![cert_der.into()],
+                rustls::pki_types::PrivateKeyDer::Pkcs8(key_der.into()),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let handler = handler.clone();
+                let upgrade = upgrade.clone();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        serve(stream, &*handler, upgrade.as_ref(), &counter).await;
+                    }
+                });
+            }
+        });
+
+        Self { port, hits }
+    }
+
+    /// Base URL of the server
+    pub fn url(&self) -> String {
+        format!("https://localhost:{}", self.port)
+    }
+
+    /// Number of requests received so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+}
+
+/// A plain HTTP listener on localhost, used to stand in for an identity provider
+pub struct MockHttp {
+    port: u16,
+    hits: Arc<AtomicUsize>,
+}
+
+impl MockHttp {
+    /// Start serving requests with `handler`
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> Option<MockResponse> + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    serve(stream, &*handler, None, &counter).await;
+                });
+            }
+        });
+
+        Self { port, hits }
+    }
+
+    /// Base URL of the server
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Number of requests received so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+}
+
+async fn serve<S, F>(
+    mut stream: S,
+    handler: &F,
+    upgrade: Option<&UpgradeHandler>,
+    hits: &AtomicUsize,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    F: Fn(&MockRequest) -> Option<MockResponse>,
+{
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    hits.fetch_add(1, Ordering::SeqCst);
+
+    if let (Some(UpgradeHandler::WebSocket(websocket)), Some(key)) =
+        (upgrade, request.header("sec-websocket-key"))
+    {
+        let head = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        if stream.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+        let stream: Box<dyn MockIo> = Box::new(stream);
+        let socket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+        websocket(request, socket).await;
+        return;
+    }
+
+    let Some(response) = handler(&request) else {
+        // Stall until the client gives up
+        let mut buf = [0u8; 1];
+        let _ = stream.read(&mut buf).await;
+        return;
+    };
+
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    if let (Some(UpgradeHandler::Raw(upgrade)), 101) = (upgrade, response.status) {
+        head.push_str("\r\n");
+        if stream.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+        upgrade(request, Box::new(stream)).await;
+        return;
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&response.body).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Option<MockRequest> {
+    let mut buf = Vec::new();
+    let header_end = loop {
+        let mut chunk = [0u8; 1024];
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let chunked = headers
+        .iter()
+        .any(|(k, v)| k == "transfer-encoding" && v.eq_ignore_ascii_case("chunked"));
+    if chunked {
+        let body = read_chunked(stream, buf[header_end..].to_vec()).await?;
+        return Some(MockRequest {
+            method,
+            path,
+            headers,
+            body,
+        });
+    }
+
+    let length = headers
+        .iter()
+        .find(|(k, _)| k == "content-length")
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = buf[header_end..].to_vec();
+    while body.len() < length {
+        let mut chunk = vec![0u8; length - body.len()];
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Some(MockRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+/// Decode a chunked request body, `buf` holding what was already read
+async fn read_chunked<S: AsyncRead + Unpin>(stream: &mut S, mut buf: Vec<u8>) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = loop {
+            if let Some(pos) = buf.windows(2).position(|w| w == b"\r\n") {
+                break pos;
+            }
+            read_more(stream, &mut buf).await?;
+        };
+        let size_line = String::from_utf8_lossy(&buf[..line_end]).to_string();
+        let size = usize::from_str_radix(size_line.split(';').next()?.trim(), 16).ok()?;
+        buf.drain(..line_end + 2);
+        if size == 0 {
+            return Some(body);
+        }
+        while buf.len() < size + 2 {
+            read_more(stream, &mut buf).await?;
+        }
+        body.extend_from_slice(&buf[..size]);
+        buf.drain(..size + 2);
+    }
+}
+
+async fn read_more<S: AsyncRead + Unpin>(stream: &mut S, buf: &mut Vec<u8>) -> Option<()> {
+    let mut chunk = [0u8; 4096];
+    let n = stream.read(&mut chunk).await.ok()?;
+    if n == 0 {
+        return None;
+    }
+    buf.extend_from_slice(&chunk[..n]);
+    Some(())
+}
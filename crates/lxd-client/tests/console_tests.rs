@@ -0,0 +1,121 @@
+//! Console tests against a stand-in LXD socket
+
+mod common;
+
+use common::{operation, MockResponse, MockSocket};
+use futures::{SinkExt, StreamExt};
+use lxd_client::Client;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Secret of the websocket for the terminal and control channel
+fn fds() -> Value {
+    json!({"fds": {"0": "term", "control": "ctl"}})
+}
+
+#[tokio::test]
+async fn test_console_attach() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let control = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    let messages = control.clone();
+
+    let socket = MockSocket::start_with_websocket(
+        move |request| {
+            log.lock().unwrap().push((
+                format!("{} {}", request.method, request.path),
+                serde_json::from_slice::<Value>(&request.body).ok(),
+            ));
+            if request.method == "POST" {
+                return Some(MockResponse::websocket_operation("con", fds()));
+            }
+            Some(MockResponse::sync(operation(
+                "con",
+                "websocket",
+                "Success",
+                fds(),
+            )))
+        },
+        move |request, mut socket| {
+            let messages = messages.clone();
+            async move {
+                if request.path.ends_with("secret=ctl") {
+                    while let Some(Ok(Message::Text(text))) = socket.next().await {
+                        let message: Value = serde_json::from_str(&text).unwrap();
+                        messages.lock().unwrap().push(message);
+                    }
+                    return;
+                }
+                socket
+                    .send(Message::binary(b"vm login: ".to_vec()))
+                    .await
+                    .unwrap();
+                while let Some(Ok(Message::Binary(data))) = socket.next().await {
+                    socket.send(Message::binary(data)).await.unwrap();
+                }
+            }
+        },
+    );
+
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("lab");
+    let mut console = client.console("vm1", 80, 25).await.unwrap();
+
+    let mut prompt = [0u8; 10];
+    console.read_exact(&mut prompt).await.unwrap();
+    assert_eq!(&prompt, b"vm login: ");
+
+    console.write_all(b"root\n").await.unwrap();
+    let mut echo = [0u8; 5];
+    console.read_exact(&mut echo).await.unwrap();
+    assert_eq!(&echo, b"root\n");
+
+    console.resize(132, 50).await.unwrap();
+    console.close().await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].0, "POST /1.0/instances/vm1/console?project=lab");
+    assert_eq!(
+        requests[0].1.as_ref().unwrap(),
+        &json!({"type": "console", "width": 80, "height": 25})
+    );
+    assert_eq!(requests[1].0, "GET /1.0/operations/con/wait?timeout=-1");
+
+    assert_eq!(
+        *control.lock().unwrap(),
+        vec![json!({"command": "window-resize", "args": {"width": "132", "height": "50"}})]
+    );
+}
+
+#[tokio::test]
+async fn test_console_log() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    let socket = MockSocket::start(move |request| {
+        log.lock()
+            .unwrap()
+            .push(format!("{} {}", request.method, request.path));
+        match request.method.as_str() {
+            "GET" => Some(MockResponse::raw(200, "[    0.000000] Linux version 6.8\n")),
+            "DELETE" => Some(MockResponse::sync(json!(null))),
+            _ => Some(MockResponse::error(404, "not found")),
+        }
+    });
+
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+    let console = client.get_console_log("vm1").await.unwrap();
+    assert_eq!(console, "[    0.000000] Linux version 6.8\n");
+    client.clear_console_log("vm1").await.unwrap();
+
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec![
+            "GET /1.0/instances/vm1/console",
+            "DELETE /1.0/instances/vm1/console"
+        ]
+    );
+}
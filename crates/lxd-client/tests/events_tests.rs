@@ -0,0 +1,277 @@
+//! Event stream tests against stand-in LXD websockets
+
+mod common;
+
+use common::{MockHttps, MockResponse, MockSocket, MockWebSocket};
+use futures::{SinkExt, StreamExt};
+use lxd_client::{
+    BearerAuth, Client, ClientConfig, Error, EventFilter, EventHub, EventHubConfig, EventType,
+    ServerVerification, SubscriptionEvent,
+};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+fn lifecycle(action: &str) -> Message {
+    Message::text(
+        json!({
+            "type": "lifecycle",
+            "timestamp": "2024-01-01T00:00:00Z",
+            "metadata": {"action": action, "source": "/1.0/instances/c1"},
+            "location": "node1",
+            "project": "web"
+        })
+        .to_string(),
+    )
+}
+
+/// Send `messages`, then close the websocket once the client has read them
+async fn send_all(mut socket: MockWebSocket, messages: Vec<Message>) {
+    for message in messages {
+        socket.send(message).await.unwrap();
+    }
+    socket.close(None).await.unwrap();
+    while let Some(Ok(_)) = socket.next().await {}
+}
+
+#[tokio::test]
+async fn test_events_over_unix_socket() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let socket = MockSocket::start_with_websocket(
+        |_| None,
+        move |request, socket| {
+            log.lock().unwrap().push(request.path);
+            send_all(
+                socket,
+                vec![
+                    lifecycle("instance-started"),
+                    Message::Ping(Default::default()),
+                    lifecycle("instance-stopped"),
+                ],
+            )
+        },
+    );
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let filter = EventFilter::new().with_type(EventType::Lifecycle);
+    let events: Vec<_> = client.events(&filter).await.unwrap().collect().await;
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["/1.0/events?type=lifecycle&project=web".to_string()]
+    );
+    let events: Vec<Value> = events
+        .into_iter()
+        .map(|event| serde_json::to_value(event.unwrap()).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["type"], "lifecycle");
+    assert_eq!(events[0]["location"], "node1");
+    assert_eq!(events[0]["metadata"]["action"], "instance-started");
+    assert_eq!(events[1]["metadata"]["action"], "instance-stopped");
+}
+
+#[tokio::test]
+async fn test_events_over_https_with_bearer_token() {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let server = MockHttps::start_with_websocket(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        |_| None,
+        move |request, socket| {
+            log.lock().unwrap().push((
+                request.path.clone(),
+                request.header("authorization").map(str::to_string),
+            ));
+            send_all(socket, vec![lifecycle("image-created")])
+        },
+    )
+    .await;
+
+    let client = Client::new_https_with_auth(
+        server.url(),
+        BearerAuth::Token("secret".to_string()),
+        ServerVerification::PinnedCertificate(certified.cert.pem().into_bytes()),
+    )
+    .unwrap();
+
+    let filter = EventFilter::new().all_projects().with_target("node2");
+    let mut events = client.events(&filter).await.unwrap();
+    let event = serde_json::to_value(events.next().await.unwrap().unwrap()).unwrap();
+    assert_eq!(event["metadata"]["action"], "image-created");
+    assert!(events.next().await.is_none());
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![(
+            "/1.0/events?all-projects=true&target=node2".to_string(),
+            Some("Bearer secret".to_string())
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_events_rejected() {
+    let socket = MockSocket::start(|_| Some(MockResponse::error(403, "not authorized")));
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(ClientConfig::new().no_retries());
+
+    let err = client.events(&EventFilter::new()).await.unwrap_err();
+    assert!(
+        matches!(&err, Error::Api { code: 403, message } if message == "not authorized"),
+        "unexpected error: {}",
+        err
+    );
+}
+
+/// The action of a lifecycle event, or a marker for anything else
+fn describe(event: SubscriptionEvent) -> String {
+    match event {
+        SubscriptionEvent::Event(event) => serde_json::to_value(&*event).unwrap()["metadata"]
+            ["action"]
+            .as_str()
+            .unwrap()
+            .to_string(),
+        SubscriptionEvent::Reconnected => "reconnected".to_string(),
+        SubscriptionEvent::Lagged(missed) => format!("lagged {}", missed),
+    }
+}
+
+#[tokio::test]
+async fn test_event_hub_reconnects_and_fans_out() {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let count = connections.clone();
+    let socket = MockSocket::start_with_websocket(
+        |_| None,
+        move |_, mut socket| {
+            let connection = count.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if connection == 0 {
+                    // The first connection drops after one event
+                    send_all(socket, vec![lifecycle("instance-started")]).await;
+                } else {
+                    socket.send(lifecycle("instance-stopped")).await.unwrap();
+                    while let Some(Ok(_)) = socket.next().await {}
+                }
+            }
+        },
+    );
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let hub = EventHub::start_with_config(
+        Arc::new(client),
+        EventFilter::new(),
+        EventHubConfig::new().with_reconnect_delay(Duration::from_millis(10)),
+    );
+    let mut first = hub.subscribe();
+    let second = hub.subscribe().into_stream();
+    assert_eq!(hub.subscriber_count(), 2);
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(describe(first.recv().await.unwrap()));
+    }
+    assert_eq!(
+        received,
+        vec!["instance-started", "reconnected", "instance-stopped"]
+    );
+
+    let received: Vec<String> = second.take(3).map(describe).collect().await;
+    assert_eq!(
+        received,
+        vec!["instance-started", "reconnected", "instance-stopped"]
+    );
+
+    // One upstream connection per attempt, shared by both subscribers
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    drop(hub);
+    assert!(first.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_event_hub_reports_lag() {
+    let socket = MockSocket::start_with_websocket(
+        |_| None,
+        |_, mut socket| async move {
+            for action in ["image-created", "image-updated", "image-deleted"] {
+                socket.send(lifecycle(action)).await.unwrap();
+            }
+            while let Some(Ok(_)) = socket.next().await {}
+        },
+    );
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let hub = EventHub::start_with_config(
+        Arc::new(client),
+        EventFilter::new(),
+        EventHubConfig::new().with_capacity(2),
+    );
+    let mut subscription = hub.subscribe();
+
+    // Let the hub receive all three events before reading any
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(describe(subscription.recv().await.unwrap()), "lagged 1");
+    assert_eq!(
+        describe(subscription.recv().await.unwrap()),
+        "image-updated"
+    );
+}
+
+#[tokio::test]
+async fn test_event_hub_reconnects_stalled_connection() {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let count = connections.clone();
+    let socket = MockSocket::start_with_websocket(
+        |_| None,
+        move |_, mut socket| {
+            let connection = count.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if connection == 0 {
+                    // A half-open connection: one event, then silence and no pongs
+                    socket.send(lifecycle("instance-started")).await.unwrap();
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                } else {
+                    // Reading answers the keepalive pings
+                    socket.send(lifecycle("instance-stopped")).await.unwrap();
+                    while let Some(Ok(_)) = socket.next().await {}
+                }
+            }
+        },
+    );
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let hub = EventHub::start_with_config(
+        Arc::new(client),
+        EventFilter::new(),
+        EventHubConfig::new()
+            .with_reconnect_delay(Duration::from_millis(10))
+            .with_keepalive_interval(Some(Duration::from_millis(50))),
+    );
+    let mut subscription = hub.subscribe();
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let event = tokio::time::timeout(Duration::from_secs(5), subscription.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        received.push(describe(event));
+    }
+    assert_eq!(
+        received,
+        vec!["instance-started", "reconnected", "instance-stopped"]
+    );
+
+    // A connection that answers pings is kept open
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,320 @@
+//! Exec tests against a stand-in LXD socket
+
+mod common;
+
+use common::{operation, MockResponse, MockSocket, MockWebSocket};
+use futures::{SinkExt, StreamExt};
+use lxd_client::{Client, Error};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Secret of the websocket for each file descriptor
+fn fds() -> Value {
+    json!({"fds": {"0": "in", "1": "out", "2": "err", "control": "ctl"}})
+}
+
+/// Read binary messages until the client closes the websocket
+async fn read_all(socket: &mut MockWebSocket) -> Vec<u8> {
+    let mut data = Vec::new();
+    while let Some(Ok(message)) = socket.next().await {
+        match message {
+            Message::Binary(bytes) => data.extend_from_slice(&bytes),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    data
+}
+
+#[tokio::test]
+async fn test_exec_output() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let stdin = Arc::new(Mutex::new(Vec::new()));
+    let secrets = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    let received = stdin.clone();
+    let connected = secrets.clone();
+
+    let socket = MockSocket::start_with_websocket(
+        move |request| {
+            log.lock().unwrap().push((
+                format!("{} {}", request.method, request.path),
+                serde_json::from_slice::<Value>(&request.body).ok(),
+            ));
+            match request.path.as_str() {
+                "/1.0/instances/c1/exec?project=web" => {
+                    Some(MockResponse::websocket_operation("abc", fds()))
+                }
+                "/1.0/operations/abc/wait?timeout=-1" => Some(MockResponse::sync(operation(
+                    "abc",
+                    "websocket",
+                    "Success",
+                    json!({"return": 3}),
+                ))),
+                _ => Some(MockResponse::error(404, "not found")),
+            }
+        },
+        move |request, mut socket| {
+            let received = received.clone();
+            let connected = connected.clone();
+            async move {
+                let secret = request.path.split("secret=").nth(1).unwrap().to_string();
+                connected.lock().unwrap().push(secret.clone());
+                match secret.as_str() {
+                    "in" => {
+                        let data = read_all(&mut socket).await;
+                        received.lock().unwrap().extend(data);
+                    }
+                    "out" | "err" => {
+                        let text = if secret == "out" { "hello\n" } else { "oops\n" };
+                        socket
+                            .send(Message::binary(text.as_bytes().to_vec()))
+                            .await
+                            .unwrap();
+                        socket.close(None).await.unwrap();
+                        while let Some(Ok(_)) = socket.next().await {}
+                    }
+                    _ => {
+                        read_all(&mut socket).await;
+                    }
+                }
+            }
+        },
+    );
+
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+    let output = client
+        .exec("c1", ["sh", "-c", "cat; echo hello; echo oops >&2; exit 3"])
+        .env("LANG", "C.UTF-8")
+        .cwd("/root")
+        .user(1000)
+        .group(1000)
+        .stdin("input")
+        .output()
+        .await
+        .unwrap();
+
+    assert_eq!(output.exit_code, 3);
+    assert!(!output.success());
+    assert_eq!(output.stdout_lossy(), "hello\n");
+    assert_eq!(output.stderr_lossy(), "oops\n");
+    assert_eq!(*stdin.lock().unwrap(), b"input");
+
+    let requests = requests.lock().unwrap();
+    let (line, body) = &requests[0];
+    assert_eq!(line, "POST /1.0/instances/c1/exec?project=web");
+    assert_eq!(
+        body.as_ref().unwrap(),
+        &json!({
+            "command": ["sh", "-c", "cat; echo hello; echo oops >&2; exit 3"],
+            "cwd": "/root",
+            "environment": {"LANG": "C.UTF-8"},
+            "group": 1000,
+            "interactive": false,
+            "record-output": false,
+            "user": 1000,
+            "wait-for-websocket": true
+        })
+    );
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].0, "GET /1.0/operations/abc/wait?timeout=-1");
+
+    let mut secrets = secrets.lock().unwrap().clone();
+    secrets.sort();
+    assert_eq!(secrets, vec!["ctl", "err", "in", "out"]);
+}
+
+#[tokio::test]
+async fn test_exec_pty() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let control = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    let messages = control.clone();
+
+    let socket = MockSocket::start_with_websocket(
+        move |request| {
+            log.lock()
+                .unwrap()
+                .push(serde_json::from_slice::<Value>(&request.body).ok());
+            if request.method == "POST" {
+                let fds = json!({"fds": {"0": "pty", "control": "ctl"}});
+                return Some(MockResponse::websocket_operation("abc", fds));
+            }
+            Some(MockResponse::sync(operation(
+                "abc",
+                "websocket",
+                "Success",
+                json!({"return": 130}),
+            )))
+        },
+        move |request, mut socket| {
+            let messages = messages.clone();
+            async move {
+                if request.path.ends_with("secret=ctl") {
+                    while let Some(Ok(Message::Text(text))) = socket.next().await {
+                        let message: Value = serde_json::from_str(&text).unwrap();
+                        messages.lock().unwrap().push(message);
+                    }
+                    return;
+                }
+                // Echo the terminal until the client types "exit"
+                while let Some(Ok(Message::Binary(data))) = socket.next().await {
+                    if data.as_ref() == b"exit\n" {
+                        break;
+                    }
+                    let mut echo = b"$ ".to_vec();
+                    echo.extend_from_slice(&data);
+                    socket.send(Message::binary(echo)).await.unwrap();
+                }
+                socket.close(None).await.unwrap();
+                while let Some(Ok(_)) = socket.next().await {}
+            }
+        },
+    );
+
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+    let mut session = client.exec("c1", ["bash"]).pty(80, 24).await.unwrap();
+
+    session.write_all(b"ls\n").await.unwrap();
+    let mut echo = [0u8; 5];
+    session.read_exact(&mut echo).await.unwrap();
+    assert_eq!(&echo, b"$ ls\n");
+
+    session.resize(120, 40).await.unwrap();
+    session.signal(2).await.unwrap();
+
+    session.write_all(b"exit\n").await.unwrap();
+    let mut rest = Vec::new();
+    session.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(session.wait().await.unwrap(), 130);
+
+    let body = requests.lock().unwrap()[0].clone().unwrap();
+    assert_eq!(body["interactive"], true);
+    assert_eq!(body["width"], 80);
+    assert_eq!(body["height"], 24);
+
+    // The control socket is closed by wait(), after both messages arrived
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert_eq!(
+        *control.lock().unwrap(),
+        vec![
+            json!({"command": "window-resize", "args": {"width": "120", "height": "40"}}),
+            json!({"command": "signal", "signal": 2}),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_exec_record() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push((
+            format!("{} {}", request.method, request.path),
+            serde_json::from_slice::<Value>(&request.body).ok(),
+        ));
+        if request.method == "POST" {
+            return Some(MockResponse::websocket_operation("abc", json!({})));
+        }
+        Some(MockResponse::sync(operation(
+            "abc",
+            "websocket",
+            "Success",
+            json!({
+                "return": 0,
+                "output": {
+                    "1": "/1.0/instances/c1/logs/exec-output/exec_0001.stdout",
+                    "2": "/1.0/instances/c1/logs/exec-output/exec_0001.stderr"
+                }
+            }),
+        )))
+    });
+
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+    let recorded = client
+        .exec("c1", ["make", "-j8"])
+        .cwd("/src")
+        .record()
+        .await
+        .unwrap();
+    assert_eq!(recorded.operation().id(), "abc");
+
+    let record = recorded.wait().await.unwrap();
+    assert!(record.success());
+    assert_eq!(record.stdout, "exec_0001.stdout");
+    assert_eq!(record.stderr, "exec_0001.stderr");
+
+    let requests = requests.lock().unwrap();
+    let body = requests[0].1.as_ref().unwrap();
+    assert_eq!(body["record-output"], true);
+    assert_eq!(body["wait-for-websocket"], false);
+    assert_eq!(body["interactive"], false);
+    assert_eq!(requests[1].0, "GET /1.0/operations/abc/wait?timeout=-1");
+}
+
+#[tokio::test]
+async fn test_exec_output_files() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    let socket = MockSocket::start(move |request| {
+        log.lock()
+            .unwrap()
+            .push(format!("{} {}", request.method, request.path));
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1/logs/exec-output?project=ci") => {
+                Some(MockResponse::sync(json!([
+                    "/1.0/instances/c1/logs/exec-output/exec_0001.stdout",
+                    "/1.0/instances/c1/logs/exec-output/exec_0001.stderr"
+                ])))
+            }
+            ("GET", "/1.0/instances/c1/logs/exec-output/exec_0001.stdout?project=ci") => {
+                Some(MockResponse::raw(200, vec![b'x'; 100_000]))
+            }
+            ("DELETE", "/1.0/instances/c1/logs/exec-output/exec_0001.stdout?project=ci") => {
+                Some(MockResponse::sync(json!(null)))
+            }
+            _ => Some(MockResponse::error(404, "not found")),
+        }
+    });
+
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("ci");
+
+    let files = client.list_exec_outputs("c1").await.unwrap();
+    assert_eq!(files.len(), 2);
+
+    let mut data = Vec::new();
+    let written = client
+        .download_exec_output("c1", "exec_0001.stdout")
+        .await
+        .unwrap()
+        .copy_to(&mut data)
+        .await
+        .unwrap();
+    assert_eq!(written, 100_000);
+    assert!(data.iter().all(|&b| b == b'x'));
+
+    client
+        .delete_exec_output("c1", "exec_0001.stdout")
+        .await
+        .unwrap();
+
+    let missing = client
+        .download_exec_output("c1", "exec_0002.stdout")
+        .await
+        .unwrap_err();
+    assert!(matches!(missing, Error::Api { code: 404, .. }));
+
+    assert_eq!(
+        requests.lock().unwrap()[2],
+        "DELETE /1.0/instances/c1/logs/exec-output/exec_0001.stdout?project=ci"
+    );
+}
@@ -0,0 +1,221 @@
+//! File transfer tests against a stand-in LXD socket
+
+mod common;
+
+use common::{MockHttps, MockRequest, MockResponse, MockSocket};
+use lxd_client::{
+    BearerAuth, Client, Error, FileOptions, FileType, FileWriteMode, ServerVerification,
+};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+/// A recorded request: method and path, `X-LXD-*` headers and body
+type Seen = (String, Vec<(String, String)>, Vec<u8>);
+
+fn record(log: &Mutex<Vec<Seen>>, request: &MockRequest) {
+    let headers = request
+        .headers
+        .iter()
+        .filter(|(name, _)| name.starts_with("x-lxd-"))
+        .cloned()
+        .collect();
+    log.lock().unwrap().push((
+        format!("{} {}", request.method, request.path),
+        headers,
+        request.body.clone(),
+    ));
+}
+
+fn file_response(file_type: &str, mode: &str, body: impl Into<Vec<u8>>) -> MockResponse {
+    let mut response = MockResponse::raw(200, body);
+    response.headers.extend([
+        ("X-LXD-type".to_string(), file_type.to_string()),
+        ("X-LXD-uid".to_string(), "0".to_string()),
+        ("X-LXD-gid".to_string(), "4".to_string()),
+        ("X-LXD-mode".to_string(), mode.to_string()),
+    ]);
+    response
+}
+
+fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_pull_and_stat_files() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    let socket = MockSocket::start(move |request| {
+        record(&log, request);
+        match request.path.as_str() {
+            "/1.0/instances/c1/files?path=%2Fetc%2Fhosts&project=web" => {
+                Some(file_response("file", "0644", "127.0.0.1 localhost\n"))
+            }
+            "/1.0/instances/c1/files?path=%2Fvar%2Flog&project=web" => Some(file_response(
+                "directory",
+                "0755",
+                json!({
+                    "type": "sync",
+                    "status": "Success",
+                    "status_code": 200,
+                    "metadata": ["syslog", "apt"]
+                })
+                .to_string(),
+            )),
+            _ => Some(MockResponse::error(404, "not found")),
+        }
+    });
+
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let file = client.pull_file("c1", "/etc/hosts").await.unwrap();
+    assert_eq!(file.metadata.file_type, FileType::File);
+    assert_eq!(file.metadata.uid, 0);
+    assert_eq!(file.metadata.gid, 4);
+    assert_eq!(file.metadata.mode, 0o644);
+    assert_eq!(file.metadata.size, Some(20));
+    assert_eq!(file.body.bytes().await.unwrap(), b"127.0.0.1 localhost\n");
+
+    let metadata = client.stat_file("c1", "/var/log").await.unwrap();
+    assert_eq!(metadata.file_type, FileType::Directory);
+    assert_eq!(metadata.mode, 0o755);
+
+    let entries = client.list_files("c1", "/var/log").await.unwrap();
+    assert_eq!(entries, vec!["syslog", "apt"]);
+
+    let err = client.list_files("c1", "/etc/hosts").await.unwrap_err();
+    assert!(matches!(err, Error::Request(_)));
+
+    let err = client.pull_file("c1", "/missing").await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 404, .. }));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[1].0,
+        "HEAD /1.0/instances/c1/files?path=%2Fvar%2Flog&project=web"
+    );
+}
+
+#[tokio::test]
+async fn test_push_create_and_delete_files() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    let socket = MockSocket::start(move |request| {
+        record(&log, request);
+        Some(MockResponse::sync(json!(null)))
+    });
+
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let contents = vec![b'a'; 200_000];
+    let options = FileOptions::new()
+        .with_uid(1000)
+        .with_gid(1000)
+        .with_mode(0o600);
+    client
+        .push_file(
+            "c1",
+            "/home/ubuntu/data.bin",
+            std::io::Cursor::new(contents.clone()),
+            &options,
+        )
+        .await
+        .unwrap();
+    client
+        .push_file(
+            "c1",
+            "/var/log/app.log",
+            &b"line\n"[..],
+            &FileOptions::new().with_write_mode(FileWriteMode::Append),
+        )
+        .await
+        .unwrap();
+    client
+        .create_directory("c1", "/srv/app", &FileOptions::new().with_mode(0o755))
+        .await
+        .unwrap();
+    client
+        .create_symlink("c1", "/srv/current", "/srv/app", &FileOptions::new())
+        .await
+        .unwrap();
+    client.delete_file("c1", "/tmp/old").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0].0,
+        "POST /1.0/instances/c1/files?path=%2Fhome%2Fubuntu%2Fdata.bin"
+    );
+    assert_eq!(
+        requests[0].1,
+        headers(&[
+            ("x-lxd-type", "file"),
+            ("x-lxd-uid", "1000"),
+            ("x-lxd-gid", "1000"),
+            ("x-lxd-mode", "0600"),
+            ("x-lxd-write", "overwrite"),
+        ])
+    );
+    assert_eq!(requests[0].2, contents);
+
+    assert_eq!(
+        requests[1].1,
+        headers(&[("x-lxd-type", "file"), ("x-lxd-write", "append")])
+    );
+    assert_eq!(requests[1].2, b"line\n");
+
+    assert_eq!(
+        requests[2].1,
+        headers(&[("x-lxd-type", "directory"), ("x-lxd-mode", "0755")])
+    );
+    assert!(requests[2].2.is_empty());
+
+    assert_eq!(requests[3].1, headers(&[("x-lxd-type", "symlink")]));
+    assert_eq!(requests[3].2, b"/srv/app");
+
+    assert_eq!(
+        requests[4].0,
+        "DELETE /1.0/instances/c1/files?path=%2Ftmp%2Fold"
+    );
+}
+
+#[tokio::test]
+async fn test_push_file_over_https() {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    let server = MockHttps::start(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        move |request| {
+            record(&log, request);
+            Some(MockResponse::sync(json!(null)))
+        },
+    )
+    .await;
+
+    let client = Client::new_https_with_auth(
+        server.url(),
+        BearerAuth::Token("secret".to_string()),
+        ServerVerification::PinnedCertificate(certified.cert.pem().into_bytes()),
+    )
+    .unwrap();
+
+    client
+        .push_file("c1", "/etc/motd", &b"Welcome!\n"[..], &FileOptions::new())
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0].0,
+        "POST /1.0/instances/c1/files?path=%2Fetc%2Fmotd"
+    );
+    assert_eq!(requests[0].2, b"Welcome!\n");
+}
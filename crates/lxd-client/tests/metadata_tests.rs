@@ -0,0 +1,160 @@
+//! Instance log and metadata template tests against a stand-in LXD socket
+
+#![cfg(not(feature = "generated"))]
+
+mod common;
+
+use common::{MockRequest, MockResponse, MockSocket};
+use lxd_client::Client;
+use lxd_types::ImageMetadataTemplate;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_instance_logs() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/v1/logs?project=web") => MockResponse::sync(json!([
+                "/1.0/instances/v1/logs/qemu.log",
+                "/1.0/instances/v1/logs/qemu.conf"
+            ])),
+            ("GET", "/1.0/instances/v1/logs/qemu.log?project=web") => {
+                MockResponse::raw(200, b"QEMU started\n".to_vec())
+            }
+            ("DELETE", "/1.0/instances/v1/logs/qemu.log?project=web") => {
+                MockResponse::sync(Value::Null)
+            }
+            _ => MockResponse::error(404, "Log file not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let logs = client.list_instance_logs("v1").await.unwrap();
+    assert_eq!(logs[0], "/1.0/instances/v1/logs/qemu.log");
+
+    let contents = client
+        .get_instance_log("v1", "qemu.log")
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(contents, b"QEMU started\n".to_vec());
+
+    client.delete_instance_log("v1", "qemu.log").await.unwrap();
+
+    let err = client
+        .get_instance_log("v1", "lxc.log")
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("Log file not found"), "{}", err);
+    assert_eq!(requests.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_instance_metadata_and_templates() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1/metadata") => MockResponse::sync(json!({
+                "architecture": "x86_64",
+                "creation_date": 1714521600,
+                "properties": {"os": "Ubuntu", "release": "noble"},
+                "templates": {
+                    "/etc/hostname": {
+                        "when": ["create", "copy"],
+                        "template": "hostname.tpl"
+                    }
+                }
+            })),
+            ("PUT", "/1.0/instances/c1/metadata") => MockResponse::sync(Value::Null),
+            ("GET", "/1.0/instances/c1/metadata/templates") => {
+                MockResponse::sync(json!(["hostname.tpl"]))
+            }
+            ("GET", "/1.0/instances/c1/metadata/templates?path=hostname.tpl") => {
+                MockResponse::raw(200, b"{{ container.name }}\n".to_vec())
+            }
+            ("POST", "/1.0/instances/c1/metadata/templates?path=motd+banner.tpl") => {
+                MockResponse::sync(Value::Null)
+            }
+            ("DELETE", "/1.0/instances/c1/metadata/templates?path=hostname.tpl") => {
+                MockResponse::sync(Value::Null)
+            }
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let mut metadata = client.get_instance_metadata("c1").await.unwrap();
+    assert_eq!(metadata.properties["release"], "noble");
+    let hostname = &metadata.templates["/etc/hostname"];
+    assert_eq!(hostname.when, vec!["create", "copy"]);
+    assert!(!hostname.create_only);
+
+    let templates = client.list_instance_templates("c1").await.unwrap();
+    assert_eq!(templates, vec!["hostname.tpl"]);
+    let template = client
+        .get_instance_template("c1", "hostname.tpl")
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(template, b"{{ container.name }}\n".to_vec());
+
+    client
+        .upload_instance_template(
+            "c1",
+            "motd banner.tpl",
+            std::io::Cursor::new(b"Welcome to {{ container.name }}\n".to_vec()),
+        )
+        .await
+        .unwrap();
+    metadata.templates.insert(
+        "/etc/motd".to_string(),
+        ImageMetadataTemplate::new("motd banner.tpl", &["create", "start"])
+            .create_only(true)
+            .property("team", "web"),
+    );
+    metadata.templates.remove("/etc/hostname");
+    client
+        .update_instance_metadata("c1", &metadata)
+        .await
+        .unwrap();
+    client
+        .delete_instance_template("c1", "hostname.tpl")
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let upload = &requests[3];
+    assert_eq!(
+        upload.header("content-type"),
+        Some("application/octet-stream")
+    );
+    assert_eq!(upload.body, b"Welcome to {{ container.name }}\n".to_vec());
+    let update = requests[4].json();
+    assert_eq!(
+        update["templates"],
+        json!({
+            "/etc/motd": {
+                "when": ["create", "start"],
+                "create_only": true,
+                "template": "motd banner.tpl",
+                "properties": {"team": "web"}
+            }
+        })
+    );
+    assert_eq!(update["creation_date"], 1714521600);
+    assert_eq!(requests[5].method, "DELETE");
+}
@@ -0,0 +1,531 @@
+//! Instance rename, move, copy and migration tests against stand-in LXD sockets
+
+#![cfg(not(feature = "generated"))]
+
+mod common;
+
+use common::{failed_operation, MockRequest, MockResponse, MockSocket};
+use lxd_client::{Client, CopyOptions, MigrationMode, MigrationOptions, MoveOptions};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn server(address: &str, certificate: &str) -> MockResponse {
+    MockResponse::sync(json!({
+        "api_version": "1.0",
+        "environment": {"addresses": [address], "certificate": certificate}
+    }))
+}
+
+fn instance() -> MockResponse {
+    MockResponse::sync(json!({
+        "name": "c1",
+        "description": "web server",
+        "status": "Running",
+        "status_code": 103,
+        "type": "container",
+        "architecture": "x86_64",
+        "config": {"limits.cpu": "2", "volatile.uuid": "3c1b7f2e", "volatile.eth0.hwaddr": "00:16:3e:aa:bb:cc", "volatile.base_image": "f00d"},
+        "devices": {"root": {"type": "disk", "path": "/", "pool": "default"}},
+        "profiles": ["default"]
+    }))
+}
+
+fn find<'r>(requests: &'r [MockRequest], method: &str, path: &str) -> &'r MockRequest {
+    requests
+        .iter()
+        .find(|request| request.method == method && request.path == path)
+        .unwrap_or_else(|| panic!("no {} {}", method, path))
+}
+
+#[tokio::test]
+async fn test_rename_and_move() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/1.0/instances/c1?project=web") => MockResponse::task("rename", "Running"),
+            ("POST", "/1.0/instances/c2?project=web") => MockResponse::task("move", "Running"),
+            ("POST", "/1.0/instances/c2?target=%40rack1&project=web") => {
+                MockResponse::task("relocate", "Running")
+            }
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let handle = client.rename_instance("c1", "c2").await.unwrap();
+    assert_eq!(handle.id(), "rename");
+
+    let options = MoveOptions::new()
+        .with_pool("fast")
+        .with_project("staging")
+        .with_instance_only(true);
+    let handle = client.move_instance("c2", &options).await.unwrap();
+    assert_eq!(handle.id(), "move");
+
+    let options = MoveOptions::new()
+        .with_target("@rack1")
+        .with_name("c3")
+        .with_live(true);
+    let handle = client.move_instance("c2", &options).await.unwrap();
+    assert_eq!(handle.id(), "relocate");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].json(), json!({"name": "c2"}));
+    assert_eq!(
+        requests[1].json(),
+        json!({
+            "name": "c2",
+            "migration": true,
+            "instance_only": true,
+            "pool": "fast",
+            "project": "staging"
+        })
+    );
+    assert_eq!(
+        requests[2].json(),
+        json!({"name": "c3", "migration": true, "live": true})
+    );
+}
+
+#[tokio::test]
+async fn test_move_with_only_a_name_renames() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        Some(MockResponse::task("rename", "Running"))
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let options = MoveOptions::new().with_name("c2").with_live(true);
+    let handle = client.move_instance("c1", &options).await.unwrap();
+    assert_eq!(handle.id(), "rename");
+
+    let err = client
+        .move_instance("c1", &MoveOptions::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("needs a new name"), "{}", err);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/1.0/instances/c1");
+    assert_eq!(requests[0].json(), json!({"name": "c2"}));
+}
+
+#[tokio::test]
+async fn test_migrate_pull() {
+    let source_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = source_requests.clone();
+    let source = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1") => instance(),
+            ("GET", "/1.0") => server("10.0.0.1:8443", "SOURCE CERT"),
+            ("POST", "/1.0/instances/c1") => MockResponse::websocket_operation(
+                "send",
+                json!({"control": "secret-c", "fs": "secret-f", "criu": null}),
+            ),
+            ("GET", "/1.0/operations/send/wait?timeout=-1") => MockResponse::finished("send"),
+            ("DELETE", "/1.0/instances/c1") => MockResponse::task("delete", "Running"),
+            ("GET", "/1.0/operations/delete/wait?timeout=-1") => MockResponse::finished("delete"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let destination_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = destination_requests.clone();
+    let destination = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/1.0/instances") => MockResponse::websocket_operation("receive", Value::Null),
+            ("GET", "/1.0/operations/receive/wait?timeout=-1") => MockResponse::finished("receive"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let source_client = Client::new_unix_socket_path(source.path()).unwrap();
+    let destination_client = Client::new_unix_socket_path(destination.path()).unwrap();
+
+    let options = MigrationOptions::new().with_live(true);
+    source_client
+        .migrate_instance("c1", &destination_client, &options)
+        .await
+        .unwrap();
+
+    let source_requests = source_requests.lock().unwrap();
+    assert_eq!(
+        find(&source_requests, "POST", "/1.0/instances/c1").json(),
+        json!({"name": "c1", "migration": true, "live": true})
+    );
+    let destination_requests = destination_requests.lock().unwrap();
+    assert_eq!(
+        find(&destination_requests, "POST", "/1.0/instances").json(),
+        json!({
+            "name": "c1",
+            "description": "web server",
+            "type": "container",
+            "architecture": "x86_64",
+            "ephemeral": false,
+            "config": {"limits.cpu": "2", "volatile.uuid": "3c1b7f2e", "volatile.eth0.hwaddr": "00:16:3e:aa:bb:cc", "volatile.base_image": "f00d"},
+            "devices": {"root": {"type": "disk", "path": "/", "pool": "default"}},
+            "profiles": ["default"],
+            "source": {
+                "type": "migration",
+                "mode": "pull",
+                "operation": "https://10.0.0.1:8443/1.0/operations/send",
+                "certificate": "SOURCE CERT",
+                "secrets": {"control": "secret-c", "fs": "secret-f"},
+                "live": true
+            }
+        })
+    );
+    find(
+        &destination_requests,
+        "GET",
+        "/1.0/operations/receive/wait?timeout=-1",
+    );
+    let delete = source_requests.last().unwrap();
+    assert_eq!(
+        (delete.method.as_str(), delete.path.as_str()),
+        ("GET", "/1.0/operations/delete/wait?timeout=-1")
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_push() {
+    let source_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = source_requests.clone();
+    let source = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1") => instance(),
+            ("POST", "/1.0/instances/c1") => MockResponse::websocket_operation("send", Value::Null),
+            ("GET", "/1.0/operations/send/wait?timeout=-1") => MockResponse::finished("send"),
+            ("DELETE", "/1.0/instances/c1") => MockResponse::task("delete", "Running"),
+            ("GET", "/1.0/operations/delete/wait?timeout=-1") => MockResponse::finished("delete"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let destination_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = destination_requests.clone();
+    let destination = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0") => server("10.0.0.2:8443", "DESTINATION CERT"),
+            ("POST", "/1.0/instances") => MockResponse::websocket_operation(
+                "receive",
+                json!({"control": "secret-c", "fs": "secret-f"}),
+            ),
+            ("GET", "/1.0/operations/receive/wait?timeout=-1") => MockResponse::finished("receive"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let source_client = Client::new_unix_socket_path(source.path()).unwrap();
+    let destination_client = Client::new_unix_socket_path(destination.path()).unwrap();
+
+    let options = MigrationOptions::new()
+        .with_mode(MigrationMode::Push)
+        .with_name("c1-copy")
+        .with_instance_only(true);
+    source_client
+        .migrate_instance("c1", &destination_client, &options)
+        .await
+        .unwrap();
+
+    let destination_requests = destination_requests.lock().unwrap();
+    let create = find(&destination_requests, "POST", "/1.0/instances").json();
+    assert_eq!(create["name"], "c1-copy");
+    assert_eq!(
+        create["source"],
+        json!({"type": "migration", "mode": "push", "instance_only": true})
+    );
+    let source_requests = source_requests.lock().unwrap();
+    assert_eq!(
+        find(&source_requests, "POST", "/1.0/instances/c1").json(),
+        json!({
+            "name": "c1",
+            "migration": true,
+            "instance_only": true,
+            "target": {
+                "certificate": "DESTINATION CERT",
+                "operation": "https://10.0.0.2:8443/1.0/operations/receive",
+                "secrets": {"control": "secret-c", "fs": "secret-f"}
+            }
+        })
+    );
+    find(&source_requests, "DELETE", "/1.0/instances/c1");
+}
+
+#[tokio::test]
+async fn test_migrate_cancels_source_when_destination_fails() {
+    let source_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = source_requests.clone();
+    let source = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1") => instance(),
+            ("GET", "/1.0") => server("10.0.0.1:8443", "SOURCE CERT"),
+            ("POST", "/1.0/instances/c1") => {
+                MockResponse::websocket_operation("send", json!({"fs": "secret-f"}))
+            }
+            ("DELETE", "/1.0/operations/send") => MockResponse::sync(Value::Null),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let destination =
+        MockSocket::start(|_| Some(MockResponse::error(409, "Instance \"c1\" already exists")));
+    let source_client = Client::new_unix_socket_path(source.path()).unwrap();
+    let destination_client = Client::new_unix_socket_path(destination.path()).unwrap();
+
+    let err = source_client
+        .migrate_instance("c1", &destination_client, &MigrationOptions::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{}", err);
+
+    let source_requests = source_requests.lock().unwrap();
+    find(&source_requests, "DELETE", "/1.0/operations/send");
+    assert!(!source_requests
+        .iter()
+        .any(|request| request.path == "/1.0/instances/c1" && request.method == "DELETE"));
+}
+
+#[tokio::test]
+async fn test_migrate_cancels_source_when_destination_wait_fails() {
+    let source_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = source_requests.clone();
+    let source = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1") => instance(),
+            ("GET", "/1.0") => server("10.0.0.1:8443", "SOURCE CERT"),
+            ("POST", "/1.0/instances/c1") => {
+                MockResponse::websocket_operation("send", json!({"fs": "secret-f"}))
+            }
+            // The source keeps waiting for a peer that has given up
+            ("GET", "/1.0/operations/send/wait?timeout=-1") => {
+                MockResponse::finished("send").after(Duration::from_secs(30))
+            }
+            ("DELETE", "/1.0/operations/send") => MockResponse::sync(Value::Null),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let destination = MockSocket::start(|request| {
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/1.0/instances") => MockResponse::websocket_operation("receive", Value::Null),
+            ("GET", "/1.0/operations/receive/wait?timeout=-1") => MockResponse::sync(
+                failed_operation("receive", "websocket", "Failed to receive filesystem"),
+            ),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let source_client = Client::new_unix_socket_path(source.path()).unwrap();
+    let destination_client = Client::new_unix_socket_path(destination.path()).unwrap();
+
+    let err = tokio::time::timeout(
+        Duration::from_secs(10),
+        source_client.migrate_instance("c1", &destination_client, &MigrationOptions::new()),
+    )
+    .await
+    .unwrap()
+    .unwrap_err();
+    assert!(err.to_string().contains("Failed to receive"), "{}", err);
+
+    let source_requests = source_requests.lock().unwrap();
+    find(&source_requests, "DELETE", "/1.0/operations/send");
+    assert!(!source_requests
+        .iter()
+        .any(|request| request.path == "/1.0/instances/c1" && request.method == "DELETE"));
+}
+
+#[tokio::test]
+async fn test_migrate_without_network_address_starts_nothing() {
+    let source_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = source_requests.clone();
+    let source = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1") => instance(),
+            ("GET", "/1.0") => MockResponse::sync(json!({
+                "api_version": "1.0",
+                "environment": {"addresses": [], "certificate": "SOURCE CERT"}
+            })),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let destination_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = destination_requests.clone();
+    let destination = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        Some(MockResponse::error(404, "not found"))
+    });
+    let source_client = Client::new_unix_socket_path(source.path()).unwrap();
+    let destination_client = Client::new_unix_socket_path(destination.path()).unwrap();
+
+    let err = source_client
+        .migrate_instance("c1", &destination_client, &MigrationOptions::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("core.https_address"), "{}", err);
+
+    let source_requests = source_requests.lock().unwrap();
+    assert!(source_requests
+        .iter()
+        .all(|request| request.method == "GET"));
+    assert!(destination_requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_copy_local() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/1.0/instances?project=prod") => MockResponse::task("copy", "Running"),
+            ("POST", "/1.0/instances?project=staging") => MockResponse::task("refresh", "Running"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("prod");
+
+    let handle = client
+        .copy_instance("web", "web-copy", &CopyOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(handle.id(), "copy");
+
+    let options = CopyOptions::new()
+        .with_snapshot("nightly")
+        .with_instance_only(true);
+    client
+        .copy_instance("web", "web-nightly", &options)
+        .await
+        .unwrap();
+
+    let options = CopyOptions::new()
+        .with_project("staging")
+        .with_refresh(true);
+    let handle = client.copy_instance("web", "web", &options).await.unwrap();
+    assert_eq!(handle.id(), "refresh");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0].json(),
+        json!({"name": "web-copy", "source": {"type": "copy", "source": "web"}})
+    );
+    assert_eq!(
+        requests[1].json(),
+        json!({
+            "name": "web-nightly",
+            "source": {"type": "copy", "source": "web/nightly", "instance_only": true}
+        })
+    );
+    assert_eq!(
+        requests[2].json(),
+        json!({
+            "name": "web",
+            "source": {"type": "copy", "source": "web", "refresh": true, "project": "prod"}
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_copy_snapshot_to_remote() {
+    let source_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = source_requests.clone();
+    let source = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1") => instance(),
+            ("GET", "/1.0/instances/c1/snapshots/nightly") => MockResponse::sync(json!({
+                "name": "nightly",
+                "architecture": "x86_64",
+                "config": {"limits.cpu": "1", "volatile.uuid": "3c1b7f2e", "volatile.eth0.hwaddr": "00:16:3e:aa:bb:cc", "volatile.base_image": "f00d"},
+                "devices": {},
+                "profiles": ["default", "web"]
+            })),
+            ("GET", "/1.0") => server("10.0.0.1:8443", "SOURCE CERT"),
+            ("POST", "/1.0/instances/c1/snapshots/nightly") => MockResponse::websocket_operation(
+                "send",
+                json!({"control": "secret-c", "fs": "secret-f"}),
+            ),
+            ("GET", "/1.0/operations/send/wait?timeout=-1") => MockResponse::finished("send"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let destination_requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = destination_requests.clone();
+    let destination = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/1.0/instances") => MockResponse::websocket_operation("receive", Value::Null),
+            ("GET", "/1.0/operations/receive/wait?timeout=-1") => MockResponse::finished("receive"),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let source_client = Client::new_unix_socket_path(source.path()).unwrap();
+    let destination_client = Client::new_unix_socket_path(destination.path()).unwrap();
+
+    let options = CopyOptions::new()
+        .with_snapshot("nightly")
+        .with_refresh(true);
+    source_client
+        .copy_instance_to("c1", &destination_client, "c1-staging", &options)
+        .await
+        .unwrap();
+
+    let source_requests = source_requests.lock().unwrap();
+    assert_eq!(
+        find(
+            &source_requests,
+            "POST",
+            "/1.0/instances/c1/snapshots/nightly"
+        )
+        .json(),
+        json!({"name": "c1-staging", "migration": true})
+    );
+    assert!(!source_requests
+        .iter()
+        .any(|request| request.method == "DELETE"));
+    let destination_requests = destination_requests.lock().unwrap();
+    assert_eq!(
+        find(&destination_requests, "POST", "/1.0/instances").json(),
+        json!({
+            "name": "c1-staging",
+            "description": "web server",
+            "type": "container",
+            "architecture": "x86_64",
+            "ephemeral": false,
+            "config": {"limits.cpu": "1", "volatile.base_image": "f00d"},
+            "devices": {},
+            "profiles": ["default", "web"],
+            "source": {
+                "type": "migration",
+                "mode": "pull",
+                "operation": "https://10.0.0.1:8443/1.0/operations/send",
+                "certificate": "SOURCE CERT",
+                "secrets": {"control": "secret-c", "fs": "secret-f"},
+                "refresh": true
+            }
+        })
+    );
+}
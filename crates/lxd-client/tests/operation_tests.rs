@@ -0,0 +1,124 @@
+//! Operation handle tests against a stand-in LXD socket
+
+mod common;
+
+use common::{MockResponse, MockSocket};
+use lxd_client::{Client, ClientConfig, Error, OperationResource};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A cancellable task operation on instance `c1`, with `extra` fields overridden
+fn operation(status: &str, extra: Value) -> Value {
+    let mut operation = common::operation("abc", "task", status, Value::Null);
+    operation["resources"] = json!({"instances": ["/1.0/instances/c1?project=web"]});
+    operation["may_cancel"] = true.into();
+    operation
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    operation
+}
+
+/// Serve `POST /1.0/instances` plus the operation endpoints from `handler`
+fn start(
+    handler: impl Fn(&str, &str) -> MockResponse + Send + Sync + 'static,
+) -> (MockSocket, Client, Arc<Mutex<Vec<String>>>) {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock()
+            .unwrap()
+            .push(format!("{} {}", request.method, request.path));
+        if request.path == "/1.0/instances" {
+            return Some(MockResponse::operation(operation("Running", json!({}))));
+        }
+        Some(handler(&request.method, &request.path))
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(ClientConfig::new().no_retries());
+    (socket, client, seen)
+}
+
+fn create_request() -> lxd_types::InstancesPost {
+    serde_json::from_value(json!({"name": "c1", "source": {"type": "none"}})).unwrap()
+}
+
+#[tokio::test]
+async fn test_wait_success() {
+    let (_socket, client, seen) = start(|_, _| MockResponse::sync(operation("Success", json!({}))));
+
+    let handle = client.create_instance(&create_request()).await.unwrap();
+    assert_eq!(handle.id(), "abc");
+    assert_eq!(
+        handle.resources(),
+        vec![OperationResource::Instance("c1".to_string())]
+    );
+
+    handle.wait().await.unwrap();
+    assert_eq!(
+        seen.lock().unwrap()[1],
+        "GET /1.0/operations/abc/wait?timeout=-1"
+    );
+}
+
+#[tokio::test]
+async fn test_wait_failure() {
+    let (_socket, client, _) = start(|_, _| {
+        MockResponse::sync(operation(
+            "Failure",
+            json!({"status_code": 400, "err": "Failed creating instance"}),
+        ))
+    });
+
+    let handle = client.create_instance(&create_request()).await.unwrap();
+    let err = handle.wait().await.unwrap_err();
+    assert!(
+        matches!(&err, Error::OperationFailed(msg) if msg == "Failed creating instance"),
+        "unexpected error: {}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_wait_timeout() {
+    let (_socket, client, seen) = start(|_, _| MockResponse::sync(operation("Running", json!({}))));
+
+    let handle = client.create_instance(&create_request()).await.unwrap();
+    let err = handle
+        .wait_timeout(Duration::from_millis(1500))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {}", err);
+    assert_eq!(
+        seen.lock().unwrap()[1],
+        "GET /1.0/operations/abc/wait?timeout=2"
+    );
+}
+
+#[tokio::test]
+async fn test_cancel() {
+    let (_socket, client, seen) = start(|_, _| MockResponse::sync(Value::Null));
+
+    let handle = client.create_instance(&create_request()).await.unwrap();
+    handle.cancel().await.unwrap();
+    assert_eq!(seen.lock().unwrap()[1], "DELETE /1.0/operations/abc");
+}
+
+#[tokio::test]
+async fn test_progress() {
+    let (_socket, client, _) = start(|_, path| {
+        assert_eq!(path, "/1.0/operations/abc");
+        MockResponse::sync(operation(
+            "Running",
+            json!({"metadata": {"download_progress": "rootfs: 45% (12.30MB/s)"}}),
+        ))
+    });
+
+    let handle = client.create_instance(&create_request()).await.unwrap();
+    let progress = handle.progress().await.unwrap().unwrap();
+    assert_eq!(progress.stage, "download");
+    assert_eq!(progress.percent, Some(45));
+    assert_eq!(progress.speed.as_deref(), Some("12.30MB/s"));
+}
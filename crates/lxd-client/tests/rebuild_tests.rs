@@ -0,0 +1,103 @@
+//! Instance rebuild tests against a stand-in LXD socket
+
+#![cfg(not(feature = "generated"))]
+
+mod common;
+
+use common::{MockRequest, MockResponse, MockSocket};
+use lxd_client::{Client, Error};
+use lxd_types::InstanceSource;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+fn instance(name: &str, status: &str) -> MockResponse {
+    MockResponse::sync(json!({
+        "name": name,
+        "status": status,
+        "status_code": if status == "Stopped" { 102 } else { 103 },
+        "type": "container"
+    }))
+}
+
+#[tokio::test]
+async fn test_rebuild_instance() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/c1?project=web") => instance("c1", "Stopped"),
+            ("POST", "/1.0/instances/c1/rebuild?project=web") => {
+                MockResponse::task("rebuild", "Running")
+            }
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let sources = [
+        InstanceSource::from_image("ubuntu/24.04"),
+        InstanceSource::from_fingerprint("a1b2c3"),
+        InstanceSource::from_remote_image("24.04", "https://cloud-images.ubuntu.com/releases"),
+        InstanceSource::none(),
+    ];
+    for source in &sources {
+        let handle = client.rebuild_instance("c1", source).await.unwrap();
+        assert_eq!(handle.id(), "rebuild");
+    }
+
+    let requests = requests.lock().unwrap();
+    let bodies: Vec<Value> = requests
+        .iter()
+        .filter(|request| request.method == "POST")
+        .map(MockRequest::json)
+        .collect();
+    assert_eq!(
+        bodies,
+        vec![
+            json!({"source": {"type": "image", "alias": "ubuntu/24.04"}}),
+            json!({"source": {"type": "image", "fingerprint": "a1b2c3"}}),
+            json!({"source": {
+                "type": "image",
+                "alias": "24.04",
+                "server": "https://cloud-images.ubuntu.com/releases",
+                "protocol": "simplestreams"
+            }}),
+            json!({"source": {"type": "none"}}),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_rebuild_running_instance() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        Some(instance("c1", "Running"))
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let Err(err) = client
+        .rebuild_instance("c1", &InstanceSource::from_image("ubuntu/24.04"))
+        .await
+    else {
+        panic!("rebuilt a running instance");
+    };
+    let Error::InstanceNotStopped { name, status } = &err else {
+        panic!("unexpected error: {}", err);
+    };
+    assert_eq!((name.as_str(), status.as_str()), ("c1", "Running"));
+    assert_eq!(
+        err.to_string(),
+        "Instance c1 is Running, it must be stopped first"
+    );
+
+    // Only the status check reached the server
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GET");
+}
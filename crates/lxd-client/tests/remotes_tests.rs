@@ -0,0 +1,124 @@
+//! Tests for loading `lxc` remotes from a configuration directory
+
+mod common;
+
+use common::{MockHttps, MockResponse, MockSocket};
+use lxd_client::{Client, ClientIdentity, Error, Remotes};
+use std::sync::{Arc, Mutex};
+use tempdir::TempDir;
+
+fn write_config(dir: &TempDir, contents: &str) {
+    std::fs::write(dir.path().join("config.yml"), contents).unwrap();
+}
+
+#[tokio::test]
+async fn test_unix_remote_with_project() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.path.clone());
+        Some(MockResponse::sync(serde_json::json!([])))
+    });
+
+    let dir = TempDir::new("lxc-conf").unwrap();
+    write_config(
+        &dir,
+        &format!(
+            "remotes:\n  dev:\n    addr: unix://{}\n    project: web\n    protocol: lxd\n",
+            socket.path().display()
+        ),
+    );
+
+    let remotes = Remotes::load_from(dir.path()).unwrap();
+    assert_eq!(remotes.default_remote(), "local");
+    assert_eq!(remotes.get("local").unwrap().addr, "unix://");
+
+    let client = remotes.client("dev:").unwrap();
+    client.list_instances().await.unwrap();
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["/1.0/instances?project=web".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn test_https_remote_pins_server_cert() {
+    let dir = TempDir::new("lxc-conf").unwrap();
+    ClientIdentity::generate("me@host")
+        .unwrap()
+        .write(dir.path().join("client.crt"), dir.path().join("client.key"))
+        .unwrap();
+
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let server = MockHttps::start(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        |_| Some(MockResponse::sync(serde_json::json!(["/1.0/instances/c1"]))),
+    )
+    .await;
+    std::fs::create_dir(dir.path().join("servercerts")).unwrap();
+    std::fs::write(
+        dir.path().join("servercerts/prod.crt"),
+        certified.cert.pem(),
+    )
+    .unwrap();
+
+    write_config(
+        &dir,
+        &format!(
+            "default-remote: prod\nremotes:\n  prod:\n    addr: {}\n    auth_type: tls\n    protocol: lxd\n  other:\n    addr: {}\n    protocol: lxd\n",
+            server.url(),
+            server.url()
+        ),
+    );
+
+    let remotes = Remotes::load_from(dir.path()).unwrap();
+    let client = remotes.default_client().unwrap();
+    assert_eq!(
+        client.list_instances().await.unwrap(),
+        vec!["/1.0/instances/c1"]
+    );
+
+    // Without a pinned certificate the self-signed server is not trusted
+    let client = remotes.client("other").unwrap();
+    assert!(client.list_instances().await.is_err());
+}
+
+#[test]
+fn test_unsupported_remotes() {
+    let dir = TempDir::new("lxc-conf").unwrap();
+    write_config(
+        &dir,
+        "remotes:\n  images:\n    addr: https://images.lxd.canonical.com\n    protocol: simplestreams\n    public: true\n",
+    );
+    let remotes = Remotes::load_from(dir.path()).unwrap();
+
+    assert!(matches!(remotes.client("images"), Err(Error::Config(_))));
+    assert!(matches!(remotes.client("missing"), Err(Error::Config(_))));
+}
+
+#[tokio::test]
+async fn test_lxd_conf_override() {
+    let socket = MockSocket::start(|_| Some(MockResponse::sync(serde_json::json!([]))));
+    let dir = TempDir::new("lxc-conf").unwrap();
+    write_config(
+        &dir,
+        &format!(
+            "default-remote: dev\nremotes:\n  dev:\n    addr: unix://{}\n",
+            socket.path().display()
+        ),
+    );
+    std::env::set_var("LXD_CONF", dir.path());
+
+    assert_eq!(Remotes::config_dir().unwrap(), dir.path());
+    let remotes = Remotes::load().unwrap();
+    assert_eq!(remotes.default_remote(), "dev");
+    Client::from_remote("dev")
+        .unwrap()
+        .list_instances()
+        .await
+        .unwrap();
+    assert_eq!(socket.hits(), 1);
+
+    std::env::remove_var("LXD_CONF");
+}
@@ -0,0 +1,148 @@
+//! Timeout and retry tests against a stand-in LXD socket
+
+mod common;
+
+use common::{MockResponse, MockSocket};
+use lxd_client::{Client, ClientConfig, Error, FileOptions};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn config() -> ClientConfig {
+    ClientConfig::new()
+        .with_timeout(Duration::from_millis(200))
+        .with_retries(2)
+        .with_retry_delay(Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn test_stalled_request_times_out() {
+    let socket = MockSocket::start(|_| None);
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config().no_retries());
+
+    let err = client.list_instances().await.unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {}", err);
+    assert_eq!(socket.hits(), 1);
+}
+
+#[tokio::test]
+async fn test_stalled_request_is_retried() {
+    let socket = MockSocket::start(|_| None);
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config());
+
+    let err = client.list_instances().await.unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {}", err);
+    assert_eq!(socket.hits(), 3);
+}
+
+#[tokio::test]
+async fn test_stalled_streamed_request_times_out() {
+    let socket = MockSocket::start(|_| None);
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config());
+
+    let contents = std::io::Cursor::new(b"hello".to_vec());
+    let err = client
+        .push_file("c1", "/tmp/hello", contents, &FileOptions::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Timeout), "unexpected error: {}", err);
+    assert_eq!(socket.hits(), 1);
+}
+
+#[tokio::test]
+async fn test_server_error_is_retried_until_success() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let socket = MockSocket::start(move |_| {
+        if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+            Some(MockResponse::error(503, "Service Unavailable"))
+        } else {
+            Some(MockResponse::sync(serde_json::json!(["/1.0/instances/c1"])))
+        }
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config());
+
+    let instances = client.list_instances().await.unwrap();
+    assert_eq!(instances, vec!["/1.0/instances/c1"]);
+    assert_eq!(socket.hits(), 3);
+}
+
+#[tokio::test]
+async fn test_server_error_gives_up_after_retries() {
+    let socket = MockSocket::start(|_| Some(MockResponse::error(500, "boom")));
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config());
+
+    let err = client.list_instances().await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 500, .. }));
+    assert_eq!(socket.hits(), 3);
+}
+
+#[tokio::test]
+async fn test_client_error_is_not_retried() {
+    let socket = MockSocket::start(|_| Some(MockResponse::error(404, "not found")));
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config());
+
+    let err = client.get_instance("missing").await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 404, .. }));
+    assert_eq!(socket.hits(), 1);
+}
+
+#[tokio::test]
+async fn test_non_idempotent_request_is_not_retried() {
+    let socket = MockSocket::start(|_| Some(MockResponse::error(503, "Service Unavailable")));
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config());
+
+    let request = serde_json::from_value(serde_json::json!({"name": "p1"})).unwrap();
+    let err = client.create_profile(&request).await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 503, .. }));
+    assert_eq!(socket.hits(), 1);
+}
+
+#[tokio::test]
+async fn test_non_idempotent_retry_is_opt_in() {
+    let socket = MockSocket::start(|_| Some(MockResponse::error(503, "Service Unavailable")));
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config().with_retry_non_idempotent(true));
+
+    let request = serde_json::from_value(serde_json::json!({"name": "p1"})).unwrap();
+    let err = client.create_profile(&request).await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 503, .. }));
+    assert_eq!(socket.hits(), 3);
+}
+
+#[tokio::test]
+async fn test_wait_operation_extends_deadline() {
+    let socket = MockSocket::start(|request| {
+        assert_eq!(request.path, "/1.0/operations/abc/wait?timeout=1");
+        Some(
+            MockResponse::sync(serde_json::json!({
+                "id": "abc",
+                "class": "task",
+                "status": "Success",
+                "status_code": 200
+            }))
+            .after(Duration::from_millis(400)),
+        )
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_config(config().no_retries());
+
+    client.wait_operation("abc", Some(1)).await.unwrap();
+    assert_eq!(socket.hits(), 1);
+}
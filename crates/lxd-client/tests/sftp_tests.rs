@@ -0,0 +1,415 @@
+//! SFTP session tests against an in-memory SFTP server behind the mock upgrade
+
+mod common;
+
+use common::{MockHttps, MockIo, MockRequest, MockResponse, MockSocket};
+use lxd_client::{BearerAuth, Client, Error, ServerVerification};
+use russh_sftp::protocol::{
+    Attrs, Data, File, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode,
+};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// A node in the fake instance filesystem
+#[derive(Debug, Clone)]
+enum Node {
+    Directory,
+    File(Vec<u8>),
+    Symlink(String),
+}
+
+type Filesystem = Arc<Mutex<BTreeMap<String, Node>>>;
+
+fn filesystem() -> Filesystem {
+    let nodes = [
+        ("/", Node::Directory),
+        ("/srv", Node::Directory),
+        ("/srv/data", Node::Directory),
+        (
+            "/srv/notes.txt",
+            Node::File(b"remember the milk\n".to_vec()),
+        ),
+        ("/srv/current", Node::Symlink("/srv/data".to_string())),
+    ];
+    Arc::new(Mutex::new(
+        nodes
+            .into_iter()
+            .map(|(path, node)| (path.to_string(), node))
+            .collect(),
+    ))
+}
+
+/// SFTP server handler over a [`Filesystem`], using paths as handles
+struct Server {
+    filesystem: Filesystem,
+    listed: Vec<String>,
+}
+
+impl Server {
+    fn new(filesystem: Filesystem) -> Self {
+        Self {
+            filesystem,
+            listed: Vec::new(),
+        }
+    }
+
+    fn attributes(&self, path: &str, follow: bool) -> Result<FileAttributes, StatusCode> {
+        let filesystem = self.filesystem.lock().unwrap();
+        let mut node = filesystem.get(path).ok_or(StatusCode::NoSuchFile)?;
+        if let (true, Node::Symlink(target)) = (follow, node) {
+            node = filesystem.get(target).ok_or(StatusCode::NoSuchFile)?;
+        }
+        let (permissions, size) = match node {
+            Node::Directory => (0o040755, 0),
+            Node::File(contents) => (0o100644, contents.len() as u64),
+            Node::Symlink(target) => (0o120777, target.len() as u64),
+        };
+        Ok(FileAttributes {
+            size: Some(size),
+            uid: Some(1000),
+            gid: Some(1000),
+            permissions: Some(permissions),
+            ..FileAttributes::empty()
+        })
+    }
+
+    fn modify(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<String, Node>) -> bool,
+    ) -> Result<(), StatusCode> {
+        match update(&mut self.filesystem.lock().unwrap()) {
+            true => Ok(()),
+            false => Err(StatusCode::NoSuchFile),
+        }
+    }
+}
+
+fn ok(id: u32) -> Status {
+    Status {
+        id,
+        status_code: StatusCode::Ok,
+        error_message: "Ok".to_string(),
+        language_tag: "en-US".to_string(),
+    }
+}
+
+impl russh_sftp::server::Handler for Server {
+    type Error = StatusCode;
+
+    fn unimplemented(&self) -> Self::Error {
+        StatusCode::OpUnsupported
+    }
+
+    async fn open(
+        &mut self,
+        id: u32,
+        filename: String,
+        pflags: OpenFlags,
+        _attrs: FileAttributes,
+    ) -> Result<Handle, Self::Error> {
+        let mut filesystem = self.filesystem.lock().unwrap();
+        match filesystem.get_mut(&filename) {
+            Some(Node::File(contents)) if pflags.contains(OpenFlags::TRUNCATE) => contents.clear(),
+            Some(Node::File(_)) => {}
+            Some(_) => return Err(StatusCode::Failure),
+            None if pflags.contains(OpenFlags::CREATE) => {
+                filesystem.insert(filename.clone(), Node::File(Vec::new()));
+            }
+            None => return Err(StatusCode::NoSuchFile),
+        }
+        Ok(Handle {
+            id,
+            handle: filename,
+        })
+    }
+
+    async fn close(&mut self, id: u32, _handle: String) -> Result<Status, Self::Error> {
+        Ok(ok(id))
+    }
+
+    async fn read(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        len: u32,
+    ) -> Result<Data, Self::Error> {
+        let filesystem = self.filesystem.lock().unwrap();
+        let Some(Node::File(contents)) = filesystem.get(&handle) else {
+            return Err(StatusCode::Failure);
+        };
+        let start = offset as usize;
+        if start >= contents.len() {
+            return Err(StatusCode::Eof);
+        }
+        let end = contents.len().min(start + len as usize);
+        Ok(Data {
+            id,
+            data: contents[start..end].to_vec(),
+        })
+    }
+
+    async fn write(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<Status, Self::Error> {
+        let mut filesystem = self.filesystem.lock().unwrap();
+        let Some(Node::File(contents)) = filesystem.get_mut(&handle) else {
+            return Err(StatusCode::Failure);
+        };
+        let start = offset as usize;
+        if contents.len() < start + data.len() {
+            contents.resize(start + data.len(), 0);
+        }
+        contents[start..start + data.len()].copy_from_slice(&data);
+        Ok(ok(id))
+    }
+
+    async fn lstat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let attrs = self.attributes(&path, false)?;
+        Ok(Attrs { id, attrs })
+    }
+
+    async fn stat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let attrs = self.attributes(&path, true)?;
+        Ok(Attrs { id, attrs })
+    }
+
+    async fn opendir(&mut self, id: u32, path: String) -> Result<Handle, Self::Error> {
+        let filesystem = self.filesystem.lock().unwrap();
+        let Some(Node::Directory) = filesystem.get(&path) else {
+            return Err(StatusCode::NoSuchFile);
+        };
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.listed = filesystem
+            .keys()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .filter(|rest| !rest.is_empty() && !rest.contains('/'))
+            .map(str::to_string)
+            .collect();
+        // Send entries out of order so the client has to sort them
+        self.listed.reverse();
+        Ok(Handle { id, handle: path })
+    }
+
+    async fn readdir(&mut self, id: u32, handle: String) -> Result<Name, Self::Error> {
+        if self.listed.is_empty() {
+            return Err(StatusCode::Eof);
+        }
+        let prefix = format!("{}/", handle.trim_end_matches('/'));
+        let mut files = vec![File::dummy("."), File::dummy("..")];
+        for name in std::mem::take(&mut self.listed) {
+            let attrs = self.attributes(&format!("{}{}", prefix, name), false)?;
+            files.push(File::new(name, attrs));
+        }
+        Ok(Name { id, files })
+    }
+
+    async fn remove(&mut self, id: u32, filename: String) -> Result<Status, Self::Error> {
+        self.modify(|filesystem| {
+            !matches!(filesystem.get(&filename), Some(Node::Directory) | None)
+                && filesystem.remove(&filename).is_some()
+        })?;
+        Ok(ok(id))
+    }
+
+    async fn mkdir(
+        &mut self,
+        id: u32,
+        path: String,
+        _attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        self.modify(|filesystem| filesystem.insert(path, Node::Directory).is_none())?;
+        Ok(ok(id))
+    }
+
+    async fn rmdir(&mut self, id: u32, path: String) -> Result<Status, Self::Error> {
+        let prefix = format!("{}/", path);
+        self.modify(|filesystem| {
+            !filesystem.keys().any(|key| key.starts_with(&prefix))
+                && matches!(filesystem.remove(&path), Some(Node::Directory))
+        })?;
+        Ok(ok(id))
+    }
+
+    async fn rename(
+        &mut self,
+        id: u32,
+        oldpath: String,
+        newpath: String,
+    ) -> Result<Status, Self::Error> {
+        self.modify(|filesystem| match filesystem.remove(&oldpath) {
+            Some(node) => filesystem.insert(newpath, node).is_none(),
+            None => false,
+        })?;
+        Ok(ok(id))
+    }
+
+    async fn readlink(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
+        let filesystem = self.filesystem.lock().unwrap();
+        let Some(Node::Symlink(target)) = filesystem.get(&path) else {
+            return Err(StatusCode::NoSuchFile);
+        };
+        Ok(Name {
+            id,
+            files: vec![File::dummy(target.clone())],
+        })
+    }
+}
+
+/// Answer SFTP upgrades for `c1` and 404 anything else
+fn upgrade_handler(
+    seen: Arc<Mutex<Vec<MockRequest>>>,
+) -> impl Fn(&MockRequest) -> Option<MockResponse> {
+    move |request| {
+        seen.lock().unwrap().push(request.clone());
+        if !request.path.starts_with("/1.0/instances/c1/sftp")
+            || request.header("upgrade") != Some("sftp")
+        {
+            return Some(MockResponse::error(404, "Instance not found"));
+        }
+        let mut response = MockResponse::raw(101, Vec::new());
+        response.headers.extend([
+            ("Connection".to_string(), "Upgrade".to_string()),
+            ("Upgrade".to_string(), "sftp".to_string()),
+        ]);
+        Some(response)
+    }
+}
+
+fn serve_sftp(
+    filesystem: Filesystem,
+) -> impl Fn(MockRequest, Box<dyn MockIo>) -> futures::future::Ready<()> {
+    move |_, stream| {
+        let server = Server::new(filesystem.clone());
+        tokio::spawn(russh_sftp::server::run(stream, server));
+        futures::future::ready(())
+    }
+}
+
+#[tokio::test]
+async fn test_sftp_session() {
+    let filesystem = filesystem();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let socket = MockSocket::start_with_upgrade(
+        upgrade_handler(seen.clone()),
+        serve_sftp(filesystem.clone()),
+    );
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("web");
+
+    let sftp = client.sftp("c1").await.unwrap();
+    {
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].method, "GET");
+        assert_eq!(seen[0].path, "/1.0/instances/c1/sftp?project=web");
+        assert_eq!(seen[0].header("connection"), Some("Upgrade"));
+    }
+
+    let entries = sftp.read_dir("/srv").await.unwrap();
+    let names: Vec<String> = entries.iter().map(|entry| entry.file_name()).collect();
+    assert_eq!(names, vec!["current", "data", "notes.txt"]);
+    assert!(entries[0].metadata().is_symlink());
+    assert!(entries[1].metadata().is_dir());
+    assert_eq!(entries[2].metadata().len(), 18);
+
+    let metadata = sftp.stat("/srv/current").await.unwrap();
+    assert!(metadata.is_dir());
+    assert_eq!(metadata.uid, Some(1000));
+    let metadata = sftp.lstat("/srv/current").await.unwrap();
+    assert!(metadata.is_symlink());
+    assert_eq!(sftp.read_link("/srv/current").await.unwrap(), "/srv/data");
+
+    let mut file = sftp.open("/srv/notes.txt").await.unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).await.unwrap();
+    assert_eq!(contents, "remember the milk\n");
+
+    let mut file = sftp.create("/srv/data/log").await.unwrap();
+    file.write_all(b"first\n").await.unwrap();
+    file.shutdown().await.unwrap();
+    sftp.write("/srv/data/big", &vec![b'x'; 100_000])
+        .await
+        .unwrap();
+    assert_eq!(sftp.read("/srv/data/big").await.unwrap().len(), 100_000);
+    assert_eq!(sftp.read("/srv/data/log").await.unwrap(), b"first\n");
+
+    sftp.rename("/srv/notes.txt", "/srv/data/notes.txt")
+        .await
+        .unwrap();
+    assert!(!sftp.exists("/srv/notes.txt").await.unwrap());
+    sftp.create_dir("/srv/empty").await.unwrap();
+    sftp.remove_dir("/srv/empty").await.unwrap();
+    sftp.remove_file("/srv/data/big").await.unwrap();
+
+    let err = sftp.remove_dir("/srv/data").await.unwrap_err();
+    assert!(matches!(err, Error::Sftp(_)), "{}", err);
+    let Err(err) = sftp.open("/missing").await else {
+        panic!("opened a missing file");
+    };
+    assert!(matches!(err, Error::Sftp(_)), "{}", err);
+
+    sftp.close().await.unwrap();
+
+    let filesystem = filesystem.lock().unwrap();
+    let paths: Vec<&str> = filesystem.keys().map(String::as_str).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/",
+            "/srv",
+            "/srv/current",
+            "/srv/data",
+            "/srv/data/log",
+            "/srv/data/notes.txt"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_sftp_rejected() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let socket = MockSocket::start_with_upgrade(upgrade_handler(seen), serve_sftp(filesystem()));
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let err = client.sftp("missing").await.unwrap_err();
+    assert!(matches!(err, Error::Api { code: 404, .. }), "{}", err);
+}
+
+#[tokio::test]
+async fn test_sftp_over_https() {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let server = MockHttps::start_with_upgrade(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        upgrade_handler(seen.clone()),
+        serve_sftp(filesystem()),
+    )
+    .await;
+
+    let client = Client::new_https_with_auth(
+        server.url(),
+        BearerAuth::Token("secret".to_string()),
+        ServerVerification::PinnedCertificate(certified.cert.pem().into_bytes()),
+    )
+    .unwrap();
+
+    let sftp = client.sftp("c1").await.unwrap();
+    assert_eq!(
+        sftp.read("/srv/notes.txt").await.unwrap(),
+        b"remember the milk\n"
+    );
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[0].header("authorization"), Some("Bearer secret"));
+    assert_eq!(
+        seen[0].header("host"),
+        server.url().strip_prefix("https://")
+    );
+}
@@ -0,0 +1,54 @@
+//! Local socket discovery tests
+//!
+//! These change `LXD_SOCKET` and `LXD_DIR`, so they run as a single test.
+
+mod common;
+
+use common::{MockResponse, MockSocket};
+use lxd_client::{Client, Error};
+use tempdir::TempDir;
+
+#[tokio::test]
+async fn test_socket_discovery() {
+    let empty = TempDir::new("lxd-dir").unwrap();
+    let missing = empty.path().join("missing.socket");
+    std::env::set_var("LXD_SOCKET", &missing);
+    std::env::set_var("LXD_DIR", empty.path());
+
+    // Every path tried is reported
+    let err = Client::new_unix_socket().err().unwrap();
+    let Error::Connection(message) = err else {
+        panic!("unexpected error: {}", err);
+    };
+    assert!(
+        message.contains(&missing.display().to_string()),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains(&empty.path().join("unix.socket").display().to_string()),
+        "{}",
+        message
+    );
+    assert!(message.contains("/var/lib/lxd/unix.socket"), "{}", message);
+
+    // A stale socket file is skipped in favour of LXD_DIR
+    std::fs::write(&missing, b"").unwrap();
+    let socket = MockSocket::start(|_| Some(MockResponse::sync(serde_json::json!([]))));
+    std::env::set_var("LXD_DIR", socket.path().parent().unwrap());
+
+    let client = Client::new_unix_socket().unwrap();
+    client.list_instances().await.unwrap();
+    assert_eq!(socket.hits(), 1);
+
+    // LXD_SOCKET wins when it is live
+    std::env::set_var("LXD_SOCKET", socket.path());
+    Client::new_unix_socket()
+        .unwrap()
+        .list_instances()
+        .await
+        .unwrap();
+
+    std::env::remove_var("LXD_SOCKET");
+    std::env::remove_var("LXD_DIR");
+}
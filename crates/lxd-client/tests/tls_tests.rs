@@ -0,0 +1,263 @@
+//! Server certificate verification tests against a local TLS server
+
+mod common;
+
+use common::{MockHttps, MockResponse};
+use lxd_client::{
+    Client, ClientBuilder, ClientConfig, ClientIdentity, Error, ServerCertificate,
+    ServerVerification,
+};
+use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tempdir::TempDir;
+
+/// Client certificate and key written to a temporary directory
+struct IdentityFiles {
+    _dir: TempDir,
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+fn client_identity() -> IdentityFiles {
+    let dir = TempDir::new("lxd-tls").unwrap();
+    let cert = dir.path().join("client.crt");
+    let key = dir.path().join("client.key");
+    ClientIdentity::generate("lxd-client")
+        .unwrap()
+        .write(&cert, &key)
+        .unwrap();
+    IdentityFiles {
+        _dir: dir,
+        cert,
+        key,
+    }
+}
+
+/// Start a server presenting a self-signed certificate, returning its PEM
+async fn self_signed_server() -> (MockHttps, String) {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let pem = certified.cert.pem();
+    let server = MockHttps::start(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        handler,
+    )
+    .await;
+    (server, pem)
+}
+
+fn handler(_: &common::MockRequest) -> Option<MockResponse> {
+    Some(MockResponse::sync(serde_json::json!(["/1.0/instances/c1"])))
+}
+
+fn client(
+    server: &MockHttps,
+    identity: &IdentityFiles,
+    verification: ServerVerification,
+) -> Client {
+    Client::new_https_with_verification(server.url(), &identity.cert, &identity.key, verification)
+        .unwrap()
+        .with_config(ClientConfig::new().no_retries())
+}
+
+#[tokio::test]
+async fn test_self_signed_rejected_by_default() {
+    let (server, _) = self_signed_server().await;
+    let identity = client_identity();
+    let client = Client::new_https(server.url(), &identity.cert, &identity.key)
+        .unwrap()
+        .with_config(ClientConfig::new().no_retries());
+
+    let err = client.list_instances().await.unwrap_err();
+    assert!(
+        matches!(err, Error::Connection(_)),
+        "unexpected error: {}",
+        err
+    );
+    assert_eq!(server.hits(), 0);
+}
+
+#[tokio::test]
+async fn test_pinned_certificate() {
+    let (server, pem) = self_signed_server().await;
+    let identity = client_identity();
+    let client = client(
+        &server,
+        &identity,
+        ServerVerification::PinnedCertificate(pem.into_bytes()),
+    );
+
+    let instances = client.list_instances().await.unwrap();
+    assert_eq!(instances, vec!["/1.0/instances/c1"]);
+}
+
+#[tokio::test]
+async fn test_pinned_certificate_file() {
+    let (server, pem) = self_signed_server().await;
+    let identity = client_identity();
+    let path = identity.cert.with_file_name("server.crt");
+    std::fs::write(&path, pem).unwrap();
+    let client = client(
+        &server,
+        &identity,
+        ServerVerification::pinned_certificate_file(&path).unwrap(),
+    );
+
+    client.list_instances().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pinned_certificate_mismatch() {
+    let (server, _) = self_signed_server().await;
+    let (_, other_pem) = self_signed_server().await;
+    let identity = client_identity();
+    let client = client(
+        &server,
+        &identity,
+        ServerVerification::PinnedCertificate(other_pem.into_bytes()),
+    );
+
+    assert!(client.list_instances().await.is_err());
+    assert_eq!(server.hits(), 0);
+}
+
+#[tokio::test]
+async fn test_fingerprint_pin() {
+    let (server, pem) = self_signed_server().await;
+    let identity = client_identity();
+    let fingerprint = ServerCertificate::from_pem(pem.as_bytes())
+        .unwrap()
+        .fingerprint();
+
+    let client = client(
+        &server,
+        &identity,
+        ServerVerification::fingerprint(fingerprint.to_uppercase()),
+    );
+    client.list_instances().await.unwrap();
+
+    let client = self::client(
+        &server,
+        &identity,
+        ServerVerification::fingerprint("00".repeat(32)),
+    );
+    assert!(client.list_instances().await.is_err());
+    assert_eq!(server.hits(), 1);
+}
+
+#[tokio::test]
+async fn test_ca_bundle() {
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_key = KeyPair::generate().unwrap();
+    let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+    let issuer = Issuer::new(ca_params, ca_key);
+
+    let leaf_key = KeyPair::generate().unwrap();
+    let leaf_cert = CertificateParams::new(vec!["localhost".to_string()])
+        .unwrap()
+        .signed_by(&leaf_key, &issuer)
+        .unwrap();
+
+    let server =
+        MockHttps::start(leaf_cert.der().to_vec(), leaf_key.serialize_der(), handler).await;
+    let identity = client_identity();
+    let client = client(
+        &server,
+        &identity,
+        ServerVerification::CaBundle(ca_cert.pem().into_bytes()),
+    );
+
+    client.list_instances().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_trust_on_first_use() {
+    let (server, pem) = self_signed_server().await;
+    let identity = client_identity();
+    let expected = ServerCertificate::from_pem(pem.as_bytes())
+        .unwrap()
+        .fingerprint();
+
+    let persisted = Arc::new(Mutex::new(None));
+    let store = persisted.clone();
+    let client = client(
+        &server,
+        &identity,
+        ServerVerification::trust_on_first_use(None, move |cert| {
+            *store.lock().unwrap() = Some(cert.fingerprint());
+        }),
+    );
+
+    client.list_instances().await.unwrap();
+    client.list_instances().await.unwrap();
+    assert_eq!(
+        persisted.lock().unwrap().as_deref(),
+        Some(expected.as_str())
+    );
+
+    // A later session uses the persisted fingerprint as the pin
+    let known = persisted.lock().unwrap().clone();
+    let (other, _) = self_signed_server().await;
+    let client = self::client(
+        &other,
+        &identity,
+        ServerVerification::trust_on_first_use(known, |_| panic!("already trusted")),
+    );
+    assert!(client.list_instances().await.is_err());
+}
+
+#[tokio::test]
+async fn test_insecure_is_builder_opt_in() {
+    let (server, _) = self_signed_server().await;
+    let identity = client_identity();
+    let client = ClientBuilder::new()
+        .config(ClientConfig::new().no_retries())
+        .danger_accept_invalid_certs(true)
+        .build_https(server.url(), &identity.cert, &identity.key)
+        .unwrap();
+
+    client.list_instances().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_identity_from_memory() {
+    let identity = ClientIdentity::generate("ci@runner").unwrap();
+    let client_der = ServerCertificate::from_pem(identity.cert_pem().as_bytes())
+        .unwrap()
+        .der()
+        .to_vec();
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let verification = ServerVerification::PinnedCertificate(certified.cert.pem().into_bytes());
+    let server = MockHttps::start_with_client_cert(
+        certified.cert.der().to_vec(),
+        certified.signing_key.serialize_der(),
+        Some(client_der),
+        handler,
+    )
+    .await;
+
+    let client = Client::new_https_with_identity(server.url(), &identity, verification.clone())
+        .unwrap()
+        .with_config(ClientConfig::new().no_retries());
+    client.list_instances().await.unwrap();
+
+    let client = Client::new_https_from_pem(
+        server.url(),
+        identity.cert_pem().as_bytes(),
+        identity.key_pem().as_bytes(),
+        verification.clone(),
+    )
+    .unwrap()
+    .with_config(ClientConfig::new().no_retries());
+    client.list_instances().await.unwrap();
+
+    // A different identity is refused during the handshake
+    let other = ClientIdentity::generate("someone@else").unwrap();
+    let client = Client::new_https_with_identity(server.url(), &other, verification)
+        .unwrap()
+        .with_config(ClientConfig::new().no_retries());
+    assert!(client.list_instances().await.is_err());
+    assert_eq!(server.hits(), 2);
+}
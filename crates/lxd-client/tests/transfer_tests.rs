@@ -0,0 +1,254 @@
+//! Directory transfer tests against a stand-in instance filesystem
+
+mod common;
+
+use common::{MockRequest, MockResponse, MockSocket};
+use lxd_client::{Client, TransferOptions, TransferSummary};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::sync::{Arc, Mutex};
+use tempdir::TempDir;
+
+/// A file in the fake instance
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    file_type: String,
+    mode: String,
+    uid: String,
+    contents: Vec<u8>,
+}
+
+type Filesystem = Arc<Mutex<BTreeMap<String, Entry>>>;
+
+/// Serve `/1.0/instances/c1/files` from `filesystem`
+fn files_handler(filesystem: Filesystem) -> impl Fn(&MockRequest) -> Option<MockResponse> {
+    move |request| {
+        let query = request.path.split_once("path=")?.1;
+        let path = url::form_urlencoded::parse(format!("path={}", query).as_bytes())
+            .next()?
+            .1
+            .into_owned();
+        let mut filesystem = filesystem.lock().unwrap();
+        match request.method.as_str() {
+            "POST" => {
+                let entry = Entry {
+                    file_type: request.header("x-lxd-type")?.to_string(),
+                    mode: request.header("x-lxd-mode").unwrap_or("0644").to_string(),
+                    uid: request.header("x-lxd-uid").unwrap_or("0").to_string(),
+                    contents: request.body.clone(),
+                };
+                filesystem.insert(path, entry);
+                Some(MockResponse::sync(json!(null)))
+            }
+            "GET" => {
+                let Some(entry) = filesystem.get(&path) else {
+                    return Some(MockResponse::error(404, "not found"));
+                };
+                let body = if entry.file_type == "directory" {
+                    let prefix = format!("{}/", path);
+                    let children: Vec<&str> = filesystem
+                        .keys()
+                        .filter_map(|key| key.strip_prefix(&prefix))
+                        .filter(|rest| !rest.contains('/'))
+                        .collect();
+                    json!({"type": "sync", "status": "Success", "status_code": 200, "metadata": children})
+                        .to_string()
+                        .into_bytes()
+                } else {
+                    entry.contents.clone()
+                };
+                let mut response = MockResponse::raw(200, body);
+                response.headers.extend([
+                    ("X-LXD-type".to_string(), entry.file_type.clone()),
+                    ("X-LXD-mode".to_string(), entry.mode.clone()),
+                    ("X-LXD-uid".to_string(), entry.uid.clone()),
+                    ("X-LXD-gid".to_string(), entry.uid.clone()),
+                ]);
+                Some(response)
+            }
+            _ => Some(MockResponse::error(405, "not allowed")),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_push_and_pull_dir() {
+    let source = TempDir::new("push").unwrap();
+    let root = source.path();
+    std::fs::create_dir_all(root.join("bin")).unwrap();
+    std::fs::create_dir_all(root.join("share/doc")).unwrap();
+    std::fs::write(root.join("bin/app"), b"#!/bin/sh\necho hi\n").unwrap();
+    std::fs::set_permissions(root.join("bin/app"), PermissionsExt::from_mode(0o755)).unwrap();
+    std::fs::write(root.join("share/doc/README"), vec![b'r'; 70_000]).unwrap();
+    std::fs::set_permissions(root.join("share/doc"), PermissionsExt::from_mode(0o750)).unwrap();
+    std::os::unix::fs::symlink("bin/app", root.join("app")).unwrap();
+    let uid = std::fs::metadata(root).unwrap().uid().to_string();
+
+    let filesystem = Filesystem::default();
+    let socket = MockSocket::start(files_handler(filesystem.clone()));
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let options = TransferOptions::new()
+        .with_parallelism(3)
+        .with_progress(move |progress| seen.lock().unwrap().push(progress.clone()));
+    let summary = client
+        .push_dir(root, "c1", "/opt/app/", &options)
+        .await
+        .unwrap();
+
+    let expected = TransferSummary {
+        directories: 4,
+        files: 2,
+        symlinks: 1,
+        bytes: 70_018,
+    };
+    assert_eq!(summary, expected);
+
+    {
+        let filesystem = filesystem.lock().unwrap();
+        let paths: Vec<&str> = filesystem.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/opt/app",
+                "/opt/app/app",
+                "/opt/app/bin",
+                "/opt/app/bin/app",
+                "/opt/app/share",
+                "/opt/app/share/doc",
+                "/opt/app/share/doc/README"
+            ]
+        );
+        assert_eq!(filesystem["/opt/app/bin/app"].mode, "0755");
+        assert_eq!(filesystem["/opt/app/bin/app"].uid, uid);
+        assert_eq!(filesystem["/opt/app/share/doc"].file_type, "directory");
+        assert_eq!(filesystem["/opt/app/share/doc"].mode, "0750");
+        assert_eq!(filesystem["/opt/app/app"].file_type, "symlink");
+        assert_eq!(filesystem["/opt/app/app"].contents, b"bin/app");
+    }
+
+    let progress = progress.lock().unwrap().clone();
+    assert_eq!(progress.len(), 7);
+    let last = progress.last().unwrap();
+    assert_eq!(last.entries_done, 7);
+    assert_eq!(last.entries_total, Some(7));
+    assert_eq!(last.bytes_done, 70_018);
+    assert_eq!(last.bytes_total, Some(70_018));
+
+    // Pull the tree back into a fresh directory
+    let target = TempDir::new("pull").unwrap();
+    let local = target.path().join("app");
+    let summary = client
+        .pull_dir("c1", "/opt/app", &local, &TransferOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(summary, expected);
+
+    assert_eq!(
+        std::fs::read(local.join("bin/app")).unwrap(),
+        b"#!/bin/sh\necho hi\n"
+    );
+    let mode = |path: &str| std::fs::metadata(local.join(path)).unwrap().mode() & 0o7777;
+    assert_eq!(mode("bin/app"), 0o755);
+    assert_eq!(mode("share/doc"), 0o750);
+    assert_eq!(
+        std::fs::read(local.join("share/doc/README")).unwrap().len(),
+        70_000
+    );
+    assert_eq!(
+        std::fs::read_link(local.join("app")).unwrap(),
+        std::path::PathBuf::from("bin/app")
+    );
+}
+
+#[tokio::test]
+async fn test_pull_dir_rejects_file() {
+    let filesystem = Filesystem::default();
+    filesystem.lock().unwrap().insert(
+        "/etc/hosts".to_string(),
+        Entry {
+            file_type: "file".to_string(),
+            mode: "0644".to_string(),
+            uid: "0".to_string(),
+            contents: b"127.0.0.1 localhost\n".to_vec(),
+        },
+    );
+    let socket = MockSocket::start(files_handler(filesystem));
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let target = TempDir::new("pull").unwrap();
+    let err = client
+        .pull_dir("c1", "/etc/hosts", target.path(), &TransferOptions::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not a directory"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_pull_dir_rejects_escaping_names() {
+    for name in ["..", "a/b"] {
+        let socket = MockSocket::start(move |request| {
+            let listing = json!({
+                "type": "sync",
+                "status": "Success",
+                "status_code": 200,
+                "metadata": [name]
+            });
+            let (file_type, body) = if request.path.ends_with("path=%2Fsrv") {
+                ("directory", listing.to_string().into_bytes())
+            } else {
+                ("file", b"pwned\n".to_vec())
+            };
+            let mut response = MockResponse::raw(200, body);
+            response.headers.extend([
+                ("X-LXD-type".to_string(), file_type.to_string()),
+                ("X-LXD-mode".to_string(), "0644".to_string()),
+                ("X-LXD-uid".to_string(), "0".to_string()),
+                ("X-LXD-gid".to_string(), "0".to_string()),
+            ]);
+            Some(response)
+        });
+        let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+        let parent = TempDir::new("pull").unwrap();
+        let local = parent.path().join("srv");
+        let err = client
+            .pull_dir("c1", "/srv", &local, &TransferOptions::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid entry"), "{}", err);
+        assert_eq!(std::fs::read_dir(parent.path()).unwrap().count(), 1);
+        assert_eq!(std::fs::read_dir(&local).unwrap().count(), 0);
+    }
+}
+
+#[tokio::test]
+async fn test_pull_dir_does_not_write_through_symlinks() {
+    let filesystem = Filesystem::default();
+    let entry = |file_type: &str, contents: &[u8]| Entry {
+        file_type: file_type.to_string(),
+        mode: "0755".to_string(),
+        uid: "0".to_string(),
+        contents: contents.to_vec(),
+    };
+    filesystem.lock().unwrap().extend([
+        ("/srv".to_string(), entry("directory", b"")),
+        ("/srv/data".to_string(), entry("directory", b"")),
+        ("/srv/data/key".to_string(), entry("file", b"pwned\n")),
+    ]);
+    let socket = MockSocket::start(files_handler(filesystem));
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let outside = TempDir::new("outside").unwrap();
+    let local = TempDir::new("pull").unwrap();
+    std::os::unix::fs::symlink(outside.path(), local.path().join("data")).unwrap();
+    let err = client
+        .pull_dir("c1", "/srv", local.path(), &TransferOptions::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("symbolic link"), "{}", err);
+    assert_eq!(std::fs::read_dir(outside.path()).unwrap().count(), 0);
+}
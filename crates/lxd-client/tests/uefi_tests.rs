@@ -0,0 +1,132 @@
+//! UEFI variable tests against a stand-in LXD socket
+
+#![cfg(not(feature = "generated"))]
+
+mod common;
+
+use common::{MockRequest, MockResponse, MockSocket};
+use lxd_client::{Client, UefiVariable, EFI_GLOBAL_VARIABLE_GUID, SECURE_BOOT_ENABLE_GUID};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+fn uefi_vars() -> MockResponse {
+    MockResponse::sync(json!({
+        "variables": {
+            "BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c": {
+                "attr": 7,
+                "data": "01000000",
+                "digest": "",
+                "timestamp": ""
+            },
+            "SecureBootEnable-f0a30bc7-af08-4556-99c4-001009c93a44": {
+                "attr": 3,
+                "data": "00",
+                "digest": "",
+                "timestamp": ""
+            }
+        }
+    }))
+}
+
+#[tokio::test]
+async fn test_uefi_vars() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/vm1/uefi-vars?project=win") => uefi_vars(),
+            ("PUT", "/1.0/instances/vm1/uefi-vars?project=win") => MockResponse::sync(Value::Null),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path())
+        .unwrap()
+        .with_project("win");
+
+    let mut vars = client.get_instance_uefi_vars("vm1").await.unwrap();
+    assert_eq!(vars.len(), 2);
+    assert_eq!(vars.secure_boot(), Some(false));
+    assert_eq!(vars.boot_order(), Some(vec![1, 0]));
+    let (guid, name, _) = vars.iter().next().unwrap();
+    assert_eq!((guid, name), (EFI_GLOBAL_VARIABLE_GUID, "BootOrder"));
+
+    vars.set_secure_boot(true);
+    vars.set_boot_order(&[2, 0x10]);
+    vars.set(
+        "8BE4DF61-93CA-11D2-AA0D-00E098032B8C",
+        "Timeout",
+        UefiVariable::new(7, vec![0x05, 0x00]),
+    );
+    client
+        .update_instance_uefi_vars("vm1", &vars)
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let update = requests[1].json();
+    assert_eq!(
+        update["variables"]["SecureBootEnable-f0a30bc7-af08-4556-99c4-001009c93a44"],
+        json!({"attr": 3, "data": "01", "digest": "", "timestamp": ""})
+    );
+    assert_eq!(
+        update["variables"]["BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"]["data"],
+        "02001000"
+    );
+    assert_eq!(
+        update["variables"]["Timeout-8be4df61-93ca-11d2-aa0d-00e098032b8c"]["data"],
+        "0500"
+    );
+    assert!(vars
+        .get(SECURE_BOOT_ENABLE_GUID, "SecureBootEnable")
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_set_secure_boot_and_boot_order() {
+    let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+    let log = requests.clone();
+    let socket = MockSocket::start(move |request| {
+        log.lock().unwrap().push(request.clone());
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/1.0/instances/vm1/uefi-vars") => uefi_vars(),
+            ("PUT", "/1.0/instances/vm1/uefi-vars") => MockResponse::sync(Value::Null),
+            _ => MockResponse::error(404, "not found"),
+        };
+        Some(response)
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    client.set_instance_secure_boot("vm1", true).await.unwrap();
+    client.set_instance_boot_order("vm1", &[3]).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let methods: Vec<&str> = requests.iter().map(|r| r.method.as_str()).collect();
+    assert_eq!(methods, vec!["GET", "PUT", "GET", "PUT"]);
+    let secure_boot = requests[1].json();
+    assert_eq!(
+        secure_boot["variables"]["SecureBootEnable-f0a30bc7-af08-4556-99c4-001009c93a44"]["data"],
+        "01"
+    );
+    let boot_order = requests[3].json();
+    assert_eq!(
+        boot_order["variables"]["BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"]["data"],
+        "0300"
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_uefi_value() {
+    let socket = MockSocket::start(|_| {
+        Some(MockResponse::sync(json!({
+            "variables": {
+                "BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c": {"attr": 7, "data": "0g"}
+            }
+        })))
+    });
+    let client = Client::new_unix_socket_path(socket.path()).unwrap();
+
+    let err = client.get_instance_uefi_vars("vm1").await.unwrap_err();
+    assert!(err.to_string().contains("BootOrder"), "{}", err);
+}
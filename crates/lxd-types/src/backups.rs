@@ -0,0 +1,104 @@
+//! Instance backup types for LXD API
+
+use serde::{Deserialize, Serialize};
+
+/// InstanceBackup represents an instance backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceBackup {
+    /// Backup name
+    pub name: String,
+
+    /// Creation timestamp
+    #[serde(default)]
+    pub created_at: String,
+
+    /// Expiration timestamp (the backup is deleted after it)
+    #[serde(default)]
+    pub expires_at: String,
+
+    /// Whether snapshots are left out of the backup
+    #[serde(default)]
+    pub instance_only: bool,
+
+    /// Whether snapshots are left out of the backup (deprecated, use `instance_only`)
+    #[serde(default)]
+    pub container_only: bool,
+
+    /// Whether the backup uses the storage pool's optimized format
+    #[serde(default)]
+    pub optimized_storage: bool,
+}
+
+/// Request to create an instance backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceBackupsPost {
+    /// Backup name
+    pub name: String,
+
+    /// Expiration timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+
+    /// Whether to leave snapshots out of the backup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_only: Option<bool>,
+
+    /// Whether to use the storage pool's optimized format instead of a plain tarball
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimized_storage: Option<bool>,
+
+    /// Compression algorithm (e.g. `gzip`, `zstd` or `none`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_algorithm: Option<String>,
+}
+
+impl InstanceBackupsPost {
+    /// Create a new backup request
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            expires_at: None,
+            instance_only: None,
+            optimized_storage: None,
+            compression_algorithm: None,
+        }
+    }
+
+    /// Leave snapshots out of the backup
+    pub fn instance_only(mut self, instance_only: bool) -> Self {
+        self.instance_only = Some(instance_only);
+        self
+    }
+
+    /// Use the storage pool's optimized format
+    pub fn optimized_storage(mut self, optimized_storage: bool) -> Self {
+        self.optimized_storage = Some(optimized_storage);
+        self
+    }
+
+    /// Set the compression algorithm
+    pub fn compression_algorithm(mut self, algorithm: impl Into<String>) -> Self {
+        self.compression_algorithm = Some(algorithm.into());
+        self
+    }
+
+    /// Set expiration timestamp
+    pub fn expires_at(mut self, expires: impl Into<String>) -> Self {
+        self.expires_at = Some(expires.into());
+        self
+    }
+}
+
+/// Request to rename a backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceBackupPost {
+    /// New backup name
+    pub name: String,
+}
+
+impl InstanceBackupPost {
+    /// Create a rename request
+    pub fn rename(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
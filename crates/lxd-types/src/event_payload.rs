@@ -0,0 +1,404 @@
+//! Typed event payloads
+//!
+//! `Event.metadata` holds a different structure for each event type. These
+//! types decode it for both the hand-written and generated `Event`.
+
+use crate::{Event, Operation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Decoded metadata of an [`Event`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventPayload {
+    /// A resource changed (`lifecycle` events)
+    Lifecycle(EventLifecycle),
+    /// An operation was created, updated or finished (`operation` events)
+    Operation(Box<Operation>),
+    /// A daemon log message (`logging` events)
+    Logging(EventLogging),
+    /// An event type not known to this version of the crate
+    Other {
+        /// Event type
+        event_type: String,
+        /// Raw metadata
+        metadata: Value,
+    },
+}
+
+/// Metadata of a `lifecycle` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventLifecycle {
+    /// What happened to the resource
+    pub action: LifecycleAction,
+
+    /// API URL of the resource (e.g. `/1.0/instances/c1`)
+    pub source: String,
+
+    /// Who triggered the change, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requestor: Option<EventLifecycleRequestor>,
+
+    /// Action specific details
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<BTreeMap<String, Value>>,
+
+    /// Name of the resource
+    #[serde(default)]
+    pub name: String,
+
+    /// Project of the resource
+    #[serde(default)]
+    pub project: String,
+}
+
+/// The user or client that triggered a lifecycle event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventLifecycleRequestor {
+    /// Username or certificate fingerprint
+    #[serde(default)]
+    pub username: String,
+
+    /// Authentication protocol (`unix`, `tls`, `oidc`, ...)
+    #[serde(default)]
+    pub protocol: String,
+
+    /// Remote address of the client
+    #[serde(default)]
+    pub address: String,
+}
+
+/// Metadata of a `logging` event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventLogging {
+    /// Log message
+    pub message: String,
+
+    /// Log level (`debug`, `info`, `warning`, `error`)
+    pub level: String,
+
+    /// Structured log fields
+    #[serde(default)]
+    pub context: BTreeMap<String, String>,
+}
+
+macro_rules! lifecycle_actions {
+    ($($variant:ident => $name:literal,)*) => {
+        /// Action reported by a `lifecycle` event
+        ///
+        /// Covers the actions emitted by LXD; anything else is kept as
+        /// [`LifecycleAction::Other`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum LifecycleAction {
+            $(
+                #[doc = concat!("`", $name, "`")]
+                $variant,
+            )*
+            /// An action not known to this version of the crate
+            Other(String),
+        }
+
+        impl LifecycleAction {
+            /// Action name as sent by LXD
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $name,)*
+                    Self::Other(action) => action,
+                }
+            }
+        }
+
+        impl From<&str> for LifecycleAction {
+            fn from(action: &str) -> Self {
+                match action {
+                    $($name => Self::$variant,)*
+                    other => Self::Other(other.to_string()),
+                }
+            }
+        }
+    };
+}
+
+lifecycle_actions! {
+    CertificateCreated => "certificate-created",
+    CertificateDeleted => "certificate-deleted",
+    CertificateUpdated => "certificate-updated",
+    ClusterCertificateUpdated => "cluster-certificate-updated",
+    ClusterDisabled => "cluster-disabled",
+    ClusterEnabled => "cluster-enabled",
+    ClusterGroupCreated => "cluster-group-created",
+    ClusterGroupDeleted => "cluster-group-deleted",
+    ClusterGroupRenamed => "cluster-group-renamed",
+    ClusterGroupUpdated => "cluster-group-updated",
+    ClusterMemberAdded => "cluster-member-added",
+    ClusterMemberRemoved => "cluster-member-removed",
+    ClusterMemberRenamed => "cluster-member-renamed",
+    ClusterMemberUpdated => "cluster-member-updated",
+    ClusterTokenCreated => "cluster-token-created",
+    ConfigUpdated => "config-updated",
+    AuthGroupCreated => "auth-group-created",
+    AuthGroupDeleted => "auth-group-deleted",
+    AuthGroupRenamed => "auth-group-renamed",
+    AuthGroupUpdated => "auth-group-updated",
+    IdentityCreated => "identity-created",
+    IdentityDeleted => "identity-deleted",
+    IdentityUpdated => "identity-updated",
+    IdentityProviderGroupCreated => "identity-provider-group-created",
+    IdentityProviderGroupDeleted => "identity-provider-group-deleted",
+    IdentityProviderGroupRenamed => "identity-provider-group-renamed",
+    IdentityProviderGroupUpdated => "identity-provider-group-updated",
+    ImageAliasCreated => "image-alias-created",
+    ImageAliasDeleted => "image-alias-deleted",
+    ImageAliasRenamed => "image-alias-renamed",
+    ImageAliasUpdated => "image-alias-updated",
+    ImageCreated => "image-created",
+    ImageDeleted => "image-deleted",
+    ImageRefreshed => "image-refreshed",
+    ImageRetrieved => "image-retrieved",
+    ImageSecretCreated => "image-secret-created",
+    ImageUpdated => "image-updated",
+    InstanceAgentStarted => "instance-agent-started",
+    InstanceBackupCreated => "instance-backup-created",
+    InstanceBackupDeleted => "instance-backup-deleted",
+    InstanceBackupRenamed => "instance-backup-renamed",
+    InstanceBackupRetrieved => "instance-backup-retrieved",
+    InstanceConsole => "instance-console",
+    InstanceConsoleReset => "instance-console-reset",
+    InstanceConsoleRetrieved => "instance-console-retrieved",
+    InstanceCreated => "instance-created",
+    InstanceDeleted => "instance-deleted",
+    InstanceExec => "instance-exec",
+    InstanceFileDeleted => "instance-file-deleted",
+    InstanceFilePushed => "instance-file-pushed",
+    InstanceFileRetrieved => "instance-file-retrieved",
+    InstanceLogDeleted => "instance-log-deleted",
+    InstanceLogRetrieved => "instance-log-retrieved",
+    InstanceMetadataRetrieved => "instance-metadata-retrieved",
+    InstanceMetadataTemplateCreated => "instance-metadata-template-created",
+    InstanceMetadataTemplateDeleted => "instance-metadata-template-deleted",
+    InstanceMetadataTemplateRetrieved => "instance-metadata-template-retrieved",
+    InstanceMetadataUpdated => "instance-metadata-updated",
+    InstanceMigrated => "instance-migrated",
+    InstancePaused => "instance-paused",
+    InstanceReady => "instance-ready",
+    InstanceRebuilt => "instance-rebuilt",
+    InstanceRenamed => "instance-renamed",
+    InstanceRestarted => "instance-restarted",
+    InstanceRestored => "instance-restored",
+    InstanceResumed => "instance-resumed",
+    InstanceShutdown => "instance-shutdown",
+    InstanceSnapshotCreated => "instance-snapshot-created",
+    InstanceSnapshotDeleted => "instance-snapshot-deleted",
+    InstanceSnapshotRenamed => "instance-snapshot-renamed",
+    InstanceSnapshotUpdated => "instance-snapshot-updated",
+    InstanceStarted => "instance-started",
+    InstanceStopped => "instance-stopped",
+    InstanceUpdated => "instance-updated",
+    NetworkAclCreated => "network-acl-created",
+    NetworkAclDeleted => "network-acl-deleted",
+    NetworkAclRenamed => "network-acl-renamed",
+    NetworkAclUpdated => "network-acl-updated",
+    NetworkCreated => "network-created",
+    NetworkDeleted => "network-deleted",
+    NetworkRenamed => "network-renamed",
+    NetworkUpdated => "network-updated",
+    NetworkForwardCreated => "network-forward-created",
+    NetworkForwardDeleted => "network-forward-deleted",
+    NetworkForwardUpdated => "network-forward-updated",
+    NetworkLoadBalancerCreated => "network-load-balancer-created",
+    NetworkLoadBalancerDeleted => "network-load-balancer-deleted",
+    NetworkLoadBalancerUpdated => "network-load-balancer-updated",
+    NetworkPeerCreated => "network-peer-created",
+    NetworkPeerDeleted => "network-peer-deleted",
+    NetworkPeerUpdated => "network-peer-updated",
+    NetworkZoneCreated => "network-zone-created",
+    NetworkZoneDeleted => "network-zone-deleted",
+    NetworkZoneUpdated => "network-zone-updated",
+    NetworkZoneRecordCreated => "network-zone-record-created",
+    NetworkZoneRecordDeleted => "network-zone-record-deleted",
+    NetworkZoneRecordUpdated => "network-zone-record-updated",
+    OperationCancelled => "operation-cancelled",
+    ProfileCreated => "profile-created",
+    ProfileDeleted => "profile-deleted",
+    ProfileRenamed => "profile-renamed",
+    ProfileUpdated => "profile-updated",
+    ProjectCreated => "project-created",
+    ProjectDeleted => "project-deleted",
+    ProjectRenamed => "project-renamed",
+    ProjectUpdated => "project-updated",
+    StorageBucketCreated => "storage-bucket-created",
+    StorageBucketDeleted => "storage-bucket-deleted",
+    StorageBucketUpdated => "storage-bucket-updated",
+    StorageBucketKeyCreated => "storage-bucket-key-created",
+    StorageBucketKeyDeleted => "storage-bucket-key-deleted",
+    StorageBucketKeyUpdated => "storage-bucket-key-updated",
+    StoragePoolCreated => "storage-pool-created",
+    StoragePoolDeleted => "storage-pool-deleted",
+    StoragePoolUpdated => "storage-pool-updated",
+    StorageVolumeBackupCreated => "storage-volume-backup-created",
+    StorageVolumeBackupDeleted => "storage-volume-backup-deleted",
+    StorageVolumeBackupRenamed => "storage-volume-backup-renamed",
+    StorageVolumeBackupRetrieved => "storage-volume-backup-retrieved",
+    StorageVolumeCreated => "storage-volume-created",
+    StorageVolumeDeleted => "storage-volume-deleted",
+    StorageVolumeRenamed => "storage-volume-renamed",
+    StorageVolumeRestored => "storage-volume-restored",
+    StorageVolumeUpdated => "storage-volume-updated",
+    StorageVolumeSnapshotCreated => "storage-volume-snapshot-created",
+    StorageVolumeSnapshotDeleted => "storage-volume-snapshot-deleted",
+    StorageVolumeSnapshotRenamed => "storage-volume-snapshot-renamed",
+    StorageVolumeSnapshotUpdated => "storage-volume-snapshot-updated",
+    WarningAcknowledged => "warning-acknowledged",
+    WarningDeleted => "warning-deleted",
+    WarningReset => "warning-reset",
+}
+
+impl From<String> for LifecycleAction {
+    fn from(action: String) -> Self {
+        Self::from(action.as_str())
+    }
+}
+
+impl From<LifecycleAction> for String {
+    fn from(action: LifecycleAction) -> Self {
+        match action {
+            LifecycleAction::Other(action) => action,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LifecycleAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<&str> for LifecycleAction {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl EventPayload {
+    /// Decode `metadata` for an event of type `event_type`
+    pub fn decode(event_type: &str, metadata: Value) -> serde_json::Result<Self> {
+        Ok(match event_type {
+            "lifecycle" => Self::Lifecycle(serde_json::from_value(metadata)?),
+            "operation" => Self::Operation(Box::new(serde_json::from_value(metadata)?)),
+            "logging" => Self::Logging(serde_json::from_value(metadata)?),
+            other => Self::Other {
+                event_type: other.to_string(),
+                metadata,
+            },
+        })
+    }
+}
+
+#[cfg(not(feature = "generated"))]
+impl Event {
+    /// Decode the metadata according to the event type
+    pub fn payload(&self) -> serde_json::Result<EventPayload> {
+        EventPayload::decode(&self.event_type, self.metadata.clone())
+    }
+}
+
+#[cfg(feature = "generated")]
+impl Event {
+    /// Decode the metadata according to the event type
+    pub fn payload(&self) -> serde_json::Result<EventPayload> {
+        let metadata = self
+            .metadata
+            .clone()
+            .map_or(Value::Null, |map| Value::Object(map.into_iter().collect()));
+        EventPayload::decode(self.kind.as_deref().unwrap_or_default(), metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OperationStatus;
+
+    fn make_event(event_type: &str, metadata: Value) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "type": event_type,
+            "timestamp": "2024-01-01T00:00:00Z",
+            "metadata": metadata,
+            "location": "node1",
+            "project": "default"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lifecycle_payload() {
+        let event = make_event(
+            "lifecycle",
+            serde_json::json!({
+                "action": "instance-started",
+                "source": "/1.0/instances/c1",
+                "requestor": {"username": "root", "protocol": "unix", "address": "@"},
+                "context": {"command": ["bash"]}
+            }),
+        );
+
+        let EventPayload::Lifecycle(lifecycle) = event.payload().unwrap() else {
+            panic!("not a lifecycle event");
+        };
+        assert_eq!(lifecycle.action, LifecycleAction::InstanceStarted);
+        assert_eq!(lifecycle.source, "/1.0/instances/c1");
+        assert_eq!(lifecycle.requestor.unwrap().protocol, "unix");
+        assert!(lifecycle.context.unwrap().contains_key("command"));
+    }
+
+    #[test]
+    fn test_operation_and_logging_payloads() {
+        let event = make_event(
+            "operation",
+            serde_json::json!({"id": "abc", "class": "task", "status": "Running", "status_code": 103}),
+        );
+        let EventPayload::Operation(operation) = event.payload().unwrap() else {
+            panic!("not an operation event");
+        };
+        assert_eq!(operation.status(), OperationStatus::Running);
+
+        let event = make_event(
+            "logging",
+            serde_json::json!({"message": "Started", "level": "info", "context": {"name": "c1"}}),
+        );
+        assert_eq!(
+            event.payload().unwrap(),
+            EventPayload::Logging(EventLogging {
+                message: "Started".to_string(),
+                level: "info".to_string(),
+                context: BTreeMap::from([("name".to_string(), "c1".to_string())]),
+            })
+        );
+
+        let event = make_event("ovn", serde_json::json!({"x": 1}));
+        assert!(matches!(
+            event.payload().unwrap(),
+            EventPayload::Other { event_type, .. } if event_type == "ovn"
+        ));
+    }
+
+    #[test]
+    fn test_lifecycle_action_round_trip() {
+        let action: LifecycleAction = serde_json::from_str("\"storage-volume-created\"").unwrap();
+        assert_eq!(action, LifecycleAction::StorageVolumeCreated);
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            "\"storage-volume-created\""
+        );
+
+        let action = LifecycleAction::from("instance-teleported");
+        assert_eq!(
+            action,
+            LifecycleAction::Other("instance-teleported".to_string())
+        );
+        assert_eq!(action, "instance-teleported");
+    }
+}
@@ -0,0 +1,27 @@
+//! Event types for LXD API
+
+use serde::{Deserialize, Serialize};
+
+/// An event received from `/1.0/events`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Event type (operation, logging or lifecycle)
+    #[serde(rename = "type")]
+    pub event_type: String,
+
+    /// Time at which the event was sent
+    #[serde(default)]
+    pub timestamp: String,
+
+    /// Event metadata (a logging entry, lifecycle entry or operation)
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+
+    /// Originating cluster member
+    #[serde(default)]
+    pub location: String,
+
+    /// Project the event belongs to
+    #[serde(default)]
+    pub project: String,
+}
@@ -196,3 +196,70 @@ pub struct ImagePut {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<String>,
 }
+
+/// ImageMetadata represents the `metadata.yaml` of an image or instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ImageMetadata {
+    /// Architecture name
+    #[serde(default)]
+    pub architecture: String,
+
+    /// Image creation date (as UNIX epoch)
+    #[serde(default)]
+    pub creation_date: i64,
+
+    /// Image expiry date (as UNIX epoch)
+    #[serde(default)]
+    pub expiry_date: i64,
+
+    /// Descriptive properties
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+
+    /// Templates applied to files in the instance, by file path
+    #[serde(default)]
+    pub templates: BTreeMap<String, ImageMetadataTemplate>,
+}
+
+/// ImageMetadataTemplate represents a template entry in image metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ImageMetadataTemplate {
+    /// When to apply the template (`create`, `copy` or `start`)
+    #[serde(default)]
+    pub when: Vec<String>,
+
+    /// Whether to apply the template only if the file is missing
+    #[serde(default)]
+    pub create_only: bool,
+
+    /// Name of the template file
+    #[serde(default)]
+    pub template: String,
+
+    /// Key/value properties passed to the template
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+impl ImageMetadataTemplate {
+    /// Create a template entry applying `template` on the `when` triggers
+    pub fn new(template: impl Into<String>, when: &[&str]) -> Self {
+        Self {
+            template: template.into(),
+            when: when.iter().map(|trigger| trigger.to_string()).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Apply the template only if the file is missing
+    pub fn create_only(mut self, create_only: bool) -> Self {
+        self.create_only = create_only;
+        self
+    }
+
+    /// Add a property passed to the template
+    pub fn property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.insert(key.into(), value.into());
+        self
+    }
+}
@@ -197,62 +197,221 @@ pub struct InstanceSource {
     /// Source container name (for "copy" type)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+
+    /// Whether the source or the target opens the connection, `pull` or
+    /// `push` (for "migration" type)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+
+    /// URL of the source operation (for "migration" type in pull mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+
+    /// Certificate of the source server (for "migration" type in pull mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<String>,
+
+    /// Migration websocket secrets (for "migration" type in pull mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<BTreeMap<String, String>>,
+
+    /// Whether this is a live migration (for "migration" type)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live: Option<bool>,
+
+    /// Whether to leave out snapshots (for "copy" and "migration" types)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_only: Option<bool>,
+
+    /// Whether to update an existing copy with only what changed (for "copy"
+    /// and "migration" types)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh: Option<bool>,
+
+    /// Project of the source instance (for "copy" type)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
 }
 
 impl InstanceSource {
-    /// Create source from an image alias
-    pub fn from_image(alias: impl Into<String>) -> Self {
+    /// Create a source of `source_type` with nothing else set
+    fn of_type(source_type: &str) -> Self {
         Self {
-            source_type: "image".to_string(),
-            alias: Some(alias.into()),
+            source_type: source_type.to_string(),
+            alias: None,
             fingerprint: None,
             properties: None,
             server: None,
             protocol: None,
             source: None,
+            mode: None,
+            operation: None,
+            certificate: None,
+            secrets: None,
+            live: None,
+            instance_only: None,
+            refresh: None,
+            project: None,
+        }
+    }
+
+    /// Create source from an image alias
+    pub fn from_image(alias: impl Into<String>) -> Self {
+        Self {
+            alias: Some(alias.into()),
+            ..Self::of_type("image")
         }
     }
 
     /// Create source from a remote image
     pub fn from_remote_image(alias: impl Into<String>, server: impl Into<String>) -> Self {
         Self {
-            source_type: "image".to_string(),
             alias: Some(alias.into()),
-            fingerprint: None,
-            properties: None,
             server: Some(server.into()),
             protocol: Some("simplestreams".to_string()),
-            source: None,
+            ..Self::of_type("image")
         }
     }
 
-    /// Create an empty source (for empty instances)
-    pub fn none() -> Self {
+    /// Create source from an image fingerprint
+    pub fn from_fingerprint(fingerprint: impl Into<String>) -> Self {
         Self {
-            source_type: "none".to_string(),
-            alias: None,
-            fingerprint: None,
-            properties: None,
-            server: None,
-            protocol: None,
-            source: None,
+            fingerprint: Some(fingerprint.into()),
+            ..Self::of_type("image")
+        }
+    }
+
+    /// Create source from a remote image fingerprint
+    pub fn from_remote_fingerprint(
+        fingerprint: impl Into<String>,
+        server: impl Into<String>,
+    ) -> Self {
+        Self {
+            fingerprint: Some(fingerprint.into()),
+            server: Some(server.into()),
+            protocol: Some("simplestreams".to_string()),
+            ..Self::of_type("image")
         }
     }
 
+    /// Create an empty source (for empty instances)
+    pub fn none() -> Self {
+        Self::of_type("none")
+    }
+
     /// Create source from copying another instance
     pub fn from_copy(source_name: impl Into<String>) -> Self {
         Self {
-            source_type: "copy".to_string(),
-            alias: None,
-            fingerprint: None,
-            properties: None,
-            server: None,
-            protocol: None,
             source: Some(source_name.into()),
+            ..Self::of_type("copy")
+        }
+    }
+
+    /// Create source from copying a snapshot of another instance
+    pub fn from_snapshot(instance: &str, snapshot: &str) -> Self {
+        Self::from_copy(format!("{}/{}", instance, snapshot))
+    }
+
+    /// Create source for a migration from another server in `mode` (`pull` or `push`)
+    pub fn from_migration(mode: impl Into<String>) -> Self {
+        Self {
+            mode: Some(mode.into()),
+            ..Self::of_type("migration")
+        }
+    }
+
+    /// Leave out snapshots of the source
+    pub fn instance_only(mut self, instance_only: bool) -> Self {
+        self.instance_only = Some(instance_only);
+        self
+    }
+
+    /// Update an existing copy with only what changed since the last copy
+    pub fn refresh(mut self, refresh: bool) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    /// Copy from an instance in another project
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+}
+
+/// Request to rename, move or migrate an instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct InstancePost {
+    /// New instance name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Whether the instance is moving to another pool, project, cluster
+    /// member or server rather than just being renamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration: Option<bool>,
+
+    /// Whether to perform a live migration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live: Option<bool>,
+
+    /// Whether to leave snapshots behind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_only: Option<bool>,
+
+    /// Target storage pool for a move on the same server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<String>,
+
+    /// Target project for a move on the same server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+
+    /// Target server operation, for a migration in push mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<InstancePostTarget>,
+}
+
+impl InstancePost {
+    /// Create a rename request
+    pub fn rename(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::default()
         }
     }
 }
 
+/// Target of a migration in push mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct InstancePostTarget {
+    /// Certificate of the target server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<String>,
+
+    /// URL of the operation on the target server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+
+    /// Migration websocket secrets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<BTreeMap<String, String>>,
+}
+
+/// Request to rebuild an instance from a new root filesystem
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceRebuildPost {
+    /// Image to rebuild from, or `none` for an empty root filesystem
+    pub source: InstanceSource,
+}
+
+impl InstanceRebuildPost {
+    /// Create a rebuild request
+    pub fn new(source: InstanceSource) -> Self {
+        Self { source }
+    }
+}
+
 /// Request to update an instance
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct InstancePut {
@@ -445,6 +604,133 @@ pub struct InstanceStatePut {
     pub stateful: Option<bool>,
 }
 
+/// Request to change the state of every instance in a project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstancesPut {
+    /// Desired state
+    pub state: InstanceStatePut,
+}
+
+impl InstancesPut {
+    /// Create a bulk state change request
+    pub fn new(state: InstanceStatePut) -> Self {
+        Self { state }
+    }
+}
+
+/// Request to attach to an instance console
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceConsolePost {
+    /// Console height in rows (console type only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<i64>,
+
+    /// Type of console to attach to (console or vga)
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub console_type: Option<String>,
+
+    /// Console width in columns (console type only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<i64>,
+}
+
+/// UEFI variables of a virtual machine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct InstanceUEFIVars {
+    /// UEFI variables, keyed by `<uefi-variable-name>-<UUID>`
+    #[serde(default)]
+    pub variables: BTreeMap<String, InstanceUEFIVariable>,
+}
+
+/// A UEFI variable entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct InstanceUEFIVariable {
+    /// UEFI variable attributes
+    #[serde(default)]
+    pub attr: i64,
+
+    /// UEFI variable data (hex-encoded)
+    #[serde(default)]
+    pub data: String,
+
+    /// UEFI variable digest (hex-encoded)
+    #[serde(default)]
+    pub digest: String,
+
+    /// UEFI variable timestamp (hex-encoded)
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+/// Request to run a command in an instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceExecPost {
+    /// Command and its arguments
+    pub command: Vec<String>,
+
+    /// Current working directory for the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+
+    /// Additional environment to pass to the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<BTreeMap<String, String>>,
+
+    /// GID of the user to spawn the command as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<i64>,
+
+    /// Terminal height in rows (for interactive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<i64>,
+
+    /// Whether to spawn the command with a single PTY instead of 3 pipes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interactive: Option<bool>,
+
+    /// Whether to capture the output for later download (non-interactive only)
+    #[serde(
+        rename = "record-output",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub record_output: Option<bool>,
+
+    /// UID of the user to spawn the command as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<i64>,
+
+    /// Whether to wait for all websockets to be connected before spawning the command
+    #[serde(
+        rename = "wait-for-websocket",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub wait_for_websocket: Option<bool>,
+
+    /// Terminal width in characters (for interactive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<i64>,
+}
+
+impl InstanceExecPost {
+    /// Create a new exec request
+    pub fn new(command: Vec<String>) -> Self {
+        Self {
+            command,
+            cwd: None,
+            environment: None,
+            group: None,
+            height: None,
+            interactive: None,
+            record_output: None,
+            user: None,
+            wait_for_websocket: None,
+            width: None,
+        }
+    }
+}
+
 /// Instance action
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
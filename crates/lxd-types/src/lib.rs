@@ -10,6 +10,7 @@
 //! - Profiles and projects
 //! - Cluster configuration
 //! - Operations
+//! - Events
 //!
 //! ## Features
 //!
@@ -32,8 +33,12 @@ pub use generated::*;
 // Core types - only available when generated feature is NOT enabled
 // This avoids conflicts between hand-written and generated types
 #[cfg(not(feature = "generated"))]
+mod backups;
+#[cfg(not(feature = "generated"))]
 mod common;
 #[cfg(not(feature = "generated"))]
+mod events;
+#[cfg(not(feature = "generated"))]
 mod images;
 #[cfg(not(feature = "generated"))]
 mod instances;
@@ -50,9 +55,13 @@ mod snapshots;
 #[cfg(not(feature = "generated"))]
 mod storage;
 
+#[cfg(not(feature = "generated"))]
+pub use backups::*;
 #[cfg(not(feature = "generated"))]
 pub use common::*;
 #[cfg(not(feature = "generated"))]
+pub use events::*;
+#[cfg(not(feature = "generated"))]
 pub use images::*;
 #[cfg(not(feature = "generated"))]
 pub use instances::*;
@@ -72,3 +81,11 @@ pub use storage::*;
 // Response types are always available as they're not in the Swagger spec
 mod responses;
 pub use responses::*;
+
+// Typed operation status and class, shared by both Operation variants
+mod operation_status;
+pub use operation_status::*;
+
+// Typed event payloads, shared by both Event variants
+mod event_payload;
+pub use event_payload::*;
@@ -0,0 +1,239 @@
+//! Typed operation status and class
+//!
+//! These are shared by the hand-written and generated `Operation` types so
+//! callers can check an operation without parsing strings themselves.
+
+use crate::Operation;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Operation status
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum OperationStatus {
+    /// Operation is pending
+    Pending,
+    /// Operation is running
+    Running,
+    /// Operation completed successfully
+    Success,
+    /// Operation failed
+    Failure,
+    /// Operation is being cancelled
+    Cancelling,
+    /// Operation was cancelled
+    Cancelled,
+    /// A status not known to this version of the crate
+    Unknown(String),
+}
+
+impl OperationStatus {
+    /// Check if the operation is complete
+    pub fn is_complete(&self) -> bool {
+        matches!(self, Self::Success | Self::Failure | Self::Cancelled)
+    }
+
+    /// Check if the operation succeeded
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success)
+    }
+
+    /// Status name as sent by LXD
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Pending => "Pending",
+            Self::Running => "Running",
+            Self::Success => "Success",
+            Self::Failure => "Failure",
+            Self::Cancelling => "Cancelling",
+            Self::Cancelled => "Cancelled",
+            Self::Unknown(status) => status,
+        }
+    }
+}
+
+impl From<&str> for OperationStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "Pending" => Self::Pending,
+            "Running" => Self::Running,
+            "Success" => Self::Success,
+            "Failure" => Self::Failure,
+            "Cancelling" => Self::Cancelling,
+            "Cancelled" => Self::Cancelled,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for OperationStatus {
+    fn from(status: String) -> Self {
+        Self::from(status.as_str())
+    }
+}
+
+impl From<OperationStatus> for String {
+    fn from(status: OperationStatus) -> Self {
+        match status {
+            OperationStatus::Unknown(status) => status,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for OperationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<&str> for OperationStatus {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// Operation class
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum OperationClass {
+    /// Task operation (normal background operation)
+    Task,
+    /// Token operation (one-time use)
+    Token,
+    /// WebSocket operation (interactive)
+    Websocket,
+    /// A class not known to this version of the crate
+    Unknown(String),
+}
+
+impl OperationClass {
+    /// Class name as sent by LXD
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Task => "task",
+            Self::Token => "token",
+            Self::Websocket => "websocket",
+            Self::Unknown(class) => class,
+        }
+    }
+}
+
+impl From<&str> for OperationClass {
+    fn from(class: &str) -> Self {
+        match class {
+            "task" => Self::Task,
+            "token" => Self::Token,
+            "websocket" => Self::Websocket,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for OperationClass {
+    fn from(class: String) -> Self {
+        Self::from(class.as_str())
+    }
+}
+
+impl From<OperationClass> for String {
+    fn from(class: OperationClass) -> Self {
+        match class {
+            OperationClass::Unknown(class) => class,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for OperationClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<&str> for OperationClass {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(not(feature = "generated"))]
+impl Operation {
+    /// Operation status
+    pub fn status(&self) -> OperationStatus {
+        self.status.clone()
+    }
+
+    /// Operation class
+    pub fn class(&self) -> OperationClass {
+        self.class.clone()
+    }
+}
+
+#[cfg(feature = "generated")]
+impl Operation {
+    /// Operation status, `Unknown("")` if missing
+    pub fn status(&self) -> OperationStatus {
+        OperationStatus::from(self.status.as_deref().unwrap_or_default())
+    }
+
+    /// Operation class, `Unknown("")` if missing
+    pub fn class(&self) -> OperationClass {
+        OperationClass::from(self.class.as_deref().unwrap_or_default())
+    }
+}
+
+impl Operation {
+    /// Check if the operation has finished (succeeded, failed or was cancelled)
+    pub fn is_complete(&self) -> bool {
+        self.status().is_complete()
+    }
+
+    /// Check if the operation succeeded
+    pub fn is_success(&self) -> bool {
+        self.status().is_success()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trip() {
+        let status: OperationStatus = serde_json::from_str("\"Running\"").unwrap();
+        assert_eq!(status, OperationStatus::Running);
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"Running\"");
+
+        let status: OperationStatus = serde_json::from_str("\"Paused\"").unwrap();
+        assert_eq!(status, OperationStatus::Unknown("Paused".to_string()));
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"Paused\"");
+        assert!(!status.is_complete());
+    }
+
+    #[test]
+    fn test_class_round_trip() {
+        let class: OperationClass = serde_json::from_str("\"websocket\"").unwrap();
+        assert_eq!(class, OperationClass::Websocket);
+        assert_eq!(class, "websocket");
+
+        let class: OperationClass = serde_json::from_str("\"stream\"").unwrap();
+        assert_eq!(class, OperationClass::Unknown("stream".to_string()));
+    }
+
+    #[test]
+    fn test_operation_accessors() {
+        let operation: Operation = serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "class": "task",
+            "status": "Failure",
+            "status_code": 400
+        }))
+        .unwrap();
+
+        assert_eq!(operation.class(), OperationClass::Task);
+        assert_eq!(operation.status(), OperationStatus::Failure);
+        assert!(operation.is_complete());
+        assert!(!operation.is_success());
+    }
+}
@@ -1,5 +1,6 @@
 //! Operation types for LXD API
 
+use crate::{OperationClass, OperationStatus};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -10,7 +11,7 @@ pub struct Operation {
     pub id: String,
 
     /// Operation class (task, token, websocket)
-    pub class: String,
+    pub class: OperationClass,
 
     /// Operation description
     #[serde(default)]
@@ -25,7 +26,7 @@ pub struct Operation {
     pub updated_at: String,
 
     /// Operation status
-    pub status: String,
+    pub status: OperationStatus,
 
     /// Status code
     pub status_code: i64,
@@ -50,47 +51,3 @@ pub struct Operation {
     #[serde(default)]
     pub location: String,
 }
-
-/// Operation status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub enum OperationStatus {
-    /// Operation is pending
-    #[serde(rename = "Pending")]
-    Pending,
-    /// Operation is running
-    #[serde(rename = "Running")]
-    Running,
-    /// Operation completed successfully
-    #[serde(rename = "Success")]
-    Success,
-    /// Operation failed
-    #[serde(rename = "Failure")]
-    Failure,
-    /// Operation was cancelled
-    #[serde(rename = "Cancelled")]
-    Cancelled,
-}
-
-impl OperationStatus {
-    /// Check if the operation is complete
-    pub fn is_complete(&self) -> bool {
-        matches!(self, Self::Success | Self::Failure | Self::Cancelled)
-    }
-
-    /// Check if the operation succeeded
-    pub fn is_success(&self) -> bool {
-        matches!(self, Self::Success)
-    }
-}
-
-/// Operation class
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum OperationClass {
-    /// Task operation (normal background operation)
-    Task,
-    /// Token operation (one-time use)
-    Token,
-    /// WebSocket operation (interactive)
-    Websocket,
-}
@@ -124,6 +124,10 @@ pub struct Server {
 /// Server environment information
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ServerEnvironment {
+    /// Addresses the server listens on
+    #[serde(default)]
+    pub addresses: Vec<String>,
+
     /// Architecture
     #[serde(default)]
     pub architecture: String,
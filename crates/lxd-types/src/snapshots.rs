@@ -1,6 +1,7 @@
 //! Instance snapshot types for LXD API
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// InstanceSnapshot represents an instance snapshot
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -23,6 +24,26 @@ pub struct InstanceSnapshot {
     /// Size of the snapshot in bytes
     #[serde(default)]
     pub size: i64,
+
+    /// Architecture at the time of the snapshot
+    #[serde(default)]
+    pub architecture: String,
+
+    /// Whether the instance was ephemeral
+    #[serde(default)]
+    pub ephemeral: bool,
+
+    /// Instance configuration at the time of the snapshot
+    #[serde(default)]
+    pub config: BTreeMap<String, String>,
+
+    /// Instance devices at the time of the snapshot
+    #[serde(default)]
+    pub devices: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// Profiles applied at the time of the snapshot
+    #[serde(default)]
+    pub profiles: Vec<String>,
 }
 
 /// Request to create an instance snapshot
@@ -34,10 +34,11 @@
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     let client = Client::new_https(
+//!     let client = Client::new_https_with_verification(
 //!         "https://lxd.example.com:8443",
 //!         "/path/to/client.crt",
 //!         "/path/to/client.key",
+//!         ServerVerification::pinned_certificate_file("/path/to/server.crt")?,
 //!     )?;
 //!     
 //!     let server = client.get_server().await?;
@@ -64,7 +65,13 @@ pub mod client {
 }
 
 pub use lxd_client::{
-    Client, ClientBuilder, ClientConfig, Error as ClientError, Result as ClientResult, Transport,
+    BackupImportOptions, BearerAuth, ByteStream, Client, ClientBuilder, ClientConfig,
+    ClientIdentity, ConsoleSession, CopyOptions, Error as ClientError, EventFilter, EventHub,
+    EventStream, EventType, ExecControl, ExecOutput, ExecRecord, FileMetadata, FileOptions,
+    FileType, FileWriteMode, InstanceFile, MigrationMode, MigrationOptions, MoveOptions,
+    OperationHandle, PtySession, Remotes, Result as ClientResult, ServerVerification, SftpSession,
+    StateChangeOptions, StateChangeResult, TransferOptions, TransferProgress, TransferSummary,
+    Transport, UefiVariable, UefiVars,
 };
 
 /// Prelude module for convenient imports
@@ -74,7 +81,10 @@ pub use lxd_client::{
 /// ```
 pub mod prelude {
     pub use lxd_client::{
-        Client, ClientBuilder, ClientConfig, Error as ClientError, Result as ClientResult,
+        BackupImportOptions, Client, ClientBuilder, ClientConfig, ClientIdentity, CopyOptions,
+        Error as ClientError, EventFilter, EventType, FileOptions, MigrationMode, MigrationOptions,
+        MoveOptions, OperationHandle, Remotes, Result as ClientResult, ServerVerification,
+        StateChangeOptions, StateChangeResult, TransferOptions, UefiVariable, UefiVars,
     };
     pub use lxd_types::*;
 }